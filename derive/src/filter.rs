@@ -13,6 +13,8 @@ fn kaldav() -> proc_macro2::TokenStream {
 }
 
 pub(crate) fn impl_macro(ast: &Map) -> syn::Result<proc_macro2::TokenStream> {
+    ast.validate()?;
+
     let body = quote::quote! {
         #ast
     };
@@ -20,53 +22,140 @@ pub(crate) fn impl_macro(ast: &Map) -> syn::Result<proc_macro2::TokenStream> {
     Ok(body)
 }
 
+/**
+ * The top-level comp-filters, each one is an alternative.
+ */
 #[derive(Clone, Debug)]
-pub(crate) struct Map(Filter);
+pub(crate) struct Map(Vec<Filter>);
+
+impl Map {
+    fn validate(&self) -> syn::Result<()> {
+        self.0.iter().try_for_each(|x| x.validate(Kind::Filter))
+    }
+}
 
 impl syn::parse::Parse for Map {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        input.parse().map(Self)
+        let mut filters = Vec::new();
+
+        while !input.is_empty() {
+            filters.push(input.parse()?);
+        }
+
+        if filters.is_empty() {
+            return Err(input.error("expected at least one `CompFilter`"));
+        }
+
+        Ok(Self(filters))
     }
 }
 
 impl quote::ToTokens for Map {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let kaldav = kaldav();
-        let filter = &self.0;
+        let filters = &self.0;
 
         let body = quote::quote! {{
             use #kaldav::elements::*;
             use #kaldav::elements::filter::*;
 
             Filter::new()
-            #filter
+            #( #filters )*
         }};
 
         tokens.extend(body);
     }
 }
 
+/**
+ * A builder method call, `label: value { children }`. The label defaults to `append`.
+ */
 #[derive(Clone, Debug)]
 struct Filter {
-    name: syn::Ident,
+    label: Option<syn::Ident>,
     me: Arg,
     children: Vec<Self>,
 }
 
+impl Filter {
+    fn method(&self) -> String {
+        self.label
+            .as_ref()
+            .map_or_else(|| "append".to_string(), ToString::to_string)
+    }
+
+    /**
+     * Checks the method exists on `parent` and receives the right type, then validates the
+     * children.
+     */
+    fn validate(&self, parent: Kind) -> syn::Result<()> {
+        let method = self.method();
+
+        let Some((_, expected)) = parent.children().iter().find(|(x, _)| *x == method) else {
+            let error = match &self.label {
+                Some(label) => syn::Error::new(
+                    label.span(),
+                    format!(
+                        "unknown `{label}` for `{parent}`, expected {}",
+                        parent.expected()
+                    ),
+                ),
+                None => syn::Error::new_spanned(
+                    &self.me,
+                    format!(
+                        "`{parent}` doesn’t accept this child, expected {}",
+                        parent.expected()
+                    ),
+                ),
+            };
+
+            return Err(error);
+        };
+
+        let kind = self.me.kind();
+
+        if let (Some(expected), Some(kind)) = (expected, kind)
+            && *expected != kind
+        {
+            return Err(syn::Error::new_spanned(
+                &self.me,
+                format!("expected `{expected}`, found `{kind}`"),
+            ));
+        }
+
+        let Some(kind) = kind.or(*expected) else {
+            return match self.children.first() {
+                Some(child) => Err(syn::Error::new_spanned(
+                    &child.me,
+                    format!("`{method}` takes a value, not children"),
+                )),
+                None => Ok(()),
+            };
+        };
+
+        self.me.validate_name(kind)?;
+
+        self.children.iter().try_for_each(|x| x.validate(kind))
+    }
+}
+
 impl syn::parse::Parse for Filter {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let name = if input.peek2(syn::Token![:]) && input.peek3(syn::Token![:]) {
-            proc_macro2::Ident::new("append", proc_macro2::Span::call_site())
-        } else {
-            let name = input.parse::<syn::Ident>()?;
+        let label = if input.peek(syn::Ident)
+            && input.peek2(syn::Token![:])
+            && !input.peek2(syn::Token![::])
+        {
+            let label = input.parse::<syn::Ident>()?;
 
             input.parse::<syn::Token![:]>()?;
 
-            name
+            Some(label)
+        } else {
+            None
         };
 
         let mut filter = Self {
-            name,
+            label,
             me: input.parse()?,
             children: Vec::new(),
         };
@@ -75,12 +164,14 @@ impl syn::parse::Parse for Filter {
             let content;
             syn::braced!(content in input);
 
-            if !content.is_empty() {
+            while !content.is_empty() {
                 filter.children.push(content.parse()?);
             }
         }
 
-        input.parse::<syn::Token![,]>().ok();
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+        }
 
         Ok(filter)
     }
@@ -88,7 +179,12 @@ impl syn::parse::Parse for Filter {
 
 impl quote::ToTokens for Filter {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let name = &self.name;
+        let name = proc_macro2::Ident::new(
+            &self.method(),
+            self.label
+                .as_ref()
+                .map_or_else(proc_macro2::Span::call_site, syn::Ident::span),
+        );
         let me = &self.me;
         let args = &self.children;
 
@@ -117,8 +213,63 @@ enum Arg {
     },
 }
 
+impl Arg {
+    /**
+     * The type built by this argument, when it’s visible like in `CompFilter::new("VEVENT")`.
+     */
+    fn kind(&self) -> Option<Kind> {
+        match self {
+            Self::Function { ty, .. } if ty.segments.len() == 2 => {
+                Kind::from_ident(&ty.segments[0].ident)
+            }
+            Self::Struct { ty, .. } => Kind::from_ident(ty),
+            _ => None,
+        }
+    }
+
+    /**
+     * Checks the syntax of the literal name given to `new`: an iana-token or an x-name, the
+     * registered names aren’t listed as new ones keep being added.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.1>
+     */
+    fn validate_name(&self, kind: Kind) -> syn::Result<()> {
+        let Self::Function { ty, arg } = self else {
+            return Ok(());
+        };
+
+        let (true, Some(constructor), Ok(name)) = (
+            kind.is_named(),
+            ty.segments.last(),
+            syn::parse2::<syn::LitStr>(arg.clone()),
+        ) else {
+            return Ok(());
+        };
+
+        let value = name.value();
+
+        if constructor.ident != "new"
+            || (!value.is_empty() && value.chars().all(|x| x.is_ascii_alphanumeric() || x == '-'))
+        {
+            return Ok(());
+        }
+
+        Err(syn::Error::new(
+            name.span(),
+            format!(
+                "invalid iCalendar {} name `{value}`, expected letters, digits and `-`",
+                kind.description(),
+            ),
+        ))
+    }
+}
+
 impl syn::parse::Parse for Arg {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Err(input.error("expected a value"));
+        }
+
         let arg = if input.peek(syn::Lit) {
             Self::Lit(input.parse::<syn::Lit>()?)
         } else if input.peek2(syn::token::Brace) {
@@ -166,3 +317,228 @@ impl quote::ToTokens for Arg {
         tokens.extend(body);
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Filter,
+    CompFilter,
+    PropFilter,
+    ParamFilter,
+    TextMatch,
+    TimeRange,
+}
+
+impl Kind {
+    fn from_ident(ident: &syn::Ident) -> Option<Self> {
+        let kind = match ident.to_string().as_str() {
+            "CompFilter" => Self::CompFilter,
+            "PropFilter" => Self::PropFilter,
+            "ParamFilter" => Self::ParamFilter,
+            "TextMatch" => Self::TextMatch,
+            "TimeRange" => Self::TimeRange,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /**
+     * The builder methods of this type, with the type of their argument when it’s a filter.
+     */
+    fn children(self) -> &'static [(&'static str, Option<Self>)] {
+        match self {
+            Self::Filter => &[("append", Some(Self::CompFilter))],
+            Self::CompFilter => &[
+                ("append", Some(Self::CompFilter)),
+                ("is_not_defined", None),
+                ("prop_filter", Some(Self::PropFilter)),
+                ("test", None),
+                ("time_range", Some(Self::TimeRange)),
+            ],
+            Self::PropFilter => &[
+                ("append", Some(Self::ParamFilter)),
+                ("is_not_defined", None),
+                ("test", None),
+                ("text_match", Some(Self::TextMatch)),
+                ("time_range", Some(Self::TimeRange)),
+            ],
+            Self::ParamFilter => &[("append", Some(Self::TextMatch)), ("is_not_defined", None)],
+            Self::TextMatch => &[
+                ("collation", None),
+                ("negate_condition", None),
+                ("text", None),
+            ],
+            Self::TimeRange => &[],
+        }
+    }
+
+    fn expected(self) -> String {
+        let mut expected = self
+            .children()
+            .iter()
+            .map(|(method, kind)| match (method, kind) {
+                (&"append", Some(kind)) => format!("a `{kind}`"),
+                _ => format!("`{method}:`"),
+            })
+            .collect::<Vec<_>>();
+
+        match expected.pop() {
+            None => "no children".to_string(),
+            Some(last) if expected.is_empty() => last,
+            Some(last) => format!("{} or {last}", expected.join(", ")),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::CompFilter => "component",
+            Self::PropFilter => "property",
+            Self::ParamFilter => "parameter",
+            _ => "name",
+        }
+    }
+
+    /**
+     * Whether the filter is built from an iCalendar name.
+     */
+    fn is_named(self) -> bool {
+        matches!(
+            self,
+            Self::CompFilter | Self::PropFilter | Self::ParamFilter
+        )
+    }
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Filter => "filter!",
+            Self::CompFilter => "CompFilter",
+            Self::PropFilter => "PropFilter",
+            Self::ParamFilter => "ParamFilter",
+            Self::TextMatch => "TextMatch",
+            Self::TimeRange => "TimeRange",
+        };
+
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn error(input: &str) -> String {
+        syn::parse_str::<super::Map>(input)
+            .and_then(|x| super::impl_macro(&x))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn valid() {
+        let map = syn::parse_str::<super::Map>(
+            r#"
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    test: Test::AnyOf,
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        text_match: TextMatch::new(text) {
+                            collation: "i;octet",
+                        },
+                    },
+                    prop_filter: PropFilter::new("X-KALDAV") {
+                        ParamFilter::new("partstat") {
+                            is_not_defined: true,
+                        }
+                    },
+                    time_range: TimeRange { start: None, end: None },
+                }
+            },
+            CompFilter::new("VCALENDAR") {}
+            "#,
+        )
+        .unwrap();
+
+        assert!(map.validate().is_ok());
+        assert_eq!(map.0.len(), 2);
+        assert_eq!(map.0[0].children[0].children.len(), 4);
+    }
+
+    #[test]
+    fn unknown_method() {
+        assert_eq!(
+            error(r#"CompFilter::new("VEVENT") { prop_filtr: PropFilter::new("UID") }"#),
+            "unknown `prop_filtr` for `CompFilter`, expected a `CompFilter`, `is_not_defined:`, `prop_filter:`, `test:` or `time_range:`",
+        );
+    }
+
+    #[test]
+    fn nesting() {
+        assert_eq!(
+            error(r#"CompFilter::new("VEVENT") { PropFilter::new("UID") }"#),
+            "expected `CompFilter`, found `PropFilter`",
+        );
+        assert_eq!(
+            error(r#"PropFilter::new("UID")"#),
+            "expected `CompFilter`, found `PropFilter`",
+        );
+        assert_eq!(
+            error(
+                r#"CompFilter::new("VEVENT") { prop_filter: PropFilter::new("UID") { prop_filter: PropFilter::new("UID") } }"#
+            ),
+            "unknown `prop_filter` for `PropFilter`, expected a `ParamFilter`, `is_not_defined:`, `test:`, `text_match:` or `time_range:`",
+        );
+        assert_eq!(
+            error(
+                r#"CompFilter::new("VEVENT") { is_not_defined: true { CompFilter::new("VTODO") } }"#
+            ),
+            "`is_not_defined` takes a value, not children",
+        );
+    }
+
+    #[test]
+    fn names() {
+        let map = syn::parse_str::<super::Map>(
+            r#"
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VLOCATION") {
+                    prop_filter: PropFilter::new("STYLED-DESCRIPTION") {
+                        ParamFilter::new("derived") {
+                            is_not_defined: true,
+                        }
+                    },
+                    prop_filter: PropFilter::new("LOCATION-TYPE"),
+                },
+                CompFilter::new("PARTICIPANT") {
+                    prop_filter: PropFilter::new("CALENDAR-ADDRESS") {
+                        ParamFilter::new("ORDER"),
+                        ParamFilter::new("X-KALDAV"),
+                    },
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        assert!(map.validate().is_ok());
+
+        assert_eq!(
+            error(r#"CompFilter::new("V EVENT")"#),
+            "invalid iCalendar component name `V EVENT`, expected letters, digits and `-`",
+        );
+        assert_eq!(
+            error(r#"CompFilter::new("VEVENT") { prop_filter: PropFilter::new("") }"#),
+            "invalid iCalendar property name ``, expected letters, digits and `-`",
+        );
+    }
+
+    #[test]
+    fn syntax() {
+        assert_eq!(
+            error(""),
+            "unexpected end of input, expected at least one `CompFilter`"
+        );
+        assert_eq!(
+            error(r#"CompFilter::new("VEVENT") CompFilter::new("VTODO")"#),
+            "expected `,`",
+        );
+    }
+}
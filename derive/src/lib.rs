@@ -12,7 +12,7 @@ pub fn object_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 
 #[proc_macro]
 pub fn filter(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let ast = syn::parse(input).unwrap();
+    let ast = syn::parse_macro_input!(input as filter::Map);
 
     filter::impl_macro(&ast)
         .unwrap_or_else(syn::Error::into_compile_error)
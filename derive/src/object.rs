@@ -28,12 +28,12 @@ pub fn impl_macro(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStrea
     let traits = quote::quote! {
         #[automatically_derived]
         impl #impl_generics crate::Requestable for #name #ty_generics #where_clause {
-            fn auth(&self) -> Option<crate::Authorization> {
-                self.auth.clone()
+            fn session(&self) -> &crate::Session {
+                &self.session
             }
 
-            fn set_auth(&mut self, auth: Option<crate::Authorization>) {
-                self.auth = auth;
+            fn set_session(&mut self, session: crate::Session) {
+                self.session = session;
             }
         }
 
@@ -17,10 +17,10 @@ fn main() -> kaldav::Result {
     let mut client = kaldav::Client::new(opt.url);
 
     if let Some(username) = opt.username {
-        client.set_auth(Some(kaldav::Authorization {
-            username,
-            password: opt.password,
-        }));
+        client.set_auth(Some(kaldav::Auth::basic(
+            &username,
+            opt.password.as_deref(),
+        )));
     }
 
     let calendars = client.calendars()?;
@@ -0,0 +1,766 @@
+use std::sync::{Arc, RwLock};
+
+/**
+ * A sensitive string: hidden from `Debug` output and wiped from memory on drop.
+ */
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        use zeroize::Zeroize as _;
+
+        self.0.zeroize();
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+/**
+ * Credentials for the HTTP basic authentication scheme.
+ */
+#[derive(Clone, Debug)]
+pub struct Authorization {
+    pub username: String,
+    pub password: Option<Secret>,
+}
+
+impl Provider for Authorization {
+    fn header(&self, _: &str, _: &str) -> crate::Result<Option<(String, String)>> {
+        use base64::Engine as _;
+
+        let credentials = Secret(format!(
+            "{}:{}",
+            self.username,
+            self.password
+                .as_ref()
+                .map(Secret::expose)
+                .unwrap_or_default()
+        ));
+        let value = format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials.expose())
+        );
+
+        Ok(Some(("Authorization".to_string(), value)))
+    }
+}
+
+/**
+ * A source of credentials.
+ *
+ * Implement this trait to plug your own authentication scheme.
+ */
+pub trait Provider: Send + Sync + std::fmt::Debug {
+    /**
+     * Returns the header to add to a `method` request on `href`.
+     */
+    fn header(&self, method: &str, href: &str) -> crate::Result<Option<(String, String)>>;
+
+    /**
+     * Called when the server responds `401 Unauthorized`, with the content of its
+     * `WWW-Authenticate` headers.
+     *
+     * Returns `true` if the credentials were renewed, in which case the request is sent again
+     * once.
+     */
+    fn refresh(&self, _challenge: &str) -> crate::Result<bool> {
+        Ok(false)
+    }
+}
+
+type RefreshFn = dyn Fn() -> crate::Result<Secret> + Send + Sync;
+
+/**
+ * OAuth2 bearer token, with an optional refresh callback.
+ *
+ * The token is shared between all clones, so a refresh triggered by a child calendar is seen by
+ * the whole tree.
+ */
+#[derive(Clone)]
+pub struct Bearer {
+    token: Arc<RwLock<Secret>>,
+    refresh: Option<Arc<RefreshFn>>,
+}
+
+impl Bearer {
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: Arc::new(RwLock::new(Secret::new(token))),
+            refresh: None,
+        }
+    }
+
+    /**
+     * Sets the callback returning a new token when the current one is rejected.
+     */
+    pub fn with_refresh<F>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> crate::Result<Secret> + Send + Sync + 'static,
+    {
+        self.refresh = Some(Arc::new(refresh));
+
+        self
+    }
+
+    pub fn token(&self) -> Secret {
+        self.token.read().unwrap().clone()
+    }
+
+    pub fn set_token(&self, token: &str) {
+        *self.token.write().unwrap() = Secret::new(token);
+    }
+}
+
+impl std::fmt::Debug for Bearer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Bearer")
+            .field("token", &self.token.read().unwrap())
+            .field("refresh", &self.refresh.is_some())
+            .finish()
+    }
+}
+
+impl Provider for Bearer {
+    fn header(&self, _: &str, _: &str) -> crate::Result<Option<(String, String)>> {
+        Ok(Some((
+            "Authorization".to_string(),
+            format!("Bearer {}", self.token().expose()),
+        )))
+    }
+
+    fn refresh(&self, _: &str) -> crate::Result<bool> {
+        let Some(refresh) = &self.refresh else {
+            return Ok(false);
+        };
+
+        self.set_token(refresh()?.expose());
+
+        Ok(true)
+    }
+}
+
+/**
+ * Authentication method used for every request.
+ */
+#[derive(Clone, Debug)]
+pub enum Auth {
+    /** <https://datatracker.ietf.org/doc/html/rfc7617> */
+    Basic(Authorization),
+    /** <https://datatracker.ietf.org/doc/html/rfc6750> */
+    Bearer(Bearer),
+    /** <https://datatracker.ietf.org/doc/html/rfc7616> */
+    Digest(Digest),
+    /** An arbitrary header, like an API key. */
+    Header {
+        name: String,
+        value: Secret,
+    },
+    Custom(Arc<dyn Provider>),
+}
+
+impl Auth {
+    pub fn basic(username: &str, password: Option<&str>) -> Self {
+        Self::Basic(Authorization {
+            username: username.to_string(),
+            password: password.map(Secret::new),
+        })
+    }
+
+    pub fn bearer(token: &str) -> Self {
+        Self::Bearer(Bearer::new(token))
+    }
+
+    pub fn digest(username: &str, password: &str) -> Self {
+        Self::Digest(Digest::new(username, password))
+    }
+
+    pub fn header(name: &str, value: &str) -> Self {
+        Self::Header {
+            name: name.to_string(),
+            value: Secret::new(value),
+        }
+    }
+
+    pub fn custom<P: Provider + 'static>(provider: P) -> Self {
+        Self::Custom(Arc::new(provider))
+    }
+
+    fn provider(&self) -> Option<&dyn Provider> {
+        match self {
+            Self::Basic(basic) => Some(basic),
+            Self::Bearer(bearer) => Some(bearer),
+            Self::Digest(digest) => Some(digest),
+            Self::Header { .. } => None,
+            Self::Custom(provider) => Some(provider.as_ref()),
+        }
+    }
+}
+
+impl Provider for Auth {
+    fn header(&self, method: &str, href: &str) -> crate::Result<Option<(String, String)>> {
+        match self {
+            Self::Header { name, value } => Ok(Some((name.clone(), value.expose().to_string()))),
+            _ => self.provider().map_or(Ok(None), |x| x.header(method, href)),
+        }
+    }
+
+    fn refresh(&self, challenge: &str) -> crate::Result<bool> {
+        self.provider().map_or(Ok(false), |x| x.refresh(challenge))
+    }
+}
+
+impl From<Authorization> for Auth {
+    fn from(value: Authorization) -> Self {
+        Self::Basic(value)
+    }
+}
+
+impl From<Bearer> for Auth {
+    fn from(value: Bearer) -> Self {
+        Self::Bearer(value)
+    }
+}
+
+impl From<Digest> for Auth {
+    fn from(value: Digest) -> Self {
+        Self::Digest(value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl Algorithm {
+    fn hash(&self, data: &str) -> String {
+        use sha2::Digest as _;
+
+        match self {
+            Self::Md5 | Self::Md5Sess => format!("{:x}", md5::Md5::digest(data)),
+            Self::Sha256 | Self::Sha256Sess => format!("{:x}", sha2::Sha256::digest(data)),
+        }
+    }
+
+    fn is_session(&self) -> bool {
+        matches!(self, Self::Md5Sess | Self::Sha256Sess)
+    }
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        };
+
+        f.write_str(s)
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let algorithm = match s.to_ascii_uppercase().as_str() {
+            "MD5" => Self::Md5,
+            "MD5-SESS" => Self::Md5Sess,
+            "SHA-256" => Self::Sha256,
+            "SHA-256-SESS" => Self::Sha256Sess,
+            _ => {
+                return Err(crate::Error::Misc(format!(
+                    "Unsupported digest algorithm: {s}"
+                )));
+            }
+        };
+
+        Ok(algorithm)
+    }
+}
+
+/**
+ * Parameters of a `Digest` challenge sent by the server.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    qop: bool,
+    stale: bool,
+}
+
+impl Challenge {
+    /**
+     * Finds the best supported digest challenge in the content of `WWW-Authenticate` headers.
+     */
+    fn parse(header: &str) -> Option<Self> {
+        let mut challenges = Vec::new();
+
+        for part in header.split("Digest ").skip(1) {
+            let params = parse_params(part);
+
+            let Some(nonce) = params.get("nonce") else {
+                continue;
+            };
+
+            let algorithm = match params.get("algorithm") {
+                Some(algorithm) => match algorithm.parse() {
+                    Ok(algorithm) => algorithm,
+                    Err(_) => continue,
+                },
+                None => Algorithm::Md5,
+            };
+
+            let qop = match params.get("qop") {
+                Some(qop) => {
+                    if !qop.split(',').any(|x| x.trim() == "auth") {
+                        continue;
+                    }
+
+                    true
+                }
+                None => false,
+            };
+
+            challenges.push(Self {
+                realm: params.get("realm").cloned().unwrap_or_default(),
+                nonce: nonce.clone(),
+                opaque: params.get("opaque").cloned(),
+                algorithm,
+                qop,
+                stale: params
+                    .get("stale")
+                    .is_some_and(|x| x.eq_ignore_ascii_case("true")),
+            });
+        }
+
+        challenges
+            .iter()
+            .find(|x| matches!(x.algorithm, Algorithm::Sha256 | Algorithm::Sha256Sess))
+            .or(challenges.first())
+            .cloned()
+    }
+}
+
+/**
+ * Parses the `key=value` or `key="value"` list of an authentication challenge.
+ */
+fn parse_params(input: &str) -> std::collections::BTreeMap<String, String> {
+    let mut params = std::collections::BTreeMap::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.next_if(|x| *x == ',' || x.is_whitespace()).is_some() {}
+
+        let key =
+            std::iter::from_fn(|| chars.next_if(|x| *x != '=' && *x != ',')).collect::<String>();
+        let key = key.trim().to_ascii_lowercase();
+
+        if key.is_empty() || chars.next() != Some('=') {
+            break;
+        }
+
+        // Another scheme starts, like in `Digest realm="a", Basic realm="b"`
+        if key.contains(' ') {
+            break;
+        }
+
+        let value = if chars.next_if_eq(&'"').is_some() {
+            let mut value = String::new();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+
+            value
+        } else {
+            std::iter::from_fn(|| chars.next_if(|x| *x != ','))
+                .collect::<String>()
+                .trim()
+                .to_string()
+        };
+
+        params.insert(key, value);
+    }
+
+    params
+}
+
+/**
+ * A quoted-string, with `"` and `\` escaped.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc7616#section-3.4>
+ */
+fn quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+#[derive(Debug)]
+struct DigestState {
+    challenge: Challenge,
+    nc: u32,
+}
+
+/**
+ * HTTP digest authentication, with `MD5` and `SHA-256` algorithms and `qop=auth`.
+ *
+ * Nonce sent by the server is kept and reused by subsequent requests, from this client and all
+ * objects derived from it.
+ */
+#[derive(Clone)]
+pub struct Digest {
+    username: String,
+    password: Secret,
+    state: Arc<std::sync::Mutex<Option<DigestState>>>,
+}
+
+impl Digest {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            password: Secret::new(password),
+            state: Arc::default(),
+        }
+    }
+
+    fn response(
+        &self,
+        challenge: &Challenge,
+        method: &str,
+        uri: &str,
+        nc: u32,
+        cnonce: &str,
+    ) -> String {
+        let algorithm = challenge.algorithm;
+
+        let mut ha1 = algorithm.hash(&format!(
+            "{}:{}:{}",
+            self.username,
+            challenge.realm,
+            self.password.expose()
+        ));
+        if algorithm.is_session() {
+            ha1 = algorithm.hash(&format!("{ha1}:{}:{cnonce}", challenge.nonce));
+        }
+
+        let ha2 = algorithm.hash(&format!("{method}:{uri}"));
+
+        if challenge.qop {
+            algorithm.hash(&format!(
+                "{ha1}:{}:{nc:08x}:{cnonce}:auth:{ha2}",
+                challenge.nonce
+            ))
+        } else {
+            algorithm.hash(&format!("{ha1}:{}:{ha2}", challenge.nonce))
+        }
+    }
+}
+
+impl std::fmt::Debug for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Digest")
+            .field("username", &self.username)
+            .field("password", &self.password)
+            .finish()
+    }
+}
+
+impl Provider for Digest {
+    fn header(&self, method: &str, href: &str) -> crate::Result<Option<(String, String)>> {
+        let mut state = self.state.lock().unwrap();
+
+        let Some(state) = state.as_mut() else {
+            return Ok(None);
+        };
+
+        state.nc += 1;
+
+        let uri = match url::Url::parse(href) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().to_string(),
+            },
+            Err(_) => href.to_string(),
+        };
+        let cnonce = uuid::Uuid::now_v7().simple().to_string();
+        let challenge = &state.challenge;
+        let response = self.response(challenge, method, &uri, state.nc, &cnonce);
+
+        let mut value = format!(
+            "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response=\"{response}\"",
+            quote(&self.username),
+            quote(&challenge.realm),
+            quote(&challenge.nonce),
+            quote(&uri),
+            challenge.algorithm,
+        );
+
+        if challenge.qop {
+            value.push_str(&format!(
+                ", qop=auth, nc={:08x}, cnonce=\"{cnonce}\"",
+                state.nc
+            ));
+        }
+
+        if let Some(opaque) = &challenge.opaque {
+            value.push_str(&format!(", opaque={}", quote(opaque)));
+        }
+
+        Ok(Some(("Authorization".to_string(), value)))
+    }
+
+    fn refresh(&self, challenge: &str) -> crate::Result<bool> {
+        let Some(challenge) = Challenge::parse(challenge) else {
+            return Ok(false);
+        };
+
+        let mut state = self.state.lock().unwrap();
+
+        // The server rejected a fresh nonce: credentials are wrong.
+        if let Some(state) = state.as_ref()
+            && state.challenge.nonce == challenge.nonce
+            && !challenge.stale
+        {
+            return Ok(false);
+        }
+
+        *state = Some(DigestState { challenge, nc: 0 });
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::auth::Provider as _;
+
+    #[test]
+    fn basic() -> crate::Result {
+        let auth = crate::Auth::basic("Aladdin", Some("open sesame"));
+
+        assert_eq!(
+            auth.header("GET", "/")?,
+            Some((
+                "Authorization".to_string(),
+                "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_string()
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh() -> crate::Result {
+        let bearer = crate::auth::Bearer::new("expired").with_refresh(|| Ok("s3cr3t".into()));
+        let auth = crate::Auth::from(bearer.clone());
+
+        assert!(auth.refresh("")?);
+        assert_eq!(bearer.token().expose(), "s3cr3t");
+        assert!(!format!("{auth:?}").contains("s3cr3t"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn header() -> crate::Result {
+        let auth = crate::Auth::header("X-Api-Key", "s3cr3t");
+
+        assert_eq!(
+            auth.header("GET", "/")?,
+            Some(("X-Api-Key".to_string(), "s3cr3t".to_string()))
+        );
+        assert!(!format!("{auth:?}").contains("s3cr3t"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bearer() -> crate::Result {
+        let server = crate::test::server();
+
+        server.mock(|when, then| {
+            when.path("/oauth/")
+                .header("Authorization", "Bearer expired");
+            then.status(401);
+        });
+
+        server.mock(|when, then| {
+            when.path("/oauth/").header("Authorization", "Bearer fresh");
+            then.status(207).body(
+                r#"
+<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:current-user-principal>
+                    <d:href>/principals/users/johndoe/</d:href>
+                </d:current-user-principal>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+    </d:response>
+</d:multistatus>"#,
+            );
+        });
+
+        let mut client = crate::Client::new(server.url("/oauth/"));
+        let bearer = crate::auth::Bearer::new("expired").with_refresh(|| Ok("fresh".into()));
+        client.set_auth(Some(bearer.into()));
+
+        assert_eq!(client.principals()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn challenge() {
+        let challenge = super::Challenge::parse(
+            r#"Basic realm="dav", Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=MD5, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS", Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        )
+        .unwrap();
+
+        assert_eq!(challenge.algorithm, super::Algorithm::Sha256);
+        assert_eq!(challenge.realm, "http-auth@example.org");
+        assert!(challenge.qop);
+    }
+
+    #[test]
+    fn digest_response() {
+        // https://datatracker.ietf.org/doc/html/rfc7616#section-3.9.1
+        let digest = super::Digest::new("Mufasa", "Circle of Life");
+        let mut challenge = super::Challenge {
+            realm: "http-auth@example.org".to_string(),
+            nonce: "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v".to_string(),
+            opaque: None,
+            algorithm: super::Algorithm::Md5,
+            qop: true,
+            stale: false,
+        };
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+        assert_eq!(
+            digest.response(&challenge, "GET", "/dir/index.html", 1, cnonce),
+            "8ca523f5e9506fed4657c9700eebdbec"
+        );
+
+        challenge.algorithm = super::Algorithm::Sha256;
+
+        assert_eq!(
+            digest.response(&challenge, "GET", "/dir/index.html", 1, cnonce),
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1"
+        );
+    }
+
+    #[test]
+    fn digest_quote() -> crate::Result {
+        let digest = super::Digest::new(r#"Mufasa", realm="evil\"#, "Circle of Life");
+        digest.refresh(r#"Digest realm="kaldav \"test\"", nonce="abc", opaque="o\\p""#)?;
+
+        let (_, value) = digest.header("GET", "/")?.unwrap();
+        let params = super::parse_params(value.strip_prefix("Digest ").unwrap());
+
+        assert_eq!(params["username"], r#"Mufasa", realm="evil\"#);
+        assert_eq!(params["realm"], r#"kaldav "test""#);
+        assert_eq!(params["opaque"], r"o\p");
+        assert_eq!(params["nonce"], "abc");
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest() -> crate::Result {
+        let server = crate::test::server();
+
+        let challenge = server.mock(|when, then| {
+            when.path("/digest/").header_missing("Authorization");
+            then.status(401).header(
+                "WWW-Authenticate",
+                r#"Digest realm="kaldav", qop="auth", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+            );
+        });
+
+        server.mock(|when, then| {
+            when.path("/digest/").header_includes(
+                "Authorization",
+                r#"nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093""#,
+            );
+            then.status(207).body(
+                r#"
+<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:current-user-principal>
+                    <d:href>/principals/users/johndoe/</d:href>
+                </d:current-user-principal>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+    </d:response>
+</d:multistatus>"#,
+            );
+        });
+
+        let mut client = crate::Client::new(server.url("/digest/"));
+        client.set_auth(Some(crate::Auth::digest("Mufasa", "Circle Of Life")));
+
+        assert_eq!(client.principals()?.len(), 1);
+        assert_eq!(client.principals()?.len(), 1);
+
+        challenge.assert_calls(1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+/*!
+ * A persistent cache of calendars and objects.
+ *
+ * Entries are JSON files named after the hash of their href. A calendar entry keeps its
+ * `getctag` and `sync-token` with the hrefs and etags of its objects; while they don’t change,
+ * [`crate::Calendar::events`] and friends are answered from the cache with a single `PROPFIND`.
+ * Object data is stored with its etag and only requested again when the etag changes.
+ *
+ * ```no_run
+ * let client = kaldav::Client::builder("https://caldav.example.org/")
+ *     .cache(kaldav::cache::Cache::new("/var/cache/my-app")?)
+ *     .build()?;
+ *
+ * for (name, calendar) in client.calendars()? {
+ *     println!("{name}: {} events", calendar.events()?.len());
+ * }
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/**
+ * A cache directory, shared by the clones of this handle.
+ */
+#[derive(Clone, Debug)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+impl Cache {
+    /**
+     * Uses `root` as cache directory, creating it if needed.
+     */
+    pub fn new<P>(root: P) -> crate::Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+
+        Ok(Self { root })
+    }
+
+    /**
+     * Forgets the calendar or object at `href`.
+     */
+    pub fn invalidate(&self, href: &str) -> crate::Result {
+        match std::fs::remove_file(self.path(href)) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Forgets every entry.
+     */
+    pub fn clear(&self) -> crate::Result {
+        for entry in std::fs::read_dir(&self.root)? {
+            let path = entry?.path();
+
+            if path.extension().is_some_and(|x| x == "json") {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn calendar(&self, href: &str) -> Option<Collection> {
+        let json = self.read(href)?;
+
+        Collection::from_json(&json)
+    }
+
+    pub(crate) fn set_calendar(&self, href: &str, collection: &Collection) -> crate::Result {
+        self.write(href, &collection.to_json(href))
+    }
+
+    /**
+     * Data of the object at `href`, if it’s still at `etag`.
+     */
+    pub(crate) fn object(&self, href: &str, etag: &str, no_timezone: bool) -> Option<String> {
+        let json = self.read(href)?;
+
+        if json.get("etag")?.as_str()? != etag || json.get("no-timezone")?.as_bool()? != no_timezone
+        {
+            return None;
+        }
+
+        json.get("data")?.as_str().map(ToString::to_string)
+    }
+
+    pub(crate) fn set_object(
+        &self,
+        href: &str,
+        etag: &str,
+        no_timezone: bool,
+        data: &str,
+    ) -> crate::Result {
+        let json = serde_json::json!({
+            "href": href,
+            "etag": etag,
+            "no-timezone": no_timezone,
+            "data": data,
+        });
+
+        self.write(href, &json)
+    }
+
+    /**
+     * Reads an entry, a corrupted one is a cache miss.
+     */
+    fn read(&self, href: &str) -> Option<serde_json::Value> {
+        let contents = std::fs::read_to_string(self.path(href)).ok()?;
+        let json = serde_json::from_str::<serde_json::Value>(&contents).ok()?;
+
+        (json.get("href")?.as_str()? == href).then_some(json)
+    }
+
+    /**
+     * Replaces an entry atomically, so a concurrent reader never sees a partial one.
+     */
+    fn write(&self, href: &str, json: &serde_json::Value) -> crate::Result {
+        let path = self.path(href);
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+
+        std::fs::write(&tmp, json.to_string())?;
+        std::fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+
+    fn path(&self, href: &str) -> PathBuf {
+        use sha2::Digest as _;
+
+        let name = sha2::Sha256::digest(href.as_bytes())
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect::<String>();
+
+        self.root.join(format!("{name}.json"))
+    }
+}
+
+/**
+ * A cached calendar: its properties and, for each component filter, the listed objects.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Collection {
+    pub properties: BTreeMap<String, String>,
+    /** `(href, etag)` of the objects, by component name or `*` for every object. */
+    pub listings: BTreeMap<String, Vec<(String, Option<String>)>>,
+}
+
+impl Collection {
+    /**
+     * Whether the calendar didn’t change since these `properties` were cached, according to
+     * its `getctag` and `sync-token`: every token present on either side must match.
+     */
+    pub fn is_fresh(&self, properties: &BTreeMap<String, String>) -> bool {
+        let tokens = ["getctag", "sync-token"]
+            .iter()
+            .filter(|x| self.properties.contains_key(**x) || properties.contains_key(**x))
+            .collect::<Vec<_>>();
+
+        !tokens.is_empty()
+            && tokens
+                .iter()
+                .all(|x| self.properties.get(**x) == properties.get(**x))
+    }
+
+    fn from_json(json: &serde_json::Value) -> Option<Self> {
+        let properties = json
+            .get("properties")?
+            .as_object()?
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+            .collect();
+
+        let listings = json
+            .get("listings")?
+            .as_object()?
+            .iter()
+            .map(|(name, objects)| {
+                let objects = objects
+                    .as_array()?
+                    .iter()
+                    .map(|x| {
+                        let href = x.get("href")?.as_str()?.to_string();
+                        let etag = x
+                            .get("etag")
+                            .and_then(|x| x.as_str())
+                            .map(ToString::to_string);
+
+                        Some((href, etag))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some((name.clone(), objects))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            properties,
+            listings,
+        })
+    }
+
+    fn to_json(&self, href: &str) -> serde_json::Value {
+        let listings = self
+            .listings
+            .iter()
+            .map(|(name, objects)| {
+                let objects = objects
+                    .iter()
+                    .map(|(href, etag)| serde_json::json!({ "href": href, "etag": etag }))
+                    .collect();
+
+                (name.clone(), serde_json::Value::Array(objects))
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        serde_json::json!({
+            "href": href,
+            "properties": self.properties,
+            "listings": listings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn cache() -> crate::Result {
+        let root = crate::test::TempPath::new("kaldav-cache", "");
+        let cache = super::Cache::new(&root)?;
+
+        assert_eq!(cache.object("/1.ics", "\"1\"", false), None);
+        cache.set_object("/1.ics", "\"1\"", false, "BEGIN:VCALENDAR")?;
+        assert_eq!(
+            cache.object("/1.ics", "\"1\"", false).as_deref(),
+            Some("BEGIN:VCALENDAR")
+        );
+        assert_eq!(cache.object("/1.ics", "\"2\"", false), None);
+        assert_eq!(cache.object("/1.ics", "\"1\"", true), None);
+
+        let mut collection = super::Collection::default();
+        collection
+            .properties
+            .insert("getctag".to_string(), "1".to_string());
+        collection.listings.insert(
+            "VEVENT".to_string(),
+            vec![("/1.ics".to_string(), Some("\"1\"".to_string()))],
+        );
+        cache.set_calendar("/home/", &collection)?;
+
+        let cached = cache.calendar("/home/").unwrap();
+        assert_eq!(cached, collection);
+        assert!(cached.is_fresh(&collection.properties));
+        assert!(!cached.is_fresh(&Default::default()));
+
+        let mut properties = collection.properties.clone();
+        properties.insert("sync-token".to_string(), "2".to_string());
+        assert!(!cached.is_fresh(&properties));
+
+        collection
+            .properties
+            .insert("sync-token".to_string(), "1".to_string());
+        properties.insert("sync-token".to_string(), "1".to_string());
+        assert!(collection.is_fresh(&properties));
+        properties.insert("getctag".to_string(), "2".to_string());
+        assert!(!collection.is_fresh(&properties));
+
+        cache.invalidate("/home/")?;
+        assert_eq!(cache.calendar("/home/"), None);
+
+        cache.clear()?;
+        assert_eq!(cache.object("/1.ics", "\"1\"", false), None);
+
+        Ok(())
+    }
+}
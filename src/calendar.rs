@@ -1,39 +1,123 @@
 use crate::Children;
 use crate::Requestable;
+use crate::Xmlable;
 
 #[derive(Clone, Debug, Default, crate::Object)]
 pub struct Calendar {
     pub color: Option<String>,
     url: String,
-    auth: Option<crate::Authorization>,
+    session: crate::Session,
+    /** Resolves the `TZID`s when the data is requested without `VTIMEZONE`. */
+    timezones: Option<crate::timezone::Resolver>,
+    /** Whether filters using the `anyof` test are sent as is. */
+    anyof: bool,
 }
 
 impl Calendar {
     pub fn objects(&self) -> crate::Result<crate::object::Iterator> {
-        let response = self.request(None)?;
-
-        Ok(crate::object::Iterator::from(
-            self.to_vec(&response, "//d:response/d:href/text()"),
-        ))
+        self.list(None)
     }
 
     pub fn events(&self) -> crate::Result<crate::object::Iterator> {
-        let response = self.request(Some("VEVENT"))?;
-
-        Ok(crate::object::Iterator::from(
-            self.to_vec(&response, "//d:response/d:href/text()"),
-        ))
+        self.list(Some("VEVENT"))
     }
 
     pub fn tasks(&self) -> crate::Result<crate::object::Iterator> {
-        let response = self.request(Some("VTODO"))?;
+        self.list(Some("VTODO"))
+    }
+
+    /**
+     * Lists the objects containing `component`, from the cache when the calendar didn’t change.
+     */
+    fn list(&self, component: Option<&str>) -> crate::Result<crate::object::Iterator> {
+        let Some(cache) = self.session.cache() else {
+            let response = self.request(component, "<d:resourcetype />")?;
+
+            return Ok(self.iterator(self.to_vec(&response, "//d:response/d:href/text()")));
+        };
+
+        let properties = self.state()?;
+        let mut collection = cache
+            .calendar(&self.url)
+            .filter(|x| x.is_fresh(&properties))
+            .unwrap_or_default();
+        let key = component.unwrap_or("*");
+
+        let listing = if let Some(listing) = collection.listings.get(key) {
+            listing.clone()
+        } else {
+            let listing = self.etags(component)?;
+
+            collection.properties = properties;
+            collection.listings.insert(key.to_string(), listing.clone());
+            cache.set_calendar(&self.url, &collection)?;
+
+            listing
+        };
+
+        let objects = listing
+            .into_iter()
+            .map(|(href, etag)| {
+                let mut object =
+                    crate::object::Object::new(href, &std::collections::BTreeMap::new());
+                object.set_session(self.session.clone());
+
+                object.with_etag(etag)
+            })
+            .collect();
+
+        Ok(self.iterator(objects))
+    }
+
+    /**
+     * URLs and etags of the objects containing `component`, or of every object.
+     */
+    pub(crate) fn etags(
+        &self,
+        component: Option<&str>,
+    ) -> crate::Result<Vec<(String, Option<String>)>> {
+        let response = self.request(component, "<d:getetag />")?;
+        let multistatus = response.parse::<crate::elements::Multistatus>()?;
+        let getetag = crate::elements::PropName::dav("getetag");
+
+        let etags = multistatus
+            .responses
+            .into_iter()
+            .map(|(href, properties)| {
+                let etag = properties
+                    .get(&getetag)
+                    .filter(|x| x.is_success())
+                    .map(|x| x.value.text.clone());
 
-        Ok(crate::object::Iterator::from(
-            self.to_vec(&response, "//d:response/d:href/text()"),
-        ))
+                (self.append_host(href), etag)
+            })
+            .collect();
+
+        Ok(etags)
+    }
+
+    /**
+     * The properties telling whether the calendar changed: `getctag` and `sync-token`.
+     */
+    fn state(&self) -> crate::Result<std::collections::BTreeMap<String, String>> {
+        let names = [
+            crate::elements::PropName::calendarserver("getctag"),
+            crate::elements::PropName::dav("sync-token"),
+        ];
+        let responses = self.propfind(&self.url, crate::elements::Depth::Zero, &names)?;
+
+        let properties = responses
+            .values()
+            .flat_map(|x| x.iter())
+            .filter(|(name, property)| names.contains(name) && property.is_success())
+            .map(|(name, property)| (name.name.clone(), property.value.text.trim().to_string()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+
+        Ok(properties)
     }
 
-    fn request(&self, filter: Option<&str>) -> crate::Result<String> {
+    fn request(&self, filter: Option<&str>, prop: &str) -> crate::Result<String> {
         let filter = if let Some(filter) = filter {
             format!("<c:comp-filter name=\"{filter}\" />")
         } else {
@@ -43,7 +127,7 @@ impl Calendar {
             r#"
 <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
     <d:prop>
-        <d:resourcetype />
+        {prop}
     </d:prop>
     <c:filter><c:comp-filter name="VCALENDAR">{filter}</c:comp-filter></c:filter>
 </c:calendar-query>
@@ -53,28 +137,243 @@ impl Calendar {
         self.report(&self.url, &body)
     }
 
+    /**
+     * Searches the objects matching `filter`.
+     *
+     * Each top-level alternative is sent as its own query. A filter using the `anyof` test,
+     * which RFC 4791 doesn’t define, is rewritten as plain RFC 4791 queries, one per
+     * alternative, and the results merged; unless the calendar is read [`Self::with_anyof`].
+     */
     pub fn search(
         &self,
         filter: &crate::elements::Filter,
     ) -> crate::Result<crate::object::Iterator> {
+        self.search_with(filter, "<d:resourcetype />")
+    }
+
+    /**
+     * Starts a [`crate::Query`] on this calendar.
+     */
+    pub fn query(&self) -> crate::Query<'_> {
+        crate::Query::new(self)
+    }
+
+    /**
+     * Starts a two-way synchronization of this calendar, see [`crate::sync`].
+     */
+    pub fn sync(&self) -> crate::sync::Synchronizer<'_> {
+        crate::sync::Synchronizer::new(self)
+    }
+
+    /**
+     * Same as [`Self::search`], requesting the `prop` properties. The objects keep the
+     * `CALDAV:calendar-data` property when it’s returned.
+     */
+    pub(crate) fn search_with(
+        &self,
+        filter: &crate::elements::Filter,
+        prop: &str,
+    ) -> crate::Result<crate::object::Iterator> {
+        filter.validate()?;
+
+        let mut objects = Vec::<crate::object::Object>::new();
+
+        let filters = if self.anyof {
+            filter
+                .comp_filters
+                .iter()
+                .map(|x| crate::elements::Filter::new().append(x.clone()))
+                .collect()
+        } else {
+            filter.alternatives()
+        };
+
+        for filter in filters {
+            for object in self.report_query(&filter, prop)? {
+                if !objects.iter().any(|x| x.url() == object.url()) {
+                    objects.push(object);
+                }
+            }
+        }
+
+        Ok(self.iterator(objects))
+    }
+
+    fn report_query(
+        &self,
+        filter: &crate::elements::Filter,
+        prop: &str,
+    ) -> crate::Result<Vec<crate::object::Object>> {
         use webdav::ToXml as _;
 
         let body = format!(
             r#"
 <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
     <d:prop>
-        <d:resourcetype />
+        {prop}
     </d:prop>
     {}
 </c:calendar-query>"#,
             filter.to_xml(),
         );
 
-        let response = self.report(&self.url, &body)?;
+        let mut headers = crate::Headers::new();
+        headers.insert("Depth", "1");
+
+        if self.timezones.is_some() {
+            headers.insert("Prefer", "calendar-no-timezone");
+        }
+
+        let response = crate::Requestable::request(
+            self,
+            crate::Method::REPORT,
+            &self.url,
+            Some(&body),
+            Some(headers),
+        )?;
+        let multistatus = response.parse::<crate::elements::Multistatus>()?;
+        let calendar_data = crate::elements::PropName::caldav("calendar-data");
+
+        let objects = multistatus
+            .responses
+            .iter()
+            .map(|(href, properties)| {
+                let mut object = crate::object::Object::new(
+                    self.append_host(href.clone()),
+                    &std::collections::BTreeMap::new(),
+                );
+                object.set_session(self.session().clone());
+
+                let data = properties
+                    .get(&calendar_data)
+                    .filter(|x| x.is_success())
+                    .map(|x| x.value.text.clone());
+
+                object.with_data(data)
+            })
+            .collect();
+
+        Ok(objects)
+    }
+
+    /**
+     * Whether the server can return the calendar data without the `VTIMEZONE` components it
+     * references, advertised by `calendar-no-timezone` in the `DAV` header.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-3.1>
+     */
+    pub fn supports_timezones_by_reference(&self) -> crate::Result<bool> {
+        let response = self
+            .session()
+            .send("OPTIONS", &self.url, None, &crate::Headers::new())?;
+
+        if !response.is_success() {
+            return Err(crate::Error::from_response(
+                crate::Method::OPTIONS,
+                &self.url,
+                response,
+            ));
+        }
+
+        let supported = response
+            .headers
+            .get_all("DAV")
+            .iter()
+            .filter_map(|x| x.to_str().ok())
+            .flat_map(|x| x.split(','))
+            .any(|x| x.trim().eq_ignore_ascii_case("calendar-no-timezone"));
+
+        Ok(supported)
+    }
+
+    /**
+     * The `TZID` of the calendar time zone, the `CALDAV:calendar-timezone-id` property.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-5.2>
+     */
+    pub fn timezone_id(&self) -> crate::Result<Option<String>> {
+        let name = crate::elements::PropName::caldav("calendar-timezone-id");
+        let properties = self.propfind(
+            &self.url,
+            crate::elements::Depth::Zero,
+            std::slice::from_ref(&name),
+        )?;
+
+        let tzid = properties
+            .values()
+            .filter_map(|x| x.get(&name))
+            .find(|x| x.is_success())
+            .map(|x| x.value.text.trim().to_string())
+            .filter(|x| !x.is_empty());
 
-        Ok(crate::object::Iterator::from(
-            self.to_vec(&response, "//d:response/d:href/text()"),
-        ))
+        Ok(tzid)
+    }
+
+    /**
+     * Requests the calendar data without the `VTIMEZONE` components, the `TZID`s are resolved
+     * by `resolver` instead, typically from an IANA database.
+     *
+     * The server should support it, see [`Self::supports_timezones_by_reference`]; the data of
+     * the others still has its `VTIMEZONE` components.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-3>
+     */
+    pub fn without_timezones(mut self, resolver: crate::timezone::Resolver) -> Self {
+        self.timezones = Some(resolver);
+
+        self
+    }
+
+    /**
+     * Sends the filters using the `anyof` test as is, in a single query, instead of a query per
+     * alternative.
+     *
+     * `anyof` is an extension to RFC 4791 and a server that doesn’t implement it may ignore it,
+     * returning too few objects without failing: only use it when the server is known to support
+     * it, see [`Self::supports_anyof`].
+     */
+    pub fn with_anyof(mut self) -> Self {
+        self.anyof = true;
+
+        self
+    }
+
+    /**
+     * Whether the server evaluates the `anyof` test, checked with a query whose result depends
+     * on it: every object of the calendar matches it with `anyof`, none with `allof`. `false`
+     * for an empty calendar, where it can’t be told.
+     */
+    pub fn supports_anyof(&self) -> crate::Result<bool> {
+        use crate::elements::filter::{CompFilter, Test};
+
+        let filter = crate::elements::Filter::new().append(
+            CompFilter::new("VCALENDAR")
+                .test(Test::AnyOf)
+                .append(CompFilter::new("VEVENT"))
+                .append(CompFilter::new("VEVENT").is_not_defined(true)),
+        );
+
+        let supported = match self.report_query(&filter, "<d:getetag />") {
+            Ok(objects) => !objects.is_empty(),
+            Err(crate::Error::Request { status, .. }) if is_rejected(status) => false,
+            Err(error) => return Err(error),
+        };
+
+        Ok(supported)
+    }
+
+    pub(crate) fn has_timezones(&self) -> bool {
+        self.timezones.is_none()
+    }
+
+    fn iterator(&self, objects: Vec<crate::object::Object>) -> crate::object::Iterator {
+        let objects = objects
+            .into_iter()
+            .map(|x| x.without_timezones(!self.has_timezones()))
+            .collect();
+
+        crate::object::Iterator::from(objects)
+            .with_resolver(self.timezones.clone().unwrap_or_default())
     }
 
     /**
@@ -83,15 +382,30 @@ impl Calendar {
     pub fn create<O: ikal::ser::Serialize>(&self, object: &O) -> crate::Result<crate::Object> {
         let url = format!("{}/{}.ics", self.url, uuid::Uuid::now_v7());
         let body = ikal::ser::ical(object);
-        self.put(&url, Some(&body))?;
+
+        let mut headers = crate::Headers::new();
+        headers.insert("If-None-Match", "*");
+        crate::Requestable::request(self, crate::Method::PUT, &url, Some(&body), Some(headers))?;
 
         let mut object = crate::Object::new(url, &Default::default());
-        object.set_auth(self.auth.clone());
+        object.set_session(self.session.clone());
 
         Ok(object)
     }
 }
 
+/**
+ * Whether the server refused a filter it doesn’t support, like an `anyof` test.
+ */
+fn is_rejected(status: attohttpc::StatusCode) -> bool {
+    matches!(
+        status,
+        attohttpc::StatusCode::BAD_REQUEST
+            | attohttpc::StatusCode::FORBIDDEN
+            | attohttpc::StatusCode::NOT_IMPLEMENTED
+    )
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -148,4 +462,252 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn search_alternatives() -> crate::Result {
+        let server = crate::test::server();
+
+        let anyof = server.mock(|when, then| {
+            when.path("/calendars/johndoe/home/")
+                .body_includes(r#"test="anyof""#);
+            then.status(207).body(
+                r#"<d:multistatus xmlns:d="DAV:"><d:response><d:href>/calendars/johndoe/home/1.ics</d:href></d:response><d:response><d:href>/calendars/johndoe/home/2.ics</d:href></d:response></d:multistatus>"#,
+            );
+        });
+
+        let mut alternatives = Vec::new();
+
+        for (text, objects) in [("lunch", &["1.ics"][..]), ("dinner", &["1.ics", "2.ics"])] {
+            let responses = objects
+                .iter()
+                .map(|x| {
+                    format!("<d:response><d:href>/calendars/johndoe/home/{x}</d:href></d:response>")
+                })
+                .collect::<String>();
+
+            alternatives.push(server.mock(|when, then| {
+                when.path("/calendars/johndoe/home/")
+                    .body_includes(format!("<![CDATA[{text}]]>"))
+                    .body_excludes(r#"test="anyof""#);
+                then.status(207).body(format!(
+                    r#"<d:multistatus xmlns:d="DAV:">{responses}</d:multistatus>"#
+                ));
+            }));
+        }
+
+        let client = crate::Client::new(server.url(""));
+        let calendars = client.calendars()?;
+        let calendar = calendars.get("Home calendar").unwrap();
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        test: Test::AnyOf,
+                        text_match: TextMatch::new("lunch"),
+                        text_match: TextMatch::new("dinner"),
+                    }
+                }
+            }
+        };
+
+        assert_eq!(calendar.search(&filter)?.len(), 2);
+        anyof.assert_calls(0);
+        alternatives.iter().for_each(|x| x.assert_calls(1));
+
+        assert_eq!(calendar.clone().with_anyof().search(&filter)?.len(), 2);
+        anyof.assert_calls(1);
+        alternatives.iter().for_each(|x| x.assert_calls(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn timezones_by_reference() -> crate::Result {
+        use crate::timezone::DateTimes as _;
+
+        let server = kaldav_testserver::Server::start();
+        let mut home = kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]);
+        home.timezone_id = Some("Europe/Paris".to_string());
+        server.add_calendar("home", home);
+        server.set_timezone_service_set(&["https://tz.example.org/"]);
+        server.put(
+            "home",
+            "1.ics",
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Paris\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+DTSTART:19810329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19961027T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20240101T090000Z\r
+DTSTART;TZID=Europe/Paris:20240701T100000\r
+SUMMARY:Meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+        );
+
+        let client = crate::Client::new(server.url(""));
+        assert_eq!(client.timezone_service_set()?, ["https://tz.example.org/"]);
+
+        let calendars = client.calendars()?;
+        let calendar = &calendars["Home calendar"];
+        assert!(calendar.supports_timezones_by_reference()?);
+        assert_eq!(calendar.timezone_id()?.as_deref(), Some("Europe/Paris"));
+
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap()
+            .and_utc();
+
+        let check = |objects: crate::object::Iterator, timezones: bool| -> crate::Result {
+            for result in objects.zoned() {
+                let (vcalendar, zoned) = result?;
+                assert_eq!(!vcalendar.timezones.is_empty(), timezones);
+
+                let event = &vcalendar.events[0];
+                assert_eq!(
+                    event.start(&zoned).unwrap().to_utc(zoned.resolver())?,
+                    start
+                );
+            }
+
+            Ok(())
+        };
+
+        check(calendar.events()?, true)?;
+
+        let calendar =
+            calendar
+                .clone()
+                .without_timezones(crate::timezone::Resolver::new().fallback(|name| {
+                    (name == "Europe/Paris").then(|| chrono::FixedOffset::east_opt(7_200).unwrap())
+                }));
+
+        check(calendar.events()?, false)?;
+        check(calendar.query().events().search()?, false)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache() -> crate::Result {
+        use std::sync::{Arc, Mutex};
+
+        let event = |uid: &str| {
+            format!(
+                "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:{uid}\r
+DTSTAMP:20240101T090000Z\r
+DTSTART:20240701T100000Z\r
+SUMMARY:Meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            )
+        };
+
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+        server.put("home", "1.ics", &event("1"));
+
+        let root = crate::test::TempPath::new("kaldav-cache", "");
+        let exchanges = Arc::new(Mutex::new(Vec::new()));
+
+        let calendar = || -> crate::Result<crate::Calendar> {
+            let recorded = exchanges.clone();
+            let client = crate::Client::builder(server.url(""))
+                .cache(crate::cache::Cache::new(&root)?)
+                .observer(move |exchange: &crate::trace::Exchange| {
+                    recorded.lock().unwrap().push(exchange.method.clone());
+                })
+                .build()?;
+            let calendar = client.calendars()?.remove("Home calendar").unwrap();
+            exchanges.lock().unwrap().clear();
+
+            Ok(calendar)
+        };
+        let requests = || std::mem::take(&mut *exchanges.lock().unwrap());
+
+        let home = calendar()?;
+        assert_eq!(home.events()?.count(), 1);
+        assert_eq!(requests(), ["PROPFIND", "REPORT", "GET"]);
+
+        assert_eq!(home.events()?.count(), 1);
+        assert_eq!(requests(), ["PROPFIND"]);
+
+        server.put("home", "2.ics", &event("2"));
+        assert_eq!(home.events()?.count(), 2);
+        assert_eq!(requests(), ["PROPFIND", "REPORT", "GET"]);
+
+        let home = calendar()?;
+        assert_eq!(home.events()?.count(), 2);
+        assert_eq!(requests(), ["PROPFIND"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create() -> crate::Result {
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+
+        let client = crate::Client::new(server.url(""));
+        let calendars = client.calendars()?;
+        let calendar = &calendars["Home calendar"];
+
+        let object = calendar.create(&crate::ical::vcalendar! {
+            version: "2.0",
+            prodid: "-//kaldav//EN",
+            events: [
+                {
+                    uid: "19970610T172345Z-AF23B2@example.com",
+                    dtstamp: "19970610T172345Z",
+                    dtstart: "19970714T170000Z",
+                    dtend: "19970715T040000Z",
+                    summary: "Bastille Day Party",
+                }
+            ],
+        }?)?;
+
+        let events = calendar.events()?.collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].events[0]
+                .summary
+                .as_ref()
+                .map(ToString::to_string),
+            Some("Bastille Day Party".to_string())
+        );
+
+        object.delete()?;
+        assert!(calendar.events()?.is_empty());
+
+        Ok(())
+    }
 }
@@ -6,7 +6,7 @@ use std::convert::Into;
 #[derive(Debug, Default, crate::Object)]
 pub struct Client {
     url: String,
-    auth: Option<crate::Authorization>,
+    session: crate::Session,
 }
 
 impl Client {
@@ -16,13 +16,21 @@ impl Client {
     {
         Self {
             url: url.into(),
-            auth: None,
+            session: crate::Session::default(),
         }
     }
 
+    pub fn builder<S>(url: S) -> ClientBuilder
+    where
+        S: Into<String>,
+    {
+        ClientBuilder::new(url)
+    }
+
     pub fn principals(&self) -> crate::Result<Vec<crate::Principal>> {
-        let response = self.propfind(
+        let response = self.propfind_raw(
             &self.url,
+            crate::elements::Depth::Zero,
             r#"
 <d:propfind xmlns:d="DAV:">
     <d:prop>
@@ -61,13 +69,368 @@ impl Client {
         home.new_calendar(path, config)
     }
 
-    pub fn set_auth(&mut self, auth: Option<crate::Authorization>) {
-        crate::Requestable::set_auth(self, auth)
+    /**
+     * See [`crate::Home::timezone_service_set`].
+     */
+    pub fn timezone_service_set(&self) -> crate::Result<Vec<String>> {
+        self.home()?.timezone_service_set()
+    }
+
+    /**
+     * A client for the time zone distribution service at `url`, sharing the credentials and
+     * HTTP settings of this client.
+     */
+    pub fn tzdist<S>(&self, url: S) -> crate::Tzdist
+    where
+        S: Into<String>,
+    {
+        crate::Tzdist::new(url).with_session(self.session.clone())
+    }
+
+    /**
+     * Changes the credentials of this client and of every object derived from it.
+     */
+    pub fn set_auth(&mut self, auth: Option<crate::Auth>) {
+        self.session.set_auth(auth);
+    }
+}
+
+/**
+ * Configures the HTTP settings shared by a [`Client`] and every calendar or object derived from
+ * it.
+ *
+ * ```no_run
+ * let client = kaldav::Client::builder("https://caldav.example.org/")
+ *     .auth(kaldav::Auth::basic("johndoe", Some("s3cr3t")))
+ *     .connect_timeout(std::time::Duration::from_secs(5))
+ *     .read_timeout(std::time::Duration::from_secs(30))
+ *     .user_agent("my-app/1.0")
+ *     .build()?;
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+#[derive(Default)]
+pub struct ClientBuilder {
+    url: String,
+    auth: Option<crate::Auth>,
+    config: crate::session::Config,
+    headers: Vec<(String, String)>,
+    proxy: Option<attohttpc::ProxySettingsBuilder>,
+    root_certificates: Vec<Vec<u8>>,
+}
+
+impl std::fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // custom headers often carry API keys
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, _)| (name, "***"))
+            .collect::<Vec<_>>();
+
+        f.debug_struct("ClientBuilder")
+            .field("url", &self.url)
+            .field("auth", &self.auth)
+            .field("config", &self.config)
+            .field("headers", &headers)
+            .field("proxy", &self.proxy)
+            .field("root_certificates", &self.root_certificates.len())
+            .finish()
     }
 }
 
+impl ClientBuilder {
+    pub fn new<S>(url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn auth(mut self, auth: crate::Auth) -> Self {
+        self.auth = Some(auth);
+
+        self
+    }
+
+    /**
+     * Maximum time to establish the connection.
+     */
+    pub fn connect_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+
+        self
+    }
+
+    /**
+     * Maximum time to wait for data from the server.
+     */
+    pub fn read_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.config.read_timeout = Some(timeout);
+
+        self
+    }
+
+    /**
+     * Maximum duration of a whole request, including redirections.
+     */
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.config.timeout = Some(timeout);
+
+        self
+    }
+
+    /**
+     * Sends HTTP requests through this proxy.
+     */
+    pub fn http_proxy(mut self, url: &str) -> crate::Result<Self> {
+        let url = url::Url::parse(url).map_err(|e| crate::Error::Misc(e.to_string()))?;
+        self.proxy = Some(self.proxy.unwrap_or_default().http_proxy(url));
+
+        Ok(self)
+    }
+
+    /**
+     * Sends HTTPS requests through this proxy.
+     */
+    pub fn https_proxy(mut self, url: &str) -> crate::Result<Self> {
+        let url = url::Url::parse(url).map_err(|e| crate::Error::Misc(e.to_string()))?;
+        self.proxy = Some(self.proxy.unwrap_or_default().https_proxy(url));
+
+        Ok(self)
+    }
+
+    /**
+     * Bypasses the proxy for hosts matching `pattern`.
+     */
+    pub fn no_proxy(mut self, pattern: &str) -> Self {
+        self.proxy = Some(self.proxy.unwrap_or_default().add_no_proxy_host(pattern));
+
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.config.user_agent = user_agent.to_string();
+
+        self
+    }
+
+    /**
+     * Adds a header to every request.
+     */
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+
+        self
+    }
+
+    /**
+     * Retries requests failing with a transient error, see [`crate::Retry`].
+     */
+    pub fn retry(mut self, retry: crate::Retry) -> Self {
+        self.config.retry = retry;
+
+        self
+    }
+
+    /**
+     * Reports every exchange with the server to `observer`, see [`crate::trace::Recorder`].
+     */
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: crate::trace::Observer + 'static,
+    {
+        self.config.observers.push(std::sync::Arc::new(observer));
+
+        self
+    }
+
+    /**
+     * Keeps calendars and objects in `cache`, see [`crate::cache`].
+     */
+    pub fn cache(mut self, cache: crate::cache::Cache) -> Self {
+        self.config.cache = Some(cache);
+
+        self
+    }
+
+    /**
+     * Includes request and response bodies in the traced exchanges. They may contain private
+     * calendar data.
+     */
+    pub fn trace_bodies(mut self, trace_bodies: bool) -> Self {
+        self.config.trace_bodies = trace_bodies;
+
+        self
+    }
+
+    /**
+     * Rewrites the traced bodies with `redact`, to hide private calendar data.
+     *
+     * ```no_run
+     * let client = kaldav::Client::builder("https://caldav.example.org/")
+     *     .trace_bodies(true)
+     *     .redact_bodies(|body| body.replace("johndoe", "***").into())
+     *     .build()?;
+     * # Ok::<(), kaldav::Error>(())
+     * ```
+     */
+    pub fn redact_bodies<F>(mut self, redact: F) -> Self
+    where
+        F: Fn(&str) -> std::borrow::Cow<'_, str> + Send + Sync + 'static,
+    {
+        self.config.redact_bodies = Some(std::sync::Arc::new(redact));
+
+        self
+    }
+
+    /**
+     * Replaces the value of this header by `***` in the traces and recorded fixtures, like the
+     * credential headers.
+     */
+    pub fn sensitive_header(mut self, name: &str) -> Self {
+        self.config.sensitive_headers.push(name.to_string());
+
+        self
+    }
+
+    /**
+     * Sends the requests through `transport` instead of directly over the network, see
+     * [`crate::transport::Record`] and [`crate::transport::Replay`].
+     */
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: crate::transport::Transport + 'static,
+    {
+        self.config.transport = Some(std::sync::Arc::new(transport));
+
+        self
+    }
+
+    pub fn redirect(mut self, redirect: crate::Redirect) -> Self {
+        self.config.redirect = redirect;
+
+        self
+    }
+
+    /**
+     * Trusts this PEM encoded certificate authority, in addition of the system ones.
+     */
+    pub fn add_root_certificate(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(pem.to_vec());
+
+        self
+    }
+
+    /**
+     * Authenticates with this PKCS#12 (`.p12` or `.pfx`) client certificate and private key.
+     */
+    #[cfg(feature = "native-tls")]
+    pub fn identity_pkcs12(mut self, der: &[u8], password: &str) -> crate::Result<Self> {
+        let identity = native_tls::Identity::from_pkcs12(der, password)
+            .map_err(|e| crate::Error::Misc(e.to_string()))?;
+        self.config.identity = Some(identity);
+
+        Ok(self)
+    }
+
+    /**
+     * Authenticates with this PEM encoded client certificate chain and private key.
+     */
+    #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+    pub fn identity_pem(mut self, certificate: &[u8], key: &[u8]) -> crate::Result<Self> {
+        use rustls_pki_types::pem::PemObject as _;
+
+        let chain = rustls_pki_types::CertificateDer::pem_slice_iter(certificate)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| crate::Error::Misc(e.to_string()))?;
+        let key = rustls_pki_types::PrivateKeyDer::from_pem_slice(key)
+            .map_err(|e| crate::Error::Misc(e.to_string()))?;
+        self.config.identity = Some(crate::tls::Identity { chain, key });
+
+        Ok(self)
+    }
+
+    /**
+     * Accepts invalid certificates, like self-signed ones. Only use this for development.
+     */
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.config.accept_invalid_certs = accept;
+
+        self
+    }
+
+    /**
+     * Accepts certificates issued for another host name. Only use this for development.
+     */
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.config.accept_invalid_hostnames = accept;
+
+        self
+    }
+
+    pub fn build(self) -> crate::Result<Client> {
+        let mut config = self.config;
+
+        for (name, value) in &self.headers {
+            let name = attohttpc::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| crate::Error::Misc(format!("Invalid header name: {name}")))?;
+            let value = attohttpc::header::HeaderValue::from_str(value)
+                .map_err(|_| crate::Error::Misc(format!("Invalid value for header {name}")))?;
+
+            config.headers.append(name, value);
+        }
+
+        config.proxy = self.proxy.map(attohttpc::ProxySettingsBuilder::build);
+
+        for pem in &self.root_certificates {
+            config.root_certificates.push(certificate(pem)?);
+        }
+
+        Ok(Client {
+            url: self.url,
+            session: crate::Session::with_config(self.auth, config),
+        })
+    }
+}
+
+#[cfg(feature = "native-tls")]
+fn certificate(pem: &[u8]) -> crate::Result<crate::session::Certificate> {
+    native_tls::Certificate::from_pem(pem).map_err(|e| crate::Error::Misc(e.to_string()))
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+fn certificate(pem: &[u8]) -> crate::Result<crate::session::Certificate> {
+    use rustls_pki_types::pem::PemObject as _;
+
+    rustls_pki_types::CertificateDer::from_pem_slice(pem)
+        .map_err(|e| crate::Error::Misc(e.to_string()))
+}
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+fn certificate(_: &[u8]) -> crate::Result<crate::session::Certificate> {
+    Err(crate::Error::Misc(
+        "kaldav was built without TLS support".to_string(),
+    ))
+}
+
 #[cfg(test)]
 mod test {
+    #[test]
+    fn debug() {
+        let builder = crate::Client::builder("https://caldav.example.org/")
+            .header("X-Api-Key", "s3cr3t")
+            .auth(crate::Auth::bearer("t0k3n"));
+
+        let debug = format!("{builder:?}");
+        assert!(debug.contains("X-Api-Key"));
+        assert!(!debug.contains("s3cr3t"));
+        assert!(!debug.contains("t0k3n"));
+    }
+
     #[test]
     fn principals() -> crate::Result {
         let server = crate::test::server();
@@ -100,6 +463,82 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn propfind() -> crate::Result {
+        use crate::Requestable as _;
+        use crate::elements::PropName;
+
+        let server = crate::test::server();
+
+        let client = crate::Client::new(server.url(""));
+        let responses = client.propfind(
+            server.url("/calendars/johndoe/home/"),
+            crate::elements::Depth::Zero,
+            &[
+                PropName::dav("displayname"),
+                PropName::calendarserver("getctag"),
+            ],
+        )?;
+
+        let properties = &responses["/calendars/johndoe/home/"];
+        assert_eq!(
+            properties[&PropName::calendarserver("getctag")].value.text,
+            "3145"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder() -> crate::Result {
+        let server = crate::test::server();
+
+        let mock = server.mock(|when, then| {
+            when.path("/calendars/johndoe/home/")
+                .header("User-Agent", "kaldav-test")
+                .header("X-Tenant", "acme");
+            then.status(207).body(
+                r#"
+<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/calendars/johndoe/home/132456-34365.ics</d:href>
+    </d:response>
+</d:multistatus>
+"#,
+            );
+        });
+
+        let client = crate::Client::builder(server.url(""))
+            .user_agent("kaldav-test")
+            .header("X-Tenant", "acme")
+            .build()?;
+        let calendars = client.calendars()?;
+        let calendar = calendars.get("Home calendar").unwrap();
+        calendar.objects()?;
+
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[test]
+    fn timeout() -> crate::Result {
+        let server = crate::test::server();
+
+        server.mock(|when, then| {
+            when.path("/slow/");
+            then.status(207).delay(std::time::Duration::from_secs(2));
+        });
+
+        let client = crate::Client::builder(server.url("/slow/"))
+            .read_timeout(std::time::Duration::from_millis(100))
+            .build()?;
+
+        assert!(matches!(client.principals(), Err(crate::Error::Http(_))));
+
+        Ok(())
+    }
+
     #[test]
     fn calendars() -> crate::Result {
         let server = crate::test::server();
@@ -0,0 +1,875 @@
+/*!
+ * Calendar data as a tree of components and raw properties, without interpreting the values.
+ *
+ * It's the representation the filters are evaluated on, see
+ * [`crate::elements::Filter::matches_with`]. Unlike `ikal`, it keeps every parameter, like
+ * `TZID`, and the properties and components it doesn't know, which the time zones, the
+ * synchronization and the server need to write the data back unchanged.
+ */
+
+/**
+ * A calendar component, like `VCALENDAR` or `VEVENT`.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/**
+ * Writes the component back, folding lines longer than 75 octets.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.1>
+ */
+impl std::fmt::Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BEGIN:{}\r\n", self.name)?;
+
+        for property in &self.properties {
+            write_folded(f, &property.to_string())?;
+        }
+
+        for component in &self.components {
+            write!(f, "{component}")?;
+        }
+
+        write!(f, "END:{}\r\n", self.name)
+    }
+}
+
+fn write_folded(f: &mut std::fmt::Formatter<'_>, line: &str) -> std::fmt::Result {
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            f.write_str("\r\n ")?;
+            width = 1;
+        }
+
+        write!(f, "{c}")?;
+        width += c.len_utf8();
+    }
+
+    f.write_str("\r\n")
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+
+        for (name, value) in &self.params {
+            let value = encode(value);
+
+            if value.contains([':', ';', ',']) {
+                write!(f, ";{name}=\"{value}\"")?;
+            } else {
+                write!(f, ";{name}={value}")?;
+            }
+        }
+
+        write!(f, ":{}", self.value)
+    }
+}
+
+/**
+ * Adds the properties `ikal` keeps as written, with their parameters: the unknown ones and the
+ * extensions.
+ */
+macro_rules! unknown {
+    ($component:expr, $($properties:expr),+) => {
+        $(
+            for (name, line) in $properties {
+                $component.properties.push(Property {
+                    name: name.to_ascii_uppercase(),
+                    params: line.params.clone().into_iter().collect(),
+                    value: line.value.clone(),
+                });
+            }
+        )+
+    };
+}
+
+impl Component {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn push<T: Value>(&mut self, name: &str, value: &T) {
+        let (params, value) = value.write();
+
+        self.properties.push(Property {
+            name: name.to_string(),
+            params,
+            value,
+        });
+    }
+
+    fn extend<'a, T: Value + 'a>(&mut self, name: &str, values: impl IntoIterator<Item = &'a T>) {
+        for value in values {
+            self.push(name, value);
+        }
+    }
+
+    /**
+     * Adds the `RECURRENCE-ID` `ikal` parsed, unless it kept the written one with the unknown
+     * properties.
+     */
+    fn recurrence_id<T>(
+        &mut self,
+        recurid: Option<&ikal::Date>,
+        unknown: &std::collections::BTreeMap<String, T>,
+    ) {
+        if !unknown
+            .keys()
+            .any(|x| x.eq_ignore_ascii_case("RECURRENCE-ID"))
+        {
+            self.extend("RECURRENCE-ID", recurid);
+        }
+    }
+}
+
+/**
+ * Builds the tree from the values parsed by `ikal`, without writing the calendar back.
+ *
+ * `ikal` keeps neither the parameters of the typed properties, like `TZID`, nor the `RANGE` of a
+ * `RECURRENCE-ID` it reads, so the date-times without `Z` are floating; the unknown properties
+ * keep theirs.
+ */
+impl From<&ikal::VCalendar> for Component {
+    fn from(calendar: &ikal::VCalendar) -> Self {
+        let mut component = Self::new("VCALENDAR");
+
+        component.push("PRODID", &calendar.prodid);
+        component.push("VERSION", &calendar.version);
+        component.extend("CALSCALE", &calendar.calscale);
+        component.extend("METHOD", &calendar.method);
+        unknown!(component, &calendar.x_prop, &calendar.iana_prop);
+
+        component.components = calendar
+            .timezones
+            .iter()
+            .map(Self::from)
+            .chain(calendar.events.iter().map(Self::from))
+            .chain(calendar.todo.iter().map(Self::from))
+            .chain(calendar.journals.iter().map(Self::from))
+            .chain(calendar.freebusy.iter().map(Self::from))
+            .chain(calendar.alarms.iter().map(Self::from))
+            .collect();
+
+        component
+    }
+}
+
+impl From<&ikal::VEvent> for Component {
+    fn from(event: &ikal::VEvent) -> Self {
+        let mut component = Self::new("VEVENT");
+
+        component.push("DTSTAMP", &event.dtstamp);
+        component.push("UID", &event.uid);
+        component.push("DTSTART", &event.dtstart);
+        component.extend("CLASS", &event.class);
+        component.extend("CREATED", &event.created);
+        component.extend("DESCRIPTION", &event.description);
+        component.extend("GEO", &event.geo);
+        component.extend("LAST-MODIFIED", &event.last_modified);
+        component.extend("LOCATION", &event.location);
+        component.extend("ORGANIZER", &event.organizer);
+        component.extend("PRIORITY", &event.priority);
+        component.extend("SEQUENCE", &event.sequence);
+        component.extend("STATUS", &event.status);
+        component.extend("SUMMARY", &event.summary);
+        component.extend("TRANSP", &event.transp);
+        component.extend("URL", &event.url);
+        component.extend("RRULE", &event.rrule);
+        component.extend("DTEND", &event.dtend);
+        component.extend("DURATION", &event.duration);
+        component.extend("ATTACH", &event.attach);
+        component.extend("ATTENDEE", &event.attendee);
+        component.extend("CATEGORIES", &event.categories);
+        component.extend("COMMENT", &event.comment);
+        component.extend("CONTACT", &event.contact);
+        component.extend("EXDATE", &event.exdate);
+        component.extend("REQUEST-STATUS", &event.rstatus);
+        component.extend("RELATED-TO", &event.related_to);
+        component.extend("RESOURCES", &event.resources);
+        component.extend("RDATE", &event.rdate);
+        component.recurrence_id(event.recurid.as_ref(), &event.iana_prop);
+        unknown!(component, &event.x_prop, &event.iana_prop);
+
+        component.components = event.alarms.iter().map(Self::from).collect();
+
+        component
+    }
+}
+
+impl From<&ikal::VTodo> for Component {
+    fn from(todo: &ikal::VTodo) -> Self {
+        let mut component = Self::new("VTODO");
+
+        component.push("DTSTAMP", &todo.dtstamp);
+        component.push("UID", &todo.uid);
+        component.extend("CLASS", &todo.class);
+        component.extend("COMPLETED", &todo.completed);
+        component.extend("CREATED", &todo.created);
+        component.extend("DTSTART", &todo.dtstart);
+        component.extend("GEO", &todo.geo);
+        component.extend("LAST-MODIFIED", &todo.last_modified);
+        component.extend("LOCATION", &todo.location);
+        component.extend("ORGANIZER", &todo.organizer);
+        component.extend("PERCENT-COMPLETE", &todo.percent_complete);
+        component.extend("PRIORITY", &todo.priority);
+        component.extend("SEQUENCE", &todo.sequence);
+        component.extend("STATUS", &todo.status);
+        component.extend("SUMMARY", &todo.summary);
+        component.extend("URL", &todo.url);
+        component.extend("RRULE", &todo.rrule);
+        component.extend("DUE", &todo.due);
+        component.extend("DURATION", &todo.duration);
+        component.extend("ATTACH", &todo.attach);
+        component.extend("ATTENDEE", &todo.attendee);
+        component.extend("CATEGORIES", &todo.categories);
+        component.extend("COMMENT", &todo.comment);
+        component.extend("CONTACT", &todo.contact);
+        component.extend("EXDATE", &todo.exdate);
+        component.extend("REQUEST-STATUS", &todo.rstatus);
+        component.extend("RELATED-TO", &todo.related_to);
+        component.extend("RESOURCES", &todo.resources);
+        component.extend("RDATE", &todo.rdate);
+        component.recurrence_id(todo.recurid.as_ref(), &todo.iana_prop);
+        unknown!(component, &todo.x_prop, &todo.iana_prop);
+
+        component
+    }
+}
+
+impl From<&ikal::VJournal> for Component {
+    fn from(journal: &ikal::VJournal) -> Self {
+        let mut component = Self::new("VJOURNAL");
+
+        component.push("DTSTAMP", &journal.dtstamp);
+        component.push("UID", &journal.uid);
+        component.extend("CLASS", &journal.class);
+        component.extend("CREATED", &journal.created);
+        component.push("DTSTART", &journal.dtstart);
+        component.extend("LAST-MODIFIED", &journal.last_modified);
+        component.extend("ORGANIZER", &journal.organizer);
+        component.extend("SEQUENCE", &journal.sequence);
+        component.extend("STATUS", &journal.status);
+        component.extend("SUMMARY", &journal.summary);
+        component.extend("URL", &journal.url);
+        component.extend("RRULE", &journal.rrule);
+        component.extend("ATTACH", &journal.attach);
+        component.extend("ATTENDEE", &journal.attendee);
+        component.extend("CATEGORIES", &journal.categories);
+        component.extend("COMMENT", &journal.comment);
+        component.extend("CONTACT", &journal.contact);
+        component.extend("DESCRIPTION", &journal.description);
+        component.extend("EXDATE", &journal.exdate);
+        component.extend("RELATED-TO", &journal.related_to);
+        component.extend("RDATE", &journal.rdate);
+        component.extend("REQUEST-STATUS", &journal.rstatus);
+        component.recurrence_id(journal.recurid.as_ref(), &journal.iana_prop);
+        unknown!(component, &journal.x_prop, &journal.iana_prop);
+
+        component
+    }
+}
+
+impl From<&ikal::VFreebusy> for Component {
+    fn from(freebusy: &ikal::VFreebusy) -> Self {
+        let mut component = Self::new("VFREEBUSY");
+
+        component.push("DTSTAMP", &freebusy.dtstamp);
+        component.push("UID", &freebusy.uid);
+        component.extend("CONTACT", &freebusy.contact);
+        component.extend("DTSTART", &freebusy.dtstart);
+        component.extend("DTEND", &freebusy.dtend);
+        component.extend("ORGANIZER", &freebusy.organizer);
+        component.extend("URL", &freebusy.url);
+        component.extend("ATTENDEE", &freebusy.attendee);
+        component.extend("COMMENT", &freebusy.comment);
+        component.extend("FREEBUSY", &freebusy.freebusy);
+        component.extend("REQUEST-STATUS", &freebusy.rstatus);
+        unknown!(component, &freebusy.x_prop, &freebusy.iana_prop);
+
+        component
+    }
+}
+
+impl From<&ikal::VAlarm> for Component {
+    fn from(alarm: &ikal::VAlarm) -> Self {
+        let mut component = Self::new("VALARM");
+
+        match alarm {
+            ikal::VAlarm::Audio(audio) => {
+                component.push("ACTION", &audio.action);
+                component.push("TRIGGER", &audio.trigger);
+                component.extend("DURATION", &audio.duration);
+                component.extend("REPEAT", &audio.repeat);
+                component.extend("ATTACH", &audio.attach);
+                unknown!(component, &audio.x_prop, &audio.iana_prop);
+            }
+            ikal::VAlarm::Display(display) => {
+                component.push("ACTION", &display.action);
+                component.push("TRIGGER", &display.trigger);
+                component.push("DESCRIPTION", &display.description);
+                component.extend("DURATION", &display.duration);
+                component.extend("REPEAT", &display.repeat);
+                unknown!(component, &display.x_prop, &display.iana_prop);
+            }
+            ikal::VAlarm::Email(email) => {
+                component.push("ACTION", &email.action);
+                component.push("TRIGGER", &email.trigger);
+                component.push("DESCRIPTION", &email.description);
+                component.push("SUMMARY", &email.summary);
+                component.extend("ATTENDEE", &email.attendee);
+                component.extend("DURATION", &email.duration);
+                component.extend("REPEAT", &email.repeat);
+                component.extend("ATTACH", &email.attach);
+                unknown!(component, &email.x_prop, &email.iana_prop);
+            }
+        }
+
+        component
+    }
+}
+
+impl From<&ikal::VTimezone> for Component {
+    fn from(timezone: &ikal::VTimezone) -> Self {
+        let mut component = Self::new("VTIMEZONE");
+
+        component.push("TZID", &timezone.tzid);
+        component.extend("LAST-MODIFIED", &timezone.last_modified);
+        component.extend("TZURL", &timezone.tzurl);
+        unknown!(component, &timezone.x_prop, &timezone.iana_prop);
+
+        component.components = timezone
+            .standard
+            .iter()
+            .map(Self::from)
+            .chain(timezone.daylight.iter().map(Self::from))
+            .collect();
+
+        component
+    }
+}
+
+macro_rules! observance {
+    ($ty:ty, $name:literal) => {
+        impl From<&$ty> for Component {
+            fn from(observance: &$ty) -> Self {
+                let mut component = Self::new($name);
+
+                component.push("DTSTART", &observance.dtstart);
+                component.push("TZOFFSETTO", &observance.tzoffsetto);
+                component.push("TZOFFSETFROM", &observance.tzoffsetfrom);
+                component.extend("RRULE", &observance.rrule);
+                component.extend("COMMENT", &observance.comment);
+                component.extend("RDATE", &observance.rdate);
+                component.extend("TZNAME", &observance.tzname);
+                unknown!(component, &observance.x_prop, &observance.iana_prop);
+
+                component
+            }
+        }
+    };
+}
+
+observance!(ikal::vtimezone::Standard, "STANDARD");
+observance!(ikal::vtimezone::Daylight, "DAYLIGHT");
+
+/**
+ * A value parsed by `ikal`, with the parameters and the text of the property it's written in.
+ */
+trait Value {
+    fn write(&self) -> (Vec<(String, String)>, String);
+}
+
+impl Value for ikal::Text {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        // `ikal` keeps the text as written, escaped
+        (self.params.clone().into_iter().collect(), self.text.clone())
+    }
+}
+
+impl Value for ikal::Uri {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        (self.params.clone().into_iter().collect(), self.uri.clone())
+    }
+}
+
+impl Value for ikal::DateTime {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        (Vec::new(), date_time(self))
+    }
+}
+
+impl Value for ikal::Date {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        match self {
+            ikal::Date::Date(date) => (value_type("DATE"), date.format("%Y%m%d").to_string()),
+            ikal::Date::DateTime(date_time) => date_time.write(),
+        }
+    }
+}
+
+impl Value for chrono::TimeDelta {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        (Vec::new(), duration(*self))
+    }
+}
+
+impl Value for chrono::FixedOffset {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        let offset = self.local_minus_utc();
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs();
+
+        let mut value = format!("{sign}{:02}{:02}", offset / 3_600, offset / 60 % 60);
+
+        if offset % 60 != 0 {
+            value.push_str(&format!("{:02}", offset % 60));
+        }
+
+        (Vec::new(), value)
+    }
+}
+
+impl Value for ikal::Recur {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        let list = |name: &str, values: &[i8]| {
+            let values = values.iter().map(i8::to_string).collect::<Vec<_>>();
+
+            (!values.is_empty()).then(|| format!("{name}={}", values.join(",")))
+        };
+
+        let by_day = self
+            .by_day
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        let parts = [
+            Some(format!("FREQ={}", self.freq)),
+            self.until
+                .as_ref()
+                .map(|x| format!("UNTIL={}", x.write().1)),
+            self.count.map(|x| format!("COUNT={x}")),
+            Some(format!("INTERVAL={}", self.interval)),
+            list("BYSECOND", &self.by_second),
+            list("BYMINUTE", &self.by_minute),
+            list("BYHOUR", &self.by_hour),
+            (!by_day.is_empty()).then(|| format!("BYDAY={}", by_day.join(","))),
+            list("BYMONTHDAY", &self.by_monthday),
+            list("BYYEARDAY", &self.by_yearday),
+            list("BYWEEKNO", &self.by_weekno),
+            list("BYMONTH", &self.by_month),
+            list("BYSETPOS", &self.by_setpos),
+            self.wkst.as_ref().map(|x| format!("WKST={x}")),
+        ];
+
+        (
+            Vec::new(),
+            parts.into_iter().flatten().collect::<Vec<_>>().join(";"),
+        )
+    }
+}
+
+impl Value for ikal::RDate {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        match self {
+            ikal::RDate::Date(dates) => {
+                let params = match dates.first() {
+                    Some(ikal::Date::Date(_)) => value_type("DATE"),
+                    _ => Vec::new(),
+                };
+                let values = dates.iter().map(|x| x.write().1).collect::<Vec<_>>();
+
+                (params, values.join(","))
+            }
+            ikal::RDate::Period(periods) => {
+                let values = periods.iter().map(|x| x.write().1).collect::<Vec<_>>();
+
+                (value_type("PERIOD"), values.join(","))
+            }
+        }
+    }
+}
+
+impl Value for ikal::Period {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        let value = match self {
+            ikal::Period::StartEnd(period) => {
+                format!("{}/{}", date_time(&period.start), date_time(&period.end))
+            }
+            ikal::Period::StartDur(period) => {
+                format!("{}/{}", date_time(&period.start), duration(period.duration))
+            }
+        };
+
+        (Vec::new(), value)
+    }
+}
+
+impl Value for ikal::Trigger {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        match self {
+            ikal::Trigger::DateTime(date_time) => (value_type("DATE-TIME"), date_time.write().1),
+            ikal::Trigger::Duration(delta) => (Vec::new(), duration(*delta)),
+        }
+    }
+}
+
+impl Value for ikal::RequestStatus {
+    fn write(&self) -> (Vec<(String, String)>, String) {
+        let mut value = format!("{:.1};{}", self.statcode, self.statdesc);
+
+        if let Some(extdata) = &self.extdata {
+            value.push(';');
+            value.push_str(extdata);
+        }
+
+        (Vec::new(), value)
+    }
+}
+
+macro_rules! display_value {
+    ($($ty:ty),+) => {
+        $(
+            impl Value for $ty {
+                fn write(&self) -> (Vec<(String, String)>, String) {
+                    (Vec::new(), self.to_string())
+                }
+            }
+        )+
+    };
+}
+
+display_value!(
+    ikal::Class,
+    ikal::Geo,
+    ikal::Status,
+    ikal::TimeTransparency,
+    u8,
+    u32
+);
+
+fn value_type(value: &str) -> Vec<(String, String)> {
+    vec![("VALUE".to_string(), value.to_string())]
+}
+
+/**
+ * `ikal` reads the UTC date-times in the local time zone, they are written back in UTC.
+ */
+fn date_time(date_time: &ikal::DateTime) -> String {
+    match date_time {
+        ikal::DateTime::Naive(naive) => naive.format("%Y%m%dT%H%M%S").to_string(),
+        ikal::DateTime::Local(local) => local.naive_utc().format("%Y%m%dT%H%M%SZ").to_string(),
+    }
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.6>
+ */
+fn duration(duration: chrono::TimeDelta) -> String {
+    let sign = if duration < chrono::TimeDelta::zero() {
+        "-"
+    } else {
+        ""
+    };
+    let seconds = duration.num_seconds().abs();
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    match (days, seconds) {
+        (0, seconds) => format!("{sign}PT{seconds}S"),
+        (days, 0) => format!("{sign}P{days}D"),
+        (days, seconds) => format!("{sign}P{days}DT{seconds}S"),
+    }
+}
+
+/**
+ * Escapes the characters a parameter value can't contain, even quoted.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc6868#section-3>
+ */
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '^' => encoded.push_str("^^"),
+            '"' => encoded.push_str("^'"),
+            '\n' => encoded.push_str("^n"),
+            '\r' => (),
+            c => encoded.push(c),
+        }
+    }
+
+    encoded
+}
+
+/**
+ * Reverts [`encode`], leaving a `^` not followed by an escaped character as is.
+ */
+fn decode(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('^', Some('^')) => '^',
+            ('^', Some('\'')) => '"',
+            ('^', Some('n')) => '\n',
+            _ => {
+                decoded.push(c);
+                continue;
+            }
+        };
+
+        decoded.push(escaped);
+        chars.next();
+    }
+
+    decoded
+}
+
+/**
+ * Parses an iCalendar object, without interpreting the values.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.1>
+ */
+pub fn parse(data: &str) -> Option<Component> {
+    let mut stack: Vec<Component> = Vec::new();
+    let mut root = None;
+
+    for line in unfold(data) {
+        let property = property(&line)?;
+
+        if property.name.eq_ignore_ascii_case("BEGIN") {
+            stack.push(Component {
+                name: property.value.to_ascii_uppercase(),
+                ..Default::default()
+            });
+        } else if property.name.eq_ignore_ascii_case("END") {
+            let component = stack.pop()?;
+
+            if !component.name.eq_ignore_ascii_case(&property.value) {
+                return None;
+            }
+
+            match stack.last_mut() {
+                Some(parent) => parent.components.push(component),
+                None => root = Some(component),
+            }
+        } else {
+            stack.last_mut()?.properties.push(property);
+        }
+    }
+
+    if stack.is_empty() { root } else { None }
+}
+
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in data.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if let Some(continuation) = line.strip_prefix([' ', '\t'])
+            && let Some(last) = lines.last_mut()
+        {
+            last.push_str(continuation);
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+
+    lines
+}
+
+fn property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut value = None;
+
+    for (n, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..n]);
+                start = n + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[start..n]);
+                value = Some(&line[n + 1..]);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|x| x.split_once('='))
+        .map(|(name, value)| (name.to_ascii_uppercase(), decode(value.trim_matches('"'))))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn parse() {
+        let component = super::parse(
+            "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Weekly\r
+  meeting\r
+ATTENDEE;CN=\"Doe: John\";ROLE=CHAIR:mailto:john@example.org\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+        )
+        .unwrap();
+
+        assert_eq!(component.name, "VCALENDAR");
+
+        let event = &component.components[0];
+        assert_eq!(event.property("summary").unwrap().value, "Weekly meeting");
+
+        let attendee = event.property("ATTENDEE").unwrap();
+        assert_eq!(attendee.param("cn"), Some("Doe: John"));
+        assert_eq!(attendee.value, "mailto:john@example.org");
+
+        assert!(super::parse("BEGIN:VCALENDAR\r\nEND:VEVENT\r\n").is_none());
+    }
+
+    #[test]
+    fn display() {
+        let data = format!(
+            "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+ATTENDEE;CN=\"Doe: John\";ROLE=CHAIR:mailto:john@example.org\r
+DESCRIPTION:{}\r
+ {}\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+            "a".repeat(63),
+            "a".repeat(10),
+        );
+
+        let component = super::parse(&data).unwrap();
+        assert_eq!(component.to_string(), data);
+    }
+
+    #[test]
+    fn from_ikal() -> crate::Result {
+        let calendar = crate::ical::VCalendar::try_from(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20120101T000000Z\r
+DTSTART;TZID=Europe/Paris:20120102T090000\r
+DURATION:P1DT30M\r
+RRULE:FREQ=MONTHLY;UNTIL=20121231T230000Z;BYDAY=-1FR\r
+RECURRENCE-ID;RANGE=THISANDFUTURE:20120127T090000\r
+X-CUSTOM;X-PARAM=value:text\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+DESCRIPTION:Reminder\r
+TRIGGER:-PT15M\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            .to_string(),
+        )?;
+
+        let component = super::Component::from(&calendar);
+        assert_eq!(component.property("VERSION").unwrap().value, "2.0");
+
+        let event = &component.components[0];
+        assert_eq!(event.name, "VEVENT");
+        assert_eq!(event.property("DTSTART").unwrap().value, "20120102T090000");
+        assert_eq!(event.property("DURATION").unwrap().value, "P1DT1800S");
+        assert_eq!(
+            event.property("RRULE").unwrap().value,
+            "FREQ=MONTHLY;UNTIL=20121231T230000Z;INTERVAL=1;BYDAY=-1FR"
+        );
+
+        let recurrence_id = event.property("RECURRENCE-ID").unwrap();
+        assert_eq!(recurrence_id.param("RANGE"), Some("THISANDFUTURE"));
+        assert_eq!(
+            event.property("X-CUSTOM").unwrap().param("X-PARAM"),
+            Some("value")
+        );
+
+        let alarm = &event.components[0];
+        assert_eq!(alarm.property("TRIGGER").unwrap().value, "-PT900S");
+
+        Ok(())
+    }
+
+    #[test]
+    fn param_encoding() {
+        let property = super::Property {
+            name: "ATTENDEE".to_string(),
+            params: vec![(
+                "CN".to_string(),
+                "George \"Dubya\" Bush; ^Jr\nTexas".to_string(),
+            )],
+            value: "mailto:gwb@example.org".to_string(),
+        };
+
+        let line = property.to_string();
+        assert_eq!(
+            line,
+            "ATTENDEE;CN=\"George ^'Dubya^' Bush; ^^Jr^nTexas\":mailto:gwb@example.org"
+        );
+        assert_eq!(super::property(&line), Some(property));
+
+        let property = super::property("X-TEST;X-PARAM=a^b^:value").unwrap();
+        assert_eq!(property.param("x-param"), Some("a^b^"));
+    }
+}
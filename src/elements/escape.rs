@@ -0,0 +1,284 @@
+/**
+ * Escapes a text node.
+ *
+ * Carriage returns are written as character references, XML parsers would normalize them to
+ * line feeds otherwise.
+ */
+pub(crate) fn text(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(valid(c)),
+        }
+    }
+
+    escaped
+}
+
+/**
+ * Escapes an attribute value, to be enclosed in double quotes.
+ *
+ * Whitespaces other than spaces are written as character references, XML parsers would
+ * normalize them to spaces otherwise.
+ */
+pub(crate) fn attribute(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(valid(c)),
+        }
+    }
+
+    escaped
+}
+
+/**
+ * Wraps a text node in CDATA sections.
+ *
+ * A section can’t contain `]]>` nor keep a carriage return, so the text is split around them.
+ */
+pub(crate) fn cdata(s: &str) -> String {
+    s.chars()
+        .map(valid)
+        .collect::<String>()
+        .replace("]]>", "]]]]><![CDATA[>")
+        .split('\r')
+        .map(|x| format!("<![CDATA[{x}]]>"))
+        .collect::<Vec<_>>()
+        .join("&#13;")
+}
+
+/**
+ * Makes an element or attribute name valid, without a namespace prefix.
+ *
+ * Characters that can’t appear in a name are replaced by U+FFFD, which can.
+ *
+ * <https://www.w3.org/TR/xml-names/#NT-NCName>
+ */
+pub(crate) fn name(s: &str) -> String {
+    let name = s
+        .chars()
+        .enumerate()
+        .map(|(n, c)| {
+            let valid = match c {
+                ':' => false,
+                '-'
+                | '.'
+                | '0'..='9'
+                | '\u{b7}'
+                | '\u{300}'..='\u{36f}'
+                | '\u{203f}'..='\u{2040}' => n > 0,
+                _ => name_start(c),
+            };
+
+            if valid {
+                c
+            } else {
+                char::REPLACEMENT_CHARACTER
+            }
+        })
+        .collect::<String>();
+
+    if name.is_empty() {
+        char::REPLACEMENT_CHARACTER.to_string()
+    } else {
+        name
+    }
+}
+
+/**
+ * <https://www.w3.org/TR/xml/#NT-NameStartChar>
+ */
+fn name_start(c: char) -> bool {
+    matches!(c,
+        'A'..='Z'
+        | '_'
+        | 'a'..='z'
+        | '\u{c0}'..='\u{d6}'
+        | '\u{d8}'..='\u{f6}'
+        | '\u{f8}'..='\u{2ff}'
+        | '\u{370}'..='\u{37d}'
+        | '\u{37f}'..='\u{1fff}'
+        | '\u{200c}'..='\u{200d}'
+        | '\u{2070}'..='\u{218f}'
+        | '\u{2c00}'..='\u{2fef}'
+        | '\u{3001}'..='\u{d7ff}'
+        | '\u{f900}'..='\u{fdcf}'
+        | '\u{fdf0}'..='\u{fffd}'
+        | '\u{10000}'..='\u{effff}'
+    )
+}
+
+/**
+ * Replaces the characters forbidden by XML 1.0, even as references, by U+FFFD.
+ *
+ * <https://www.w3.org/TR/xml/#charsets>
+ */
+fn valid(c: char) -> char {
+    match c {
+        '\t' | '\n' | '\r' | '\u{20}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'.. => c,
+        _ => char::REPLACEMENT_CHARACTER,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use webdav::ToXml as _;
+
+    const SEED: u64 = 0x6b61_6c64_6176;
+
+    /**
+     * Builds random strings from XML special sequences and arbitrary valid characters.
+     */
+    fn strings(count: usize) -> Vec<String> {
+        const PIECES: &[&str] = &[
+            "]]>",
+            "]",
+            ">",
+            "<",
+            "&",
+            "&amp;",
+            "\"",
+            "'",
+            "<![CDATA[",
+            "\r",
+            "\n",
+            "\r\n",
+            "\t",
+            " ",
+            "a",
+            "é",
+            "🗓",
+            "\u{feff}",
+        ];
+
+        let mut rng = fastrand::Rng::with_seed(SEED);
+
+        (0..count)
+            .map(|_| {
+                (0..rng.usize(1..16))
+                    .map(|_| {
+                        if rng.bool() {
+                            PIECES[rng.usize(..PIECES.len())].to_string()
+                        } else {
+                            let c = rng.char(..);
+                            super::valid(c).to_string()
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            super::text("a < b & \"c\"\r\n"),
+            "a &lt; b &amp; &quot;c&quot;&#13;\n"
+        );
+        assert_eq!(super::attribute("a\tb\nc"), "a&#9;b&#10;c");
+        assert_eq!(super::cdata("a]]>b"), "<![CDATA[a]]]]><![CDATA[>b]]>");
+        assert_eq!(super::cdata("a\r\nb"), "<![CDATA[a]]>&#13;<![CDATA[\nb]]>");
+        assert_eq!(super::text("\u{0}\u{1b}"), "\u{fffd}\u{fffd}");
+        assert_eq!(super::name("calendar-data"), "calendar-data");
+        assert_eq!(
+            super::name("1a b:c/>"),
+            "\u{fffd}a\u{fffd}b\u{fffd}c\u{fffd}\u{fffd}"
+        );
+        assert_eq!(super::name(""), "\u{fffd}");
+    }
+
+    #[test]
+    fn text_match() -> crate::Result {
+        use crate::elements::filter::TextMatch;
+
+        for s in strings(500) {
+            let text_match = TextMatch::new(&s).collation(&s);
+
+            assert_eq!(text_match.to_xml().parse::<TextMatch>()?, text_match);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter() -> crate::Result {
+        use crate::elements::filter::{CompFilter, Filter, ParamFilter, PropFilter};
+
+        for s in strings(500) {
+            let filter = Filter::new().append(
+                CompFilter::new(&s).prop_filter(PropFilter::new(&s).append(ParamFilter::new(&s))),
+            );
+
+            assert_eq!(filter.to_xml().parse::<Filter>()?, filter);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn prop_name() -> crate::Result {
+        use crate::elements::{Element, PropName, Propfind};
+
+        for s in strings(500) {
+            let propfind = Propfind::Prop(vec![PropName::new(&s, &s)]);
+            let xml = Element::parse(&propfind.to_xml())?;
+
+            assert_eq!(xml.children[0].children[0].name.namespace, s);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn element() -> crate::Result {
+        use crate::elements::{Element, PropName};
+
+        for s in strings(500) {
+            let element = Element {
+                name: PropName::new(&s, "property"),
+                attributes: [("value".to_string(), s.clone())].into(),
+                text: s.clone(),
+                children: Vec::new(),
+            };
+
+            assert_eq!(Element::parse(&element.to_xml())?, element);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn mkcalendar() -> crate::Result {
+        use crate::elements::{Element, Mkcalendar};
+
+        for s in strings(500) {
+            let mkcalendar = Mkcalendar {
+                name: Some(s.clone()),
+                description: Some(s.clone()),
+                ..Default::default()
+            };
+
+            let parsed = Mkcalendar::from_element(&Element::parse(&mkcalendar.to_xml())?)?;
+            assert_eq!(parsed.name, mkcalendar.name);
+            assert_eq!(parsed.description, mkcalendar.description);
+        }
+
+        Ok(())
+    }
+}
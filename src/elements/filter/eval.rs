@@ -0,0 +1,1618 @@
+use super::{
+    CompChild, CompFilter, Filter, ParamChild, ParamFilter, PropChild, PropFilter, Test, TextMatch,
+};
+use crate::component::{Component, Property};
+use crate::elements::TimeRange;
+use crate::recurrence::Rule;
+use crate::timezone::{self, Resolver, Timezone};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use std::ops::ControlFlow;
+
+/**
+ * Upper bound of the occurrences expanded for a recurring component.
+ */
+const MAX_OCCURRENCES: usize = 100_000;
+
+impl Filter {
+    /**
+     * Evaluates the filter against a calendar, like a server does for a `calendar-query`.
+     *
+     * The values are read as `ikal` parsed them: it drops the `TZID` parameters, so those
+     * date-times are floating, in the local time zone. Use [`Self::matches_with`] on the calendar
+     * data to keep their time zone. A calendar whose recurrences can't be expanded doesn't match.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7>
+     */
+    pub fn matches(&self, calendar: &ikal::VCalendar) -> bool {
+        match self.evaluate(Component::from(calendar), &Resolver::default()) {
+            Ok(matches) => matches,
+            Err(error) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(%error, "unable to evaluate filter");
+                #[cfg(not(feature = "tracing"))]
+                let _ = error;
+
+                false
+            }
+        }
+    }
+
+    /**
+     * Evaluates the filter against a serialized calendar.
+     *
+     * The date-times are resolved with the `VTIMEZONE` components of the calendar, then with
+     * `resolver`, which also gives the time zone of the dates and floating date-times. Recurrence
+     * rules are fully expanded, along with `RDATE`, `EXDATE` and overridden instances.
+     *
+     * Fails on invalid calendar data, an unknown `TZID`, an invalid recurrence rule, or one with
+     * more than 100 000 occurrences in the time range.
+     */
+    pub fn matches_with(&self, data: &str, resolver: &Resolver) -> crate::Result<bool> {
+        let calendar = crate::component::parse(data)
+            .filter(|x| x.name == "VCALENDAR")
+            .ok_or_else(|| crate::Error::Misc("Invalid calendar data".to_string()))?;
+
+        self.evaluate(calendar, resolver)
+    }
+
+    fn evaluate(&self, calendar: Component, resolver: &Resolver) -> crate::Result<bool> {
+        let mut resolver = resolver.clone();
+
+        for timezone in calendar.components.iter().filter(|x| x.name == "VTIMEZONE") {
+            resolver = resolver.append(Timezone::from_component(timezone)?);
+        }
+
+        if self.comp_filters.is_empty() {
+            return Ok(true);
+        }
+
+        for filter in &self.comp_filters {
+            if comp_filter(filter, std::slice::from_ref(&calendar), None, &resolver)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.1>
+ */
+fn comp_filter(
+    filter: &CompFilter,
+    components: &[Component],
+    parent: Option<&Component>,
+    resolver: &Resolver,
+) -> crate::Result<bool> {
+    let mut candidates = components
+        .iter()
+        .filter(|x| x.name.eq_ignore_ascii_case(&filter.name));
+
+    if filter.children.contains(&CompChild::IsNotDefined) {
+        return Ok(candidates.next().is_none());
+    }
+
+    for component in candidates {
+        let matches = test(filter.test, filter.children.iter(), |child| match child {
+            CompChild::IsNotDefined => Ok(false),
+            CompChild::CompFilter(x) => {
+                comp_filter(x, &component.components, Some(component), resolver)
+            }
+            CompChild::PropFilter(x) => prop_filter(x, component, resolver),
+            CompChild::TimeRange(x) => {
+                component_time_range(x, component, components, parent, resolver)
+            }
+        })?;
+
+        if matches {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.2>
+ */
+fn prop_filter(
+    filter: &PropFilter,
+    component: &Component,
+    resolver: &Resolver,
+) -> crate::Result<bool> {
+    let mut candidates = component
+        .properties
+        .iter()
+        .filter(|x| x.name.eq_ignore_ascii_case(&filter.name));
+
+    if filter.children.contains(&PropChild::IsNotDefined) {
+        return Ok(candidates.next().is_none());
+    }
+
+    for property in candidates {
+        let matches = test(filter.test, filter.children.iter(), |child| match child {
+            PropChild::IsNotDefined => Ok(false),
+            PropChild::ParamFilter(x) => Ok(param_filter(x, property)),
+            PropChild::TextMatch(x) => Ok(text_match(x, &unescape(&property.value))),
+            PropChild::TimeRange(x) => property_time_range(x, property, resolver),
+        })?;
+
+        if matches {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/**
+ * Combines the children conditions, an empty `anyof` matches like an empty `allof`.
+ */
+fn test<T>(
+    test: Test,
+    children: std::slice::Iter<T>,
+    f: impl Fn(&T) -> crate::Result<bool>,
+) -> crate::Result<bool> {
+    let expected = match test {
+        Test::AllOf => false,
+        Test::AnyOf if children.len() == 0 => return Ok(true),
+        Test::AnyOf => true,
+    };
+
+    for child in children {
+        if f(child)? == expected {
+            return Ok(expected);
+        }
+    }
+
+    Ok(!expected)
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.3>
+ */
+fn param_filter(filter: &ParamFilter, property: &Property) -> bool {
+    let value = property.param(&filter.name);
+
+    if filter.children.contains(&ParamChild::IsNotDefined) {
+        return value.is_none();
+    }
+
+    value.is_some_and(|value| {
+        filter.children.iter().all(|child| match child {
+            ParamChild::IsNotDefined => false,
+            ParamChild::TextMatch(x) => text_match(x, value),
+        })
+    })
+}
+
+/**
+ * Matches `value` against a substring, according to the collation. Unknown collations never
+ * match.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.5>
+ */
+fn text_match(filter: &TextMatch, value: &str) -> bool {
+    let matches = match filter.collation.as_deref().unwrap_or("i;ascii-casemap") {
+        "i;octet" => value.contains(&filter.text),
+        "i;ascii-casemap" => value
+            .to_ascii_lowercase()
+            .contains(&filter.text.to_ascii_lowercase()),
+        // https://datatracker.ietf.org/doc/html/rfc5051, without normalization
+        "i;unicode-casemap" => value.to_lowercase().contains(&filter.text.to_lowercase()),
+        _ => return false,
+    };
+
+    matches != filter.negate_condition
+}
+
+/**
+ * Reverts the escaping of `TEXT` values.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.11>
+ */
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+fn start_before(range: &TimeRange, date: DateTime<Utc>) -> bool {
+    range.start.is_none_or(|start| start < date)
+}
+
+fn start_not_after(range: &TimeRange, date: DateTime<Utc>) -> bool {
+    range.start.is_none_or(|start| start <= date)
+}
+
+fn end_after(range: &TimeRange, date: DateTime<Utc>) -> bool {
+    range.end.is_none_or(|end| end > date)
+}
+
+fn end_not_before(range: &TimeRange, date: DateTime<Utc>) -> bool {
+    range.end.is_none_or(|end| end >= date)
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
+ */
+fn property_time_range(
+    range: &TimeRange,
+    property: &Property,
+    resolver: &Resolver,
+) -> crate::Result<bool> {
+    let matches = dates(property, resolver)?
+        .into_iter()
+        .any(|(date, _)| start_not_after(range, date) && end_after(range, date));
+
+    Ok(matches)
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
+ */
+fn component_time_range(
+    range: &TimeRange,
+    component: &Component,
+    siblings: &[Component],
+    parent: Option<&Component>,
+    resolver: &Resolver,
+) -> crate::Result<bool> {
+    let matches = match component.name.as_str() {
+        "VEVENT" => instances(component, siblings, range, resolver)?
+            .iter()
+            .any(|x| event(range, x)),
+        "VTODO" => {
+            let completed = date(component, "COMPLETED", resolver)?;
+            let created = date(component, "CREATED", resolver)?;
+
+            instances(component, siblings, range, resolver)?
+                .iter()
+                .any(|x| todo(range, x, completed, created))
+        }
+        "VJOURNAL" => instances(component, siblings, range, resolver)?
+            .iter()
+            .any(|x| journal(range, x)),
+        "VFREEBUSY" => freebusy(range, component, resolver)?,
+        "VALARM" => match parent {
+            Some(parent) => alarm(range, component, parent, resolver)?,
+            None => false,
+        },
+        _ => false,
+    };
+
+    Ok(matches)
+}
+
+fn event(range: &TimeRange, instance: &Instance) -> bool {
+    let Some(start) = instance.start else {
+        return false;
+    };
+
+    match (instance.end, instance.duration) {
+        (Some(end), _) => start_before(range, end) && end_after(range, start),
+        (None, Some(duration)) if duration > TimeDelta::zero() => {
+            start_before(range, start + duration) && end_after(range, start)
+        }
+        (None, None) if instance.is_date => {
+            start_before(range, start + TimeDelta::days(1)) && end_after(range, start)
+        }
+        (None, _) => start_not_after(range, start) && end_after(range, start),
+    }
+}
+
+fn todo(
+    range: &TimeRange,
+    instance: &Instance,
+    completed: Option<DateTime<Utc>>,
+    created: Option<DateTime<Utc>>,
+) -> bool {
+    match (instance.start, instance.duration, instance.due) {
+        (Some(start), Some(duration), _) => {
+            start_not_after(range, start + duration)
+                && (end_after(range, start) || end_not_before(range, start + duration))
+        }
+        (Some(start), None, Some(due)) => {
+            (start_before(range, due) || start_not_after(range, start))
+                && (end_after(range, start) || end_not_before(range, due))
+        }
+        (Some(start), None, None) => start_not_after(range, start) && end_after(range, start),
+        (None, _, Some(due)) => start_before(range, due) && end_not_before(range, due),
+        (None, _, None) => match (completed, created) {
+            (Some(completed), Some(created)) => {
+                (start_not_after(range, created) || start_not_after(range, completed))
+                    && (end_not_before(range, created) || end_not_before(range, completed))
+            }
+            (Some(completed), None) => {
+                start_not_after(range, completed) && end_not_before(range, completed)
+            }
+            (None, Some(created)) => end_after(range, created),
+            (None, None) => true,
+        },
+    }
+}
+
+fn journal(range: &TimeRange, instance: &Instance) -> bool {
+    let Some(start) = instance.start else {
+        return false;
+    };
+
+    if instance.is_date {
+        start_before(range, start + TimeDelta::days(1)) && end_after(range, start)
+    } else {
+        start_not_after(range, start) && end_after(range, start)
+    }
+}
+
+fn freebusy(range: &TimeRange, component: &Component, resolver: &Resolver) -> crate::Result<bool> {
+    if let (Some(start), Some(end)) = (
+        date(component, "DTSTART", resolver)?,
+        date(component, "DTEND", resolver)?,
+    ) {
+        return Ok(start_not_after(range, end) && end_after(range, start));
+    }
+
+    for property in component.properties.iter().filter(|x| x.name == "FREEBUSY") {
+        let matches = dates(property, resolver)?
+            .into_iter()
+            .filter_map(|(start, end)| Some((start, end?)))
+            .any(|(start, end)| start_before(range, end) && end_after(range, start));
+
+        if matches {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/**
+ * Matches when one of the alarm triggers, including its repetitions, falls in the range, for any
+ * occurrence of the parent component.
+ */
+fn alarm(
+    range: &TimeRange,
+    alarm: &Component,
+    parent: &Component,
+    resolver: &Resolver,
+) -> crate::Result<bool> {
+    let Some(trigger) = alarm.property("TRIGGER") else {
+        return Ok(false);
+    };
+
+    let repeat = alarm
+        .property("REPEAT")
+        .and_then(|x| x.value.parse::<u32>().ok())
+        .unwrap_or_default();
+    let interval = alarm
+        .property("DURATION")
+        .and_then(|x| parse_duration(&x.value))
+        .unwrap_or_default();
+
+    let matches = |time: DateTime<Utc>| {
+        (0..=repeat)
+            .map(|n| time + interval * n as i32)
+            .any(|x| start_not_after(range, x) && end_after(range, x))
+    };
+
+    if trigger.param("VALUE") == Some("DATE-TIME") {
+        return Ok(date(alarm, "TRIGGER", resolver)?.is_some_and(matches));
+    }
+
+    let Some(offset) = parse_duration(&trigger.value) else {
+        return Ok(false);
+    };
+
+    // Alarms may fire before their occurrence, the expansion is widened accordingly
+    let margin = offset.abs() + interval * repeat as i32;
+    let widened = TimeRange {
+        start: range.start.map(|x| x - margin),
+        end: range.end.map(|x| x + margin),
+    };
+
+    let matches = instances(parent, &[], &widened, resolver)?
+        .iter()
+        .any(|instance| {
+            let anchor = if trigger.param("RELATED") == Some("END") {
+                instance.end.or(instance.due).or(instance
+                    .start
+                    .zip(instance.duration)
+                    .map(|(start, duration)| start + duration))
+            } else {
+                instance.start
+            };
+
+            anchor.is_some_and(|anchor| matches(anchor + offset))
+        });
+
+    Ok(matches)
+}
+
+/**
+ * An occurrence of a component.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Instance {
+    start: Option<DateTime<Utc>>,
+    /** The start is a `DATE` value. */
+    is_date: bool,
+    end: Option<DateTime<Utc>>,
+    due: Option<DateTime<Utc>>,
+    duration: Option<TimeDelta>,
+}
+
+impl Instance {
+    fn new(component: &Component, resolver: &Resolver) -> crate::Result<Self> {
+        let start = local_date(component, "DTSTART");
+
+        Ok(Self {
+            start: start.as_ref().map(|x| x.to_utc(resolver)).transpose()?,
+            is_date: matches!(start, Some(timezone::DateTime::Date(_))),
+            end: date(component, "DTEND", resolver)?,
+            due: date(component, "DUE", resolver)?,
+            duration: component
+                .property("DURATION")
+                .and_then(|x| parse_duration(&x.value)),
+        })
+    }
+
+    /**
+     * How long after its start the instance ends, a day for a date without end.
+     */
+    fn extent(&self) -> TimeDelta {
+        let Some(start) = self.start else {
+            return TimeDelta::zero();
+        };
+
+        [
+            self.end.map(|x| x - start),
+            self.due.map(|x| x - start),
+            self.duration,
+            self.is_date.then(|| TimeDelta::days(1)),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(TimeDelta::zero(), TimeDelta::max)
+    }
+
+    /**
+     * The same instance, moved to start at `start`.
+     */
+    fn at(&self, start: DateTime<Utc>) -> Self {
+        let offset = self.start.map(|x| start - x).unwrap_or_default();
+
+        Self {
+            start: Some(start),
+            end: self.end.map(|x| x + offset),
+            due: self.due.map(|x| x + offset),
+            ..*self
+        }
+    }
+}
+
+/**
+ * Occurrences of a component that may overlap `range`.
+ *
+ * The recurrence rule is expanded in the time zone of `DTSTART`, so the occurrences keep their
+ * local time across daylight saving time transitions. The ones ending before the range are only
+ * counted for `COUNT`, and skipped altogether without it.
+ */
+fn instances(
+    component: &Component,
+    siblings: &[Component],
+    range: &TimeRange,
+    resolver: &Resolver,
+) -> crate::Result<Vec<Instance>> {
+    let first = Instance::new(component, resolver)?;
+
+    let (Some(start), Some(rrule)) = (
+        local_date(component, "DTSTART"),
+        component.property("RRULE"),
+    ) else {
+        if component.property("RDATE").is_none() {
+            return Ok(vec![first]);
+        }
+
+        return expand(component, siblings, &first, Vec::new(), resolver);
+    };
+
+    let rule = Rule::parse(&rrule.value, &start)?;
+    let until = match &rule.until {
+        Some(until @ timezone::DateTime::Utc(_)) => Some(until.to_utc(resolver)?),
+        _ => None,
+    };
+
+    let reach = reach(component, siblings, &first, resolver)?;
+    // Local times are less than a day away from UTC
+    let after = range
+        .start
+        .filter(|_| rule.count.is_none())
+        .and_then(|x| x.naive_utc().checked_sub_signed(reach + TimeDelta::days(1)));
+
+    let mut starts = Vec::new();
+    // `DTSTART` always counts as the first occurrence, it’s added by `expand`
+    let mut count = 1;
+
+    rule.occurrences(start.naive(), after, |local| {
+        if local == start.naive() {
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        let date = with_local(&start, local).to_utc(resolver)?;
+
+        let after_until = match (&rule.until, until) {
+            (_, Some(until)) => date > until,
+            (Some(timezone::DateTime::Date(until)), None) => local.date() > *until,
+            (Some(until), None) => local > until.naive(),
+            (None, None) => false,
+        };
+
+        if rule.count.is_some_and(|x| count >= x)
+            || after_until
+            || range.end.is_some_and(|end| date >= end)
+        {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        count += 1;
+
+        let ended = range
+            .start
+            .is_some_and(|x| date.checked_add_signed(reach).is_some_and(|end| end < x));
+
+        if ended {
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        if starts.len() >= MAX_OCCURRENCES {
+            return Err(crate::Error::Recurrence(format!(
+                "{} has more than {MAX_OCCURRENCES} occurrences in the time range",
+                rrule.value
+            )));
+        }
+
+        starts.push(date);
+
+        // Later occurrences can’t change the result of an unbounded range
+        if range.end.is_none() && range.start.is_none_or(|x| date > x) {
+            return Ok(ControlFlow::Break(()));
+        }
+
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    expand(component, siblings, &first, starts, resolver)
+}
+
+/**
+ * How long after its start an occurrence of `component` may end, once moved by the
+ * `RANGE=THISANDFUTURE` overrides.
+ */
+fn reach(
+    component: &Component,
+    siblings: &[Component],
+    first: &Instance,
+    resolver: &Resolver,
+) -> crate::Result<TimeDelta> {
+    let mut reach = first.extent();
+
+    for sibling in overrides(component, siblings) {
+        let Some(property) = sibling.property("RECURRENCE-ID") else {
+            continue;
+        };
+
+        if !property
+            .param("RANGE")
+            .is_some_and(|x| x.eq_ignore_ascii_case("THISANDFUTURE"))
+        {
+            continue;
+        }
+
+        let Some((recurrence_id, _)) = dates(property, resolver)?.first().copied() else {
+            continue;
+        };
+        let overriding = Instance::new(sibling, resolver)?;
+
+        if let Some(moved) = overriding.start {
+            reach = reach.max(moved - recurrence_id + overriding.extent());
+        }
+    }
+
+    Ok(reach)
+}
+
+/**
+ * The siblings of `component` with the same `UID`, which override some of its occurrences.
+ */
+fn overrides<'a>(
+    component: &Component,
+    siblings: &'a [Component],
+) -> impl Iterator<Item = &'a Component> {
+    let name = component.name.clone();
+    let uid = component.property("UID").map(|x| x.value.clone());
+
+    siblings
+        .iter()
+        .filter(move |x| x.name == name && x.property("UID").map(|x| &x.value) == uid.as_ref())
+}
+
+/**
+ * Adds the `RDATE` and removes the `EXDATE` and overridden instances from `starts`.
+ *
+ * The overriding components, siblings with the same `UID` and a `RECURRENCE-ID`, are evaluated on
+ * their own. One with `RANGE=THISANDFUTURE` also moves the following instances.
+ */
+fn expand(
+    component: &Component,
+    siblings: &[Component],
+    first: &Instance,
+    starts: Vec<DateTime<Utc>>,
+    resolver: &Resolver,
+) -> crate::Result<Vec<Instance>> {
+    let values = |name: &str| -> crate::Result<Vec<_>> {
+        let mut values = Vec::new();
+
+        for property in component.properties.iter().filter(|x| x.name == name) {
+            values.extend(dates(property, resolver)?);
+        }
+
+        Ok(values)
+    };
+
+    let mut instances = starts.into_iter().map(|x| first.at(x)).collect::<Vec<_>>();
+
+    if first.start.is_some() && !instances.iter().any(|x| x.start == first.start) {
+        instances.insert(0, *first);
+    }
+
+    for (start, end) in values("RDATE")? {
+        let instance = first.at(start);
+
+        instances.push(match end {
+            Some(end) => Instance {
+                end: Some(end),
+                duration: None,
+                ..instance
+            },
+            None => instance,
+        });
+    }
+
+    if component.property("RECURRENCE-ID").is_some() {
+        return Ok(instances);
+    }
+
+    let mut excluded = values("EXDATE")?
+        .into_iter()
+        .map(|(x, _)| x)
+        .collect::<Vec<_>>();
+    let mut following = Vec::new();
+
+    for sibling in overrides(component, siblings) {
+        let Some(property) = sibling.property("RECURRENCE-ID") else {
+            continue;
+        };
+        let Some((recurrence_id, _)) = dates(property, resolver)?.first().copied() else {
+            continue;
+        };
+
+        excluded.push(recurrence_id);
+
+        if property
+            .param("RANGE")
+            .is_some_and(|x| x.eq_ignore_ascii_case("THISANDFUTURE"))
+        {
+            following.push((recurrence_id, Instance::new(sibling, resolver)?));
+        }
+    }
+
+    following.sort_by_key(|(recurrence_id, _)| *recurrence_id);
+
+    let instances = instances
+        .into_iter()
+        .filter(|x| x.start.is_none_or(|start| !excluded.contains(&start)))
+        .map(|instance| {
+            let Some(start) = instance.start else {
+                return instance;
+            };
+
+            // The last override before this instance applies, shifted like its own instance
+            match following.iter().rev().find(|(x, _)| *x < start) {
+                Some((recurrence_id, overriding)) => match overriding.start {
+                    Some(moved) => overriding.at(start + (moved - *recurrence_id)),
+                    None => instance,
+                },
+                None => instance,
+            }
+        })
+        .collect();
+
+    Ok(instances)
+}
+
+/**
+ * The start of a date, date-time or period value, and the end of a period.
+ */
+type Period = (DateTime<Utc>, Option<DateTime<Utc>>);
+
+/**
+ * The `name` property of `component`, with its time zone.
+ */
+fn local_date(component: &Component, name: &str) -> Option<timezone::DateTime> {
+    component
+        .property(name)
+        .and_then(|x| timezone::DateTime::parse(&x.value, x.param("TZID")).ok())
+}
+
+/**
+ * The `name` property of `component`, in UTC.
+ */
+fn date(
+    component: &Component,
+    name: &str,
+    resolver: &Resolver,
+) -> crate::Result<Option<DateTime<Utc>>> {
+    local_date(component, name)
+        .map(|x| x.to_utc(resolver))
+        .transpose()
+}
+
+/**
+ * The same date-time at another local time.
+ */
+fn with_local(datetime: &timezone::DateTime, local: NaiveDateTime) -> timezone::DateTime {
+    use timezone::DateTime::*;
+
+    match datetime {
+        Date(_) => Date(local.date()),
+        Floating(_) => Floating(local),
+        Utc(_) => Utc(local),
+        Zoned { tzid, .. } => Zoned {
+            datetime: local,
+            tzid: tzid.clone(),
+        },
+    }
+}
+
+/**
+ * Parses a comma separated list of dates, date-times or periods, in UTC, with the end of the
+ * periods. Invalid values are skipped.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.9>
+ */
+fn dates(property: &Property, resolver: &Resolver) -> crate::Result<Vec<Period>> {
+    let tzid = property.param("TZID");
+    let mut dates = Vec::new();
+
+    for value in property.value.split(',') {
+        let (start, end) = match value.split_once('/') {
+            Some((start, end)) => (start, Some(end)),
+            None => (value, None),
+        };
+
+        let Ok(start) = timezone::DateTime::parse(start, tzid) else {
+            continue;
+        };
+        let start = start.to_utc(resolver)?;
+
+        let end = match end {
+            Some(end) => match parse_duration(end) {
+                Some(duration) => Some(start + duration),
+                None => timezone::DateTime::parse(end, tzid)
+                    .ok()
+                    .map(|x| x.to_utc(resolver))
+                    .transpose()?,
+            },
+            None => None,
+        };
+
+        dates.push((start, end));
+    }
+
+    Ok(dates)
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.6>
+ */
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim();
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+
+    for c in value.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' => continue,
+            'W' => TimeDelta::weeks(1),
+            'D' => TimeDelta::days(1),
+            'H' => TimeDelta::hours(1),
+            'M' => TimeDelta::minutes(1),
+            'S' => TimeDelta::seconds(1),
+            _ => return None,
+        };
+
+        duration += unit * number.parse().ok()?;
+        number.clear();
+    }
+
+    Some(duration * sign)
+}
+
+#[cfg(test)]
+mod test {
+    fn matches(filter: crate::elements::Filter, object: &str) -> bool {
+        filter.matches_with(object, &resolver()).unwrap()
+    }
+
+    fn resolver() -> crate::timezone::Resolver {
+        crate::timezone::Resolver::new().floating(chrono::Utc)
+    }
+
+    fn range(start: &str, end: &str) -> crate::elements::TimeRange {
+        let date = |x: &str| {
+            chrono::NaiveDateTime::parse_from_str(x, "%Y%m%dT%H%M%S")
+                .ok()
+                .map(|x| x.and_utc())
+        };
+
+        crate::elements::TimeRange {
+            start: date(start),
+            end: date(end),
+        }
+    }
+
+    fn event_filter(start: &str, end: &str) -> crate::elements::Filter {
+        crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    time_range: range(start, end),
+                }
+            }
+        }
+    }
+
+    const EVENT: &str = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+SUMMARY:Weekly meeting\\, room 2\r
+ATTENDEE;PARTSTAT=ACCEPTED:mailto:john@example.org\r
+DTSTART:20120101T120000Z\r
+DURATION:PT1H\r
+RRULE:FREQ=WEEKLY;UNTIL=20120301T120000Z\r
+EXDATE:20120108T120000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:1\r
+RECURRENCE-ID:20120115T120000Z\r
+SUMMARY:Weekly meeting\r
+DTSTART:20120116T120000Z\r
+DURATION:PT1H\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn comp_filter() {
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {}
+                }
+            },
+            EVENT
+        ));
+        assert!(!matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VTODO") {}
+                }
+            },
+            EVENT
+        ));
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VTODO") {
+                        is_not_defined: true,
+                    }
+                }
+            },
+            EVENT
+        ));
+    }
+
+    #[test]
+    fn prop_filter() {
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("SUMMARY") {
+                            text_match: TextMatch::new("MEETING, ROOM"),
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+        assert!(!matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("SUMMARY") {
+                            text_match: TextMatch::new("MEETING") {
+                                collation: "i;octet",
+                            }
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("LOCATION") {
+                            is_not_defined: true,
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("ATTENDEE") {
+                            ParamFilter::new("PARTSTAT") {
+                                TextMatch::new("accepted") {}
+                            }
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+        assert!(!matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("ATTENDEE") {
+                            ParamFilter::new("PARTSTAT") {
+                                is_not_defined: true,
+                            }
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        prop_filter: PropFilter::new("ATTENDEE") {
+                            ParamFilter::new("ROLE") {
+                                is_not_defined: true,
+                            }
+                        }
+                    }
+                }
+            },
+            EVENT
+        ));
+    }
+
+    #[test]
+    fn any_of() {
+        let filter = |summary: &str, attendee: &str| {
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        test: Test::AnyOf,
+                        prop_filter: PropFilter::new("SUMMARY") {
+                            text_match: TextMatch::new(summary),
+                        },
+                        prop_filter: PropFilter::new("ATTENDEE") {
+                            text_match: TextMatch::new(attendee),
+                        },
+                    }
+                }
+            }
+        };
+
+        assert!(matches(filter("meeting", "jane"), EVENT));
+        assert!(matches(filter("lunch", "john"), EVENT));
+        assert!(!matches(filter("lunch", "jane"), EVENT));
+
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VTODO") {}
+                },
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {}
+                },
+            },
+            EVENT
+        ));
+        assert!(matches(
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        test: Test::AnyOf,
+                    }
+                }
+            },
+            EVENT
+        ));
+    }
+
+    #[test]
+    fn text_match() {
+        use crate::elements::filter::TextMatch;
+
+        assert!(super::text_match(&TextMatch::new("ÉTÉ"), "ÉTÉ"));
+        assert!(!super::text_match(&TextMatch::new("été"), "ÉTÉ"));
+        assert!(super::text_match(
+            &TextMatch::new("été").collation("i;unicode-casemap"),
+            "ÉTÉ"
+        ));
+        assert!(super::text_match(
+            &TextMatch::new("meeting").negate_condition(true),
+            "Lunch"
+        ));
+        assert!(!super::text_match(
+            &TextMatch::new("lunch").collation("i;unknown"),
+            "Lunch"
+        ));
+    }
+
+    #[test]
+    fn time_range() {
+        assert!(matches(event_filter("20120201T000000", ""), EVENT));
+        assert!(matches(
+            event_filter("20111201T000000", "20120101T123000"),
+            EVENT
+        ));
+        assert!(!matches(
+            event_filter("20111201T000000", "20120101T120000"),
+            EVENT
+        ));
+        assert!(!matches(
+            event_filter("20120401T000000", "20120501T000000"),
+            EVENT
+        ));
+        assert!(matches(
+            event_filter("20120122T000000", "20120123T000000"),
+            EVENT
+        ));
+        assert!(!matches(
+            event_filter("20120123T000000", "20120129T000000"),
+            EVENT
+        ));
+    }
+
+    #[test]
+    fn recurrence() {
+        // Excluded
+        assert!(!matches(
+            event_filter("20120108T000000", "20120109T000000"),
+            EVENT
+        ));
+        // Overridden
+        assert!(!matches(
+            event_filter("20120115T000000", "20120116T000000"),
+            EVENT
+        ));
+        assert!(matches(
+            event_filter("20120116T000000", "20120117T000000"),
+            EVENT
+        ));
+
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:2\r
+DTSTART;VALUE=DATE:20120131\r
+RRULE:FREQ=MONTHLY;COUNT=3\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        // Monthly on the 31st: January, March and May
+        assert!(!matches(
+            event_filter("20120201T000000", "20120301T000000"),
+            event
+        ));
+        assert!(matches(
+            event_filter("20120531T000000", "20120601T000000"),
+            event
+        ));
+        assert!(!matches(
+            event_filter("20120701T000000", "20120801T000000"),
+            event
+        ));
+
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:3\r
+DTSTART:20120102T090000Z\r
+RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        // Every other week on Monday and Wednesday
+        assert!(matches(
+            event_filter("20120104T000000", "20120105T000000"),
+            event
+        ));
+        assert!(!matches(
+            event_filter("20120109T000000", "20120112T000000"),
+            event
+        ));
+        assert!(matches(event_filter("20200101T000000", ""), event));
+    }
+
+    const PARIS: &str = "BEGIN:VTIMEZONE\r
+TZID:Europe/Paris\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+DTSTART:19810329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19961027T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+    fn event(properties: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\n{PARIS}BEGIN:VEVENT\r\nUID:1\r\n{properties}END:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+    }
+
+    #[test]
+    fn timezone() {
+        let event = event(
+            "DTSTART;TZID=Europe/Paris:20120322T120000\r
+DURATION:PT30M\r
+RRULE:FREQ=WEEKLY;COUNT=2\r
+EXDATE;TZID=Europe/Paris:20120329T120000\r
+RDATE;VALUE=PERIOD:20120405T080000Z/20120405T090000Z\r
+",
+        );
+
+        // 12:00 in Paris is 11:00 UTC in winter, 10:00 UTC in summer
+        assert!(matches(
+            event_filter("20120322T110000", "20120322T111500"),
+            &event
+        ));
+        assert!(!matches(
+            event_filter("20120322T120000", "20120322T123000"),
+            &event
+        ));
+        assert!(!matches(
+            event_filter("20120329T000000", "20120330T000000"),
+            &event
+        ));
+        assert!(matches(
+            event_filter("20120405T084500", "20120405T085000"),
+            &event
+        ));
+
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:20120101T120000\r
+DURATION:PT1H\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let resolver = crate::timezone::Resolver::new()
+            .floating(chrono::FixedOffset::east_opt(3_600 * 5).unwrap());
+        let filter = event_filter("20120101T070000", "20120101T080000");
+
+        assert!(filter.matches_with(event, &resolver).unwrap());
+        assert!(!matches(filter, event));
+    }
+
+    #[test]
+    fn daylight_saving_time() {
+        let event = event(
+            "DTSTART;TZID=Europe/Paris:20120320T120000\r
+DURATION:PT30M\r
+RRULE:FREQ=DAILY\r
+",
+        );
+
+        assert!(matches(
+            event_filter("20120324T110000", "20120324T111500"),
+            &event
+        ));
+        assert!(matches(
+            event_filter("20120326T100000", "20120326T101500"),
+            &event
+        ));
+        assert!(!matches(
+            event_filter("20120326T110000", "20120326T111500"),
+            &event
+        ));
+    }
+
+    #[test]
+    fn rules() {
+        let matches = |rule: &str, start: &str, end: &str| {
+            let event = format!(
+                "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:19970101T090000Z\r
+DURATION:PT1H\r
+RRULE:{rule}\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            );
+
+            matches(event_filter(start, end), &event)
+        };
+        let day = |rule: &str, day: &str| {
+            matches(rule, &format!("{day}T000000"), &format!("{day}T235959"))
+        };
+
+        // The second Tuesday of the month
+        let rule = "FREQ=MONTHLY;BYDAY=2TU";
+        assert!(day(rule, "19970114"));
+        assert!(!day(rule, "19970107"));
+        assert!(day(rule, "19970211"));
+
+        // The last weekday of the month
+        let rule = "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1";
+        assert!(day(rule, "19970131"));
+        assert!(day(rule, "19970228"));
+        assert!(day(rule, "19970530"));
+        assert!(!day(rule, "19970531"));
+
+        // Friday the 13th
+        let rule = "FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13";
+        assert!(day(rule, "19970613"));
+        assert!(!day(rule, "19970513"));
+
+        // The day before the last day of the month
+        let rule = "FREQ=MONTHLY;BYMONTHDAY=-2";
+        assert!(day(rule, "19970227"));
+        assert!(!day(rule, "19970228"));
+
+        // The first Sunday of March and the last Thursday of the year
+        let rule = "FREQ=YEARLY;BYMONTH=3;BYDAY=1SU";
+        assert!(day(rule, "19980301"));
+        assert!(!day(rule, "19980308"));
+        let rule = "FREQ=YEARLY;BYDAY=-1TH";
+        assert!(day(rule, "19971225"));
+        assert!(!day(rule, "19971218"));
+
+        let rule = "FREQ=YEARLY;BYYEARDAY=1,100,200";
+        assert!(day(rule, "19970410"));
+        assert!(day(rule, "19980719"));
+        assert!(!day(rule, "19970411"));
+
+        // Monday of the week 20
+        let rule = "FREQ=YEARLY;BYWEEKNO=20;BYDAY=MO";
+        assert!(day(rule, "19970512"));
+        assert!(day(rule, "19980511"));
+        assert!(day(rule, "19990517"));
+        assert!(!day(rule, "19990510"));
+
+        // Every 20 minutes from 9:00 to 16:40
+        let rule = "FREQ=DAILY;BYHOUR=9,10,11,12,13,14,15,16;BYMINUTE=0,20,40";
+        assert!(matches(rule, "19970105T164000", "19970105T164100"));
+        assert!(!matches(rule, "19970105T174500", "19970105T174600"));
+
+        let rule = "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29;COUNT=2";
+        assert!(day(rule, "20000229"));
+        assert!(!day(rule, "20040229"));
+    }
+
+    #[test]
+    fn invalid_rule() {
+        let filter = event_filter("20120101T000000", "20120201T000000");
+
+        for rule in [
+            "FREQ=MONTHLY;BYDAY=1MO;BYEASTER=0",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=DAILY;BYMONTHDAY=0",
+            "BYDAY=MO",
+        ] {
+            let event = format!(
+                "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:20120101T090000Z\r
+RRULE:{rule}\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            );
+
+            assert!(matches!(
+                filter.matches_with(&event, &resolver()),
+                Err(crate::Error::Recurrence(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn too_many_occurrences() {
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:20120101T000000Z\r
+RRULE:FREQ=SECONDLY\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+        assert!(matches!(
+            event_filter("20130101T000000", "20130103T000000").matches_with(event, &resolver()),
+            Err(crate::Error::Recurrence(_))
+        ));
+    }
+
+    #[test]
+    fn occurrences_before_range() {
+        // More than 100 000 occurrences before the range
+        let hourly = event("DTSTART:20150101T001500Z\r\nDURATION:PT30M\r\nRRULE:FREQ=HOURLY\r\n");
+
+        assert!(matches(
+            event_filter("20270601T122000", "20270601T122500"),
+            &hourly
+        ));
+        assert!(!matches(
+            event_filter("20270601T125000", "20270601T130000"),
+            &hourly
+        ));
+
+        // More than 1 000 000 periods before the range
+        let minutely = event("DTSTART:20150101T000000Z\r\nRRULE:FREQ=MINUTELY\r\n");
+
+        assert!(matches(
+            event_filter("20270601T122000", "20270601T122010"),
+            &minutely
+        ));
+        assert!(!matches(
+            event_filter("20270601T122030", "20270601T122040"),
+            &minutely
+        ));
+
+        // An occurrence started before the range
+        let monthly = event("DTSTART:20150101T000000Z\r\nDURATION:P10D\r\nRRULE:FREQ=MONTHLY\r\n");
+
+        assert!(matches(
+            event_filter("20270605T000000", "20270606T000000"),
+            &monthly
+        ));
+        assert!(!matches(
+            event_filter("20270615T000000", "20270616T000000"),
+            &monthly
+        ));
+
+        // The occurrences before the range still count
+        let counted =
+            event("DTSTART:20150101T001500Z\r\nDURATION:PT30M\r\nRRULE:FREQ=HOURLY;COUNT=3\r\n");
+
+        assert!(matches(
+            event_filter("20150101T022000", "20150101T022500"),
+            &counted
+        ));
+        assert!(!matches(
+            event_filter("20150101T032000", "20150101T032500"),
+            &counted
+        ));
+    }
+
+    #[test]
+    fn this_and_future() {
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:20120102T090000Z\r
+DURATION:PT1H\r
+RRULE:FREQ=DAILY;COUNT=10\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:1\r
+RECURRENCE-ID;RANGE=THISANDFUTURE:20120105T090000Z\r
+DTSTART:20120105T140000Z\r
+DURATION:PT1H\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+        assert!(matches(
+            event_filter("20120104T090000", "20120104T100000"),
+            event
+        ));
+        assert!(!matches(
+            event_filter("20120107T090000", "20120107T100000"),
+            event
+        ));
+        assert!(matches(
+            event_filter("20120107T140000", "20120107T150000"),
+            event
+        ));
+    }
+
+    #[test]
+    fn todo() {
+        let filter = |start: &str, end: &str| {
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VTODO") {
+                        time_range: range(start, end),
+                    }
+                }
+            }
+        };
+
+        let todo = "BEGIN:VCALENDAR\r
+BEGIN:VTODO\r
+UID:1\r
+DUE:20120110T000000Z\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+        assert!(matches(filter("20120109T000000", "20120110T000000"), todo));
+        assert!(!matches(filter("20120110T000000", "20120111T000000"), todo));
+
+        let todo = "BEGIN:VCALENDAR\r
+BEGIN:VTODO\r
+UID:1\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+        assert!(matches(filter("20120110T000000", "20120111T000000"), todo));
+    }
+
+    #[test]
+    fn alarm() {
+        let filter = |start: &str, end: &str| {
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VEVENT") {
+                        CompFilter::new("VALARM") {
+                            time_range: range(start, end),
+                        }
+                    }
+                }
+            }
+        };
+
+        let event = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTART:20120101T120000Z\r
+RRULE:FREQ=DAILY\r
+BEGIN:VALARM\r
+TRIGGER:-PT15M\r
+ACTION:DISPLAY\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        assert!(matches(filter("20120103T114000", "20120103T115000"), event));
+        assert!(!matches(
+            filter("20120103T115000", "20120103T120000"),
+            event
+        ));
+    }
+
+    #[test]
+    fn vcalendar() -> crate::Result {
+        let calendar = crate::ical::vcalendar! {
+            version: "2.0",
+            prodid: "-//kaldav//EN",
+            events: [
+                {
+                    uid: "19970610T172345Z-AF23B2@example.com",
+                    dtstamp: "19970610T172345Z",
+                    dtstart: "19970714T170000Z",
+                    dtend: "19970715T040000Z",
+                    summary: "Bastille Day Party",
+                }
+            ],
+        }?;
+
+        assert!(event_filter("19970714T000000", "19970715T000000").matches(&calendar));
+        assert!(!event_filter("19970716T000000", "").matches(&calendar));
+
+        let calendar = crate::ical::VCalendar::try_from(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20120101T000000Z\r
+DTSTART:20120101T120000Z\r
+DURATION:PT1H\r
+RRULE:FREQ=WEEKLY;COUNT=4\r
+EXDATE:20120108T120000Z\r
+SUMMARY:Weekly meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            .to_string(),
+        )?;
+
+        assert!(event_filter("20120115T000000", "20120116T000000").matches(&calendar));
+        assert!(!event_filter("20120108T000000", "20120109T000000").matches(&calendar));
+        assert!(!event_filter("20120129T000000", "20120130T000000").matches(&calendar));
+        // The UTC date-times are kept as such, whatever the local time zone
+        assert!(event_filter("20120115T125000", "20120115T130500").matches(&calendar));
+        assert!(!event_filter("20120115T130000", "20120115T131000").matches(&calendar));
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        text_match: TextMatch::new("meeting"),
+                    }
+                }
+            }
+        };
+        assert!(filter.matches(&calendar));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(matches!(
+            event_filter("", "").matches_with("BEGIN:VCALENDAR\r\nEND:VEVENT\r\n", &resolver()),
+            Err(crate::Error::Misc(_))
+        ));
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(
+            super::parse_duration("P1DT2H"),
+            Some(chrono::TimeDelta::hours(26))
+        );
+        assert_eq!(
+            super::parse_duration("-PT15M"),
+            Some(chrono::TimeDelta::minutes(-15))
+        );
+        assert_eq!(super::parse_duration("1H"), None);
+    }
+}
@@ -0,0 +1,1121 @@
+mod eval;
+
+use super::escape;
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    /**
+     * Top-level alternatives, an object matches the filter if it matches one of them.
+     *
+     * RFC 4791 allows exactly one top-level `comp-filter`: a filter with several isn’t valid
+     * once serialized. [`crate::Calendar::search`] sends each one as its own query, see also
+     * [`Filter::alternatives`].
+     */
+    pub comp_filters: Vec<CompFilter>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn append(mut self, comp_filter: CompFilter) -> Self {
+        self.comp_filters.push(comp_filter);
+
+        self
+    }
+
+    /**
+     * Checks the filter against the RFC 4791 grammar: a `comp-filter` is required and
+     * `is-not-defined` excludes the other conditions, see [`CompFilter::validate`].
+     */
+    pub fn validate(&self) -> crate::Result {
+        if self.comp_filters.is_empty() {
+            return Err(invalid("filter", "a comp-filter is required"));
+        }
+
+        self.comp_filters.iter().try_for_each(CompFilter::validate)
+    }
+
+    /**
+     * Rewrites the filter as plain RFC 4791 filters, with a single `comp-filter` and without
+     * `anyof` test. An object matches the filter if it matches one of them.
+     */
+    pub fn alternatives(&self) -> Vec<Self> {
+        self.comp_filters
+            .iter()
+            .flat_map(CompFilter::alternatives)
+            .map(|x| Self::new().append(x))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&super::Element::parse_fragment(s)?)
+    }
+}
+
+impl webdav::ToXml for Filter {
+    fn to_xml(&self) -> String {
+        format!("<c:filter>{}</c:filter>", self.comp_filters.to_xml())
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.1>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompFilter {
+    pub name: String,
+    pub test: Test,
+    pub children: Vec<CompChild>,
+}
+
+impl CompFilter {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            test: Test::default(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn append(mut self, comp_filter: Self) -> Self {
+        self.children.push(CompChild::CompFilter(comp_filter));
+
+        self
+    }
+
+    pub fn is_not_defined(mut self, value: bool) -> Self {
+        self.children.retain(|x| x != &CompChild::IsNotDefined);
+
+        if value {
+            self.children.insert(0, CompChild::IsNotDefined);
+        }
+
+        self
+    }
+
+    pub fn prop_filter(mut self, prop_filter: PropFilter) -> Self {
+        self.children.push(CompChild::PropFilter(prop_filter));
+
+        self
+    }
+
+    pub fn time_range(mut self, time_range: super::TimeRange) -> Self {
+        self.children.push(CompChild::TimeRange(time_range));
+
+        self
+    }
+
+    pub fn test(mut self, test: Test) -> Self {
+        self.test = test;
+
+        self
+    }
+
+    /**
+     * Checks the RFC 4791 grammar, `(is-not-defined | (time-range?, prop-filter*,
+     * comp-filter*))`, recursively. Several time ranges are allowed with the `anyof` test.
+     */
+    pub fn validate(&self) -> crate::Result {
+        let element = format!("comp-filter {}", self.name);
+
+        validate_name(&element, &self.name)?;
+
+        let count = |f: fn(&CompChild) -> bool| self.children.iter().filter(|x| f(x)).count();
+
+        if count(|x| matches!(x, CompChild::IsNotDefined)) > 0 && self.children.len() > 1 {
+            return Err(invalid(
+                &element,
+                "is-not-defined excludes other conditions",
+            ));
+        }
+
+        if self.test == Test::AllOf && count(|x| matches!(x, CompChild::TimeRange(_))) > 1 {
+            return Err(invalid(&element, "only one time-range is allowed"));
+        }
+
+        for child in &self.children {
+            match child {
+                CompChild::CompFilter(x) => x.validate()?,
+                CompChild::PropFilter(x) => x.validate()?,
+                CompChild::TimeRange(x) => x.validate()?,
+                CompChild::IsNotDefined => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn alternatives(&self) -> Vec<Self> {
+        let children = self.children.iter().map(|child| match child {
+            CompChild::CompFilter(x) => x
+                .alternatives()
+                .into_iter()
+                .map(CompChild::CompFilter)
+                .collect(),
+            CompChild::PropFilter(x) => x
+                .alternatives()
+                .into_iter()
+                .map(CompChild::PropFilter)
+                .collect(),
+            CompChild::IsNotDefined | CompChild::TimeRange(_) => vec![child.clone()],
+        });
+
+        combine(self.test, children)
+            .into_iter()
+            .map(|children| Self {
+                name: self.name.clone(),
+                test: Test::AllOf,
+                children,
+            })
+            .collect()
+    }
+}
+
+impl std::str::FromStr for CompFilter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&super::Element::parse_fragment(s)?)
+    }
+}
+
+impl webdav::ToXml for CompFilter {
+    fn to_xml(&self) -> String {
+        if self.children.is_empty() {
+            return format!(
+                "<c:comp-filter name=\"{}\"{} />",
+                escape::attribute(&self.name),
+                self.test.as_attribute()
+            );
+        }
+
+        format!(
+            "<c:comp-filter name=\"{}\"{}>{}</c:comp-filter>",
+            escape::attribute(&self.name),
+            self.test.as_attribute(),
+            self.children.to_xml(),
+        )
+    }
+}
+
+/// A condition of a [`CompFilter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompChild {
+    /// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.4>
+    IsNotDefined,
+    TimeRange(super::TimeRange),
+    PropFilter(PropFilter),
+    CompFilter(CompFilter),
+}
+
+impl webdav::ToXml for CompChild {
+    fn to_xml(&self) -> String {
+        match self {
+            Self::IsNotDefined => IS_NOT_DEFINED.to_string(),
+            Self::TimeRange(x) => x.to_xml(),
+            Self::PropFilter(x) => x.to_xml(),
+            Self::CompFilter(x) => x.to_xml(),
+        }
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.2>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropFilter {
+    pub name: String,
+    pub test: Test,
+    pub children: Vec<PropChild>,
+}
+
+impl PropFilter {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            test: Test::default(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn append(mut self, param_filter: ParamFilter) -> Self {
+        self.children.push(PropChild::ParamFilter(param_filter));
+
+        self
+    }
+
+    pub fn is_not_defined(mut self, value: bool) -> Self {
+        self.children.retain(|x| x != &PropChild::IsNotDefined);
+
+        if value {
+            self.children.insert(0, PropChild::IsNotDefined);
+        }
+
+        self
+    }
+
+    pub fn text_match(mut self, text_match: TextMatch) -> Self {
+        self.children.push(PropChild::TextMatch(text_match));
+
+        self
+    }
+
+    pub fn time_range(mut self, time_range: super::TimeRange) -> Self {
+        self.children.push(PropChild::TimeRange(time_range));
+
+        self
+    }
+
+    pub fn test(mut self, test: Test) -> Self {
+        self.test = test;
+
+        self
+    }
+
+    /**
+     * Checks the RFC 4791 grammar, `(is-not-defined | ((time-range | text-match)?,
+     * param-filter*))`, recursively. Several conditions are allowed with the `anyof` test.
+     */
+    pub fn validate(&self) -> crate::Result {
+        let element = format!("prop-filter {}", self.name);
+
+        validate_name(&element, &self.name)?;
+
+        let count = |f: fn(&PropChild) -> bool| self.children.iter().filter(|x| f(x)).count();
+
+        if count(|x| matches!(x, PropChild::IsNotDefined)) > 0 && self.children.len() > 1 {
+            return Err(invalid(
+                &element,
+                "is-not-defined excludes other conditions",
+            ));
+        }
+
+        if self.test == Test::AllOf
+            && count(|x| matches!(x, PropChild::TimeRange(_) | PropChild::TextMatch(_))) > 1
+        {
+            return Err(invalid(
+                &element,
+                "only one time-range or text-match is allowed",
+            ));
+        }
+
+        for child in &self.children {
+            match child {
+                PropChild::ParamFilter(x) => x.validate()?,
+                PropChild::TimeRange(x) => x.validate()?,
+                PropChild::IsNotDefined | PropChild::TextMatch(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn alternatives(&self) -> Vec<Self> {
+        let children = self.children.iter().map(|x| vec![x.clone()]);
+
+        combine(self.test, children)
+            .into_iter()
+            .map(|children| Self {
+                name: self.name.clone(),
+                test: Test::AllOf,
+                children,
+            })
+            .collect()
+    }
+}
+
+impl std::str::FromStr for PropFilter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&super::Element::parse_fragment(s)?)
+    }
+}
+
+impl webdav::ToXml for PropFilter {
+    fn to_xml(&self) -> String {
+        if self.children.is_empty() {
+            format!(
+                "<c:prop-filter name=\"{}\"{} />",
+                escape::attribute(&self.name),
+                self.test.as_attribute()
+            )
+        } else {
+            format!(
+                "<c:prop-filter name=\"{}\"{}>{}</c:prop-filter>",
+                escape::attribute(&self.name),
+                self.test.as_attribute(),
+                self.children.to_xml(),
+            )
+        }
+    }
+}
+
+/// A condition of a [`PropFilter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropChild {
+    /// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.4>
+    IsNotDefined,
+    TimeRange(super::TimeRange),
+    TextMatch(TextMatch),
+    ParamFilter(ParamFilter),
+}
+
+impl webdav::ToXml for PropChild {
+    fn to_xml(&self) -> String {
+        match self {
+            Self::IsNotDefined => IS_NOT_DEFINED.to_string(),
+            Self::TimeRange(x) => x.to_xml(),
+            Self::TextMatch(x) => x.to_xml(),
+            Self::ParamFilter(x) => x.to_xml(),
+        }
+    }
+}
+
+/**
+ * How the conditions of a [`CompFilter`] or a [`PropFilter`] combine. This is the `test`
+ * attribute of CardDAV, also supported by some CalDAV servers.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc6352#section-10.5>
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Test {
+    /** All conditions must match, the RFC 4791 behavior. */
+    #[default]
+    AllOf,
+    /** At least one condition must match. */
+    AnyOf,
+}
+
+impl std::str::FromStr for Test {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "allof" => Ok(Self::AllOf),
+            "anyof" => Ok(Self::AnyOf),
+            _ => Err(invalid("test", &format!("unknown value {s}"))),
+        }
+    }
+}
+
+impl Test {
+    /**
+     * The `test` attribute with its leading space, empty for the default value.
+     */
+    pub fn as_attribute(&self) -> &'static str {
+        match self {
+            Self::AllOf => "",
+            Self::AnyOf => " test=\"anyof\"",
+        }
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.3>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamFilter {
+    pub name: String,
+    pub children: Vec<ParamChild>,
+}
+
+impl ParamFilter {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn append(mut self, text_match: TextMatch) -> Self {
+        self.children.push(ParamChild::TextMatch(text_match));
+
+        self
+    }
+
+    pub fn is_not_defined(mut self, value: bool) -> Self {
+        self.children.retain(|x| x != &ParamChild::IsNotDefined);
+
+        if value {
+            self.children.insert(0, ParamChild::IsNotDefined);
+        }
+
+        self
+    }
+
+    /**
+     * Checks the RFC 4791 grammar, `(is-not-defined | text-match?)`.
+     */
+    pub fn validate(&self) -> crate::Result {
+        let element = format!("param-filter {}", self.name);
+
+        validate_name(&element, &self.name)?;
+
+        if self.children.len() > 1 {
+            return Err(invalid(
+                &element,
+                "only one is-not-defined or text-match is allowed",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ParamFilter {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&super::Element::parse_fragment(s)?)
+    }
+}
+
+impl webdav::ToXml for ParamFilter {
+    fn to_xml(&self) -> String {
+        format!(
+            "<c:param-filter name=\"{}\">{}</c:param-filter>",
+            escape::attribute(&self.name),
+            self.children.to_xml(),
+        )
+    }
+}
+
+/// A condition of a [`ParamFilter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamChild {
+    /// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.4>
+    IsNotDefined,
+    TextMatch(TextMatch),
+}
+
+impl webdav::ToXml for ParamChild {
+    fn to_xml(&self) -> String {
+        match self {
+            Self::IsNotDefined => IS_NOT_DEFINED.to_string(),
+            Self::TextMatch(x) => x.to_xml(),
+        }
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.4>
+const IS_NOT_DEFINED: &str = "<c:is-not-defined />";
+
+/// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.5>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextMatch {
+    pub text: String,
+    pub collation: Option<String>,
+    pub negate_condition: bool,
+}
+
+impl TextMatch {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            collation: None,
+            negate_condition: false,
+        }
+    }
+
+    pub fn collation(mut self, collation: &str) -> Self {
+        self.collation = Some(collation.to_string());
+
+        self
+    }
+
+    pub fn negate_condition(mut self, value: bool) -> Self {
+        self.negate_condition = value;
+
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+
+        self
+    }
+}
+
+impl std::str::FromStr for TextMatch {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&super::Element::parse_fragment(s)?)
+    }
+}
+
+impl webdav::ToXml for TextMatch {
+    fn to_xml(&self) -> String {
+        let collation = match &self.collation {
+            Some(collation) => format!(" collation=\"{}\"", escape::attribute(collation)),
+            None => String::new(),
+        };
+
+        let negate_condition = if self.negate_condition {
+            " negate-condition=\"yes\"".to_string()
+        } else {
+            String::new()
+        };
+
+        format!(
+            "<c:text-match{collation}{negate_condition}>{}</c:text-match>",
+            escape::cdata(&self.text)
+        )
+    }
+}
+
+impl Filter {
+    /**
+     * Reads and validates a `CALDAV:filter` element, unknown children are ignored.
+     */
+    pub fn from_element(element: &super::Element) -> crate::Result<Self> {
+        expect(element, "filter")?;
+
+        let comp_filters = caldav_children(element)
+            .filter(|x| x.name.name == "comp-filter")
+            .map(CompFilter::from_element)
+            .collect::<crate::Result<_>>()?;
+
+        let filter = Self { comp_filters };
+        filter.validate()?;
+
+        Ok(filter)
+    }
+}
+
+impl CompFilter {
+    /**
+     * Reads and validates a `CALDAV:comp-filter` element, unknown children are ignored.
+     */
+    pub fn from_element(element: &super::Element) -> crate::Result<Self> {
+        expect(element, "comp-filter")?;
+
+        let mut filter = Self::new(name(element)?).test(test(element)?);
+
+        for child in caldav_children(element) {
+            let child = match child.name.name.as_str() {
+                "is-not-defined" => CompChild::IsNotDefined,
+                "comp-filter" => CompChild::CompFilter(Self::from_element(child)?),
+                "prop-filter" => CompChild::PropFilter(PropFilter::from_element(child)?),
+                "time-range" => CompChild::TimeRange(super::TimeRange::from_element(child)?),
+                _ => continue,
+            };
+
+            filter.children.push(child);
+        }
+
+        filter.validate()?;
+
+        Ok(filter)
+    }
+}
+
+impl PropFilter {
+    /**
+     * Reads and validates a `CALDAV:prop-filter` element, unknown children are ignored.
+     */
+    pub fn from_element(element: &super::Element) -> crate::Result<Self> {
+        expect(element, "prop-filter")?;
+
+        let mut filter = Self::new(name(element)?).test(test(element)?);
+
+        for child in caldav_children(element) {
+            let child = match child.name.name.as_str() {
+                "is-not-defined" => PropChild::IsNotDefined,
+                "param-filter" => PropChild::ParamFilter(ParamFilter::from_element(child)?),
+                "text-match" => PropChild::TextMatch(TextMatch::from_element(child)?),
+                "time-range" => PropChild::TimeRange(super::TimeRange::from_element(child)?),
+                _ => continue,
+            };
+
+            filter.children.push(child);
+        }
+
+        filter.validate()?;
+
+        Ok(filter)
+    }
+}
+
+impl ParamFilter {
+    /**
+     * Reads and validates a `CALDAV:param-filter` element, unknown children are ignored.
+     */
+    pub fn from_element(element: &super::Element) -> crate::Result<Self> {
+        expect(element, "param-filter")?;
+
+        let mut filter = Self::new(name(element)?);
+
+        for child in caldav_children(element) {
+            let child = match child.name.name.as_str() {
+                "is-not-defined" => ParamChild::IsNotDefined,
+                "text-match" => ParamChild::TextMatch(TextMatch::from_element(child)?),
+                _ => continue,
+            };
+
+            filter.children.push(child);
+        }
+
+        filter.validate()?;
+
+        Ok(filter)
+    }
+}
+
+impl TextMatch {
+    /**
+     * Reads a `CALDAV:text-match` element.
+     */
+    pub fn from_element(element: &super::Element) -> crate::Result<Self> {
+        expect(element, "text-match")?;
+
+        let mut text_match = Self::new(&element.text).negate_condition(
+            element
+                .attributes
+                .get("negate-condition")
+                .map(String::as_str)
+                == Some("yes"),
+        );
+
+        if let Some(collation) = element.attributes.get("collation") {
+            text_match = text_match.collation(collation);
+        }
+
+        Ok(text_match)
+    }
+}
+
+/**
+ * Ensures `element` is the `name` CalDAV element.
+ */
+pub(crate) fn expect(element: &super::Element, name: &str) -> crate::Result {
+    if element.name == super::PropName::caldav(name) {
+        Ok(())
+    } else {
+        Err(crate::Error::Xml(format!(
+            "expected {}, found {}",
+            super::PropName::caldav(name),
+            element.name
+        )))
+    }
+}
+
+fn name(element: &super::Element) -> crate::Result<&str> {
+    element
+        .attributes
+        .get("name")
+        .map(String::as_str)
+        .ok_or_else(|| crate::Error::Xml(format!("missing name attribute on {}", element.name)))
+}
+
+fn test(element: &super::Element) -> crate::Result<Test> {
+    element
+        .attributes
+        .get("test")
+        .map_or(Ok(Test::default()), |x| x.parse())
+}
+
+/**
+ * Combines the alternatives of each child: one set of children per alternative with
+ * `anyof`, their cartesian product with `allof`.
+ */
+pub(crate) fn combine<T: Clone>(test: Test, children: impl Iterator<Item = Vec<T>>) -> Vec<Vec<T>> {
+    match test {
+        Test::AllOf => children.fold(vec![Vec::new()], |combinations, alternatives| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    alternatives.iter().map(move |x| {
+                        let mut combination = combination.clone();
+                        combination.push(x.clone());
+                        combination
+                    })
+                })
+                .collect()
+        }),
+        Test::AnyOf => {
+            let alternatives = children.flatten().map(|x| vec![x]).collect::<Vec<_>>();
+
+            if alternatives.is_empty() {
+                vec![Vec::new()]
+            } else {
+                alternatives
+            }
+        }
+    }
+}
+
+fn caldav_children(element: &super::Element) -> impl Iterator<Item = &super::Element> {
+    element
+        .children
+        .iter()
+        .filter(|x| x.name.namespace == super::propfind::CALDAV)
+}
+
+fn validate_name(element: &str, name: &str) -> crate::Result {
+    if name.is_empty() {
+        Err(invalid(element, "the name is empty"))
+    } else {
+        Ok(())
+    }
+}
+
+fn invalid(element: &str, reason: &str) -> crate::Error {
+    crate::Error::Filter(format!("{element}: {reason}"))
+}
+
+#[cfg(test)]
+mod test {
+    use webdav::ToXml as _;
+
+    #[test]
+    fn comp_filter() {
+        let filter = crate::filter! {
+            CompFilter::new("VEVENT")
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VEVENT\" /></c:filter>",
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                }
+            }
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\" /></c:comp-filter></c:filter>",
+        );
+    }
+
+    #[test]
+    fn text_match() {
+        let filter = crate::filter! {
+            CompFilter::new("VEVENT") {
+                prop_filter: PropFilter::new("URL") {
+                    text_match: TextMatch::new("https://example.org"),
+                }
+            }
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"URL\"><c:text-match><![CDATA[https://example.org]]></c:text-match></c:prop-filter></c:comp-filter></c:filter>",
+        );
+
+        let filter = crate::elements::filter::TextMatch::new("https://example.org")
+            .collation("i;octet")
+            .negate_condition(true);
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:text-match collation=\"i;octet\" negate-condition=\"yes\"><![CDATA[https://example.org]]></c:text-match>",
+        );
+    }
+
+    #[test]
+    fn default() {
+        let filter = crate::filter! {
+            CompFilter::new("VEVENT") {
+                prop_filter: PropFilter::new("URL") {
+                    text_match: TextMatch::default() {
+                        text: "https://example.org",
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"URL\"><c:text-match><![CDATA[https://example.org]]></c:text-match></c:prop-filter></c:comp-filter></c:filter>",
+        );
+    }
+
+    #[test]
+    fn is_not_defined() {
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VTODO") {
+                    prop_filter: PropFilter::new("COMPLETED") {
+                        is_not_defined: true,
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VTODO\"><c:prop-filter name=\"COMPLETED\"><c:is-not-defined /></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+        );
+    }
+
+    #[test]
+    fn time_range() {
+        use chrono::TimeZone as _;
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    time_range: TimeRange {
+                        start: chrono::Utc.with_ymd_and_hms(2006, 1, 4, 0, 0, 0).earliest(),
+                        end: None,
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:time-range start=\"20060104T000000Z\" end=\"+infinity\" /></c:comp-filter></c:comp-filter></c:filter>",
+        );
+    }
+
+    #[test]
+    fn parse() -> crate::Result {
+        use chrono::TimeZone as _;
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    prop_filter: PropFilter::new("ATTENDEE") {
+                        ParamFilter::new("PARTSTAT") {
+                            TextMatch::new("NEEDS-ACTION") {
+                                collation: "i;octet",
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        .to_xml();
+        assert_eq!(filter.parse::<crate::elements::Filter>()?.to_xml(), filter);
+
+        let time_range = crate::elements::TimeRange {
+            start: chrono::Utc.with_ymd_and_hms(2006, 1, 4, 0, 0, 0).earliest(),
+            end: None,
+        };
+        assert_eq!(
+            time_range.to_xml().parse::<crate::elements::TimeRange>()?,
+            time_range
+        );
+
+        let filter: crate::elements::Filter = r#"<?xml version="1.0" encoding="utf-8" ?>
+<C:filter xmlns:C="urn:ietf:params:xml:ns:caldav">
+    <C:comp-filter name="VCALENDAR">
+        <C:comp-filter name="VTODO">
+            <C:prop-filter name="COMPLETED">
+                <C:is-not-defined/>
+            </C:prop-filter>
+            <C:prop-filter name="SUMMARY">
+                <C:text-match negate-condition="yes">meeting</C:text-match>
+            </C:prop-filter>
+        </C:comp-filter>
+    </C:comp-filter>
+</C:filter>"#
+            .parse()?;
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VTODO\"><c:prop-filter name=\"COMPLETED\"><c:is-not-defined /></c:prop-filter><c:prop-filter name=\"SUMMARY\"><c:text-match negate-condition=\"yes\"><![CDATA[meeting]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+        );
+
+        assert!(
+            "<c:comp-filter />"
+                .parse::<crate::elements::filter::CompFilter>()
+                .is_err()
+        );
+        assert!(
+            "<c:prop-filter name=\"SUMMARY\" />"
+                .parse::<crate::elements::filter::CompFilter>()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() {
+        use crate::elements::filter::{CompFilter, Filter, ParamFilter, PropFilter, TextMatch};
+
+        assert!(Filter::new().validate().is_err());
+        assert!(
+            Filter::new()
+                .append(CompFilter::new("VCALENDAR").append(CompFilter::new("VTODO")))
+                .validate()
+                .is_ok()
+        );
+        assert!(CompFilter::new("").validate().is_err());
+        assert!(
+            CompFilter::new("VCALENDAR")
+                .is_not_defined(true)
+                .append(CompFilter::new("VEVENT"))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            CompFilter::new("VEVENT")
+                .time_range(crate::elements::TimeRange::default())
+                .time_range(crate::elements::TimeRange::default())
+                .validate()
+                .is_err()
+        );
+        assert!(
+            PropFilter::new("SUMMARY")
+                .time_range(crate::elements::TimeRange::default())
+                .text_match(TextMatch::new("meeting"))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            CompFilter::new("VEVENT")
+                .prop_filter(
+                    PropFilter::new("ATTENDEE").append(
+                        ParamFilter::new("PARTSTAT")
+                            .is_not_defined(true)
+                            .append(TextMatch::new("ACCEPTED"))
+                    )
+                )
+                .validate()
+                .is_err()
+        );
+        assert!(
+            CompFilter::new("VEVENT")
+                .is_not_defined(true)
+                .is_not_defined(false)
+                .validate()
+                .is_ok()
+        );
+
+        let error = "<c:comp-filter name=\"VTODO\"><c:is-not-defined /><c:time-range start=\"20060104T000000Z\" /></c:comp-filter>"
+            .parse::<CompFilter>()
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::Filter(_)));
+    }
+
+    #[test]
+    fn param_filter() -> crate::Result {
+        use crate::elements::filter::{CompChild, ParamChild, PropChild};
+
+        let filter = crate::filter! {
+            CompFilter::new("VEVENT") {
+                prop_filter: PropFilter::new("ATTENDEE") {
+                    ParamFilter::new("PARTSTAT") {
+                        is_not_defined: true,
+                    }
+                }
+            }
+        };
+
+        let xml = filter.to_xml();
+        assert_eq!(
+            xml,
+            "<c:filter><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"ATTENDEE\"><c:param-filter name=\"PARTSTAT\"><c:is-not-defined /></c:param-filter></c:prop-filter></c:comp-filter></c:filter>",
+        );
+        assert_eq!(xml.parse::<crate::elements::Filter>()?, filter);
+
+        let comp_filter = &filter.comp_filters[0];
+        assert_eq!(comp_filter.name, "VEVENT");
+
+        let [CompChild::PropFilter(prop_filter)] = comp_filter.children.as_slice() else {
+            panic!("expected a prop-filter");
+        };
+        let [PropChild::ParamFilter(param_filter)] = prop_filter.children.as_slice() else {
+            panic!("expected a param-filter");
+        };
+        assert_eq!(param_filter.name, "PARTSTAT");
+        assert_eq!(param_filter.children, [ParamChild::IsNotDefined]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn any_of() -> crate::Result {
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    test: Test::AnyOf,
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        text_match: TextMatch::new("meeting"),
+                    },
+                    prop_filter: PropFilter::new("LOCATION") {
+                        text_match: TextMatch::new("meeting"),
+                    },
+                }
+            }
+        };
+
+        let xml = filter.to_xml();
+        assert_eq!(
+            xml,
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\" test=\"anyof\"><c:prop-filter name=\"SUMMARY\"><c:text-match><![CDATA[meeting]]></c:text-match></c:prop-filter><c:prop-filter name=\"LOCATION\"><c:text-match><![CDATA[meeting]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+        );
+        assert_eq!(xml.parse::<crate::elements::Filter>()?, filter);
+
+        let alternatives = filter
+            .alternatives()
+            .iter()
+            .map(|x| x.to_xml())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alternatives,
+            [
+                "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"SUMMARY\"><c:text-match><![CDATA[meeting]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+                "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"LOCATION\"><c:text-match><![CDATA[meeting]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+            ]
+        );
+
+        assert!(
+            "<c:comp-filter name=\"VEVENT\" test=\"oneof\" />"
+                .parse::<crate::elements::filter::CompFilter>()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn alternatives() {
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        test: Test::AnyOf,
+                        text_match: TextMatch::new("lunch"),
+                        text_match: TextMatch::new("dinner"),
+                    },
+                    prop_filter: PropFilter::new("LOCATION") {
+                        test: Test::AnyOf,
+                        text_match: TextMatch::new("home"),
+                        text_match: TextMatch::new("office"),
+                    },
+                }
+            },
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VTODO") {}
+            },
+        };
+
+        assert_eq!(filter.comp_filters.len(), 2);
+        assert!(filter.validate().is_ok());
+
+        let alternatives = filter.alternatives();
+        assert_eq!(alternatives.len(), 5);
+        assert!(alternatives.iter().all(|x| x.alternatives() == [x.clone()]));
+        assert_eq!(
+            alternatives[4],
+            crate::filter! {
+                CompFilter::new("VCALENDAR") {
+                    CompFilter::new("VTODO") {}
+                }
+            }
+        );
+    }
+}
@@ -11,13 +11,18 @@ impl webdav::ToXml for Mkcalendar {
         let description = self
             .description
             .as_ref()
-            .map(|x| format!("<c:calendar-description>{x}</c:calendar-description>"))
+            .map(|x| {
+                format!(
+                    "<c:calendar-description>{}</c:calendar-description>",
+                    super::escape::text(x)
+                )
+            })
             .unwrap_or_default();
 
         let timezone = if let Some(timezone) = &self.timezone {
             format!(
-                "<c:calendar-timezone><![CDATA[{}]]></c:calendar-timezone>",
-                ikal::ser::ical(timezone)
+                "<c:calendar-timezone>{}</c:calendar-timezone>",
+                super::escape::cdata(&ikal::ser::ical(timezone))
             )
         } else {
             String::new()
@@ -34,7 +39,7 @@ impl webdav::ToXml for Mkcalendar {
         let displayname = self
             .name
             .as_ref()
-            .map(|x| format!("<d:displayname><![CDATA[{x}]]></d:displayname>"))
+            .map(|x| format!("<d:displayname>{}</d:displayname>", super::escape::cdata(x)))
             .unwrap_or_default();
 
         let xml = format!(
@@ -56,3 +61,56 @@ impl webdav::ToXml for Mkcalendar {
         xml
     }
 }
+
+#[cfg(feature = "server")]
+impl Mkcalendar {
+    pub(crate) fn from_element(element: &super::Element) -> crate::Result<Self> {
+        use super::PropName;
+
+        super::filter::expect(element, "mkcalendar")?;
+
+        let mut mkcalendar = Self::default();
+
+        let Some(prop) = element
+            .child(&PropName::dav("set"))
+            .and_then(|x| x.child(&PropName::dav("prop")))
+        else {
+            return Ok(mkcalendar);
+        };
+
+        let text = |name: &PropName| prop.child(name).map(|x| x.text.clone());
+
+        mkcalendar.name = text(&PropName::dav("displayname"));
+        mkcalendar.description = text(&PropName::caldav("calendar-description"));
+        mkcalendar.timezone = text(&PropName::caldav("calendar-timezone"))
+            .map(ikal::VCalendar::try_from)
+            .transpose()?;
+
+        if let Some(set) = prop.child(&PropName::caldav("supported-calendar-component-set")) {
+            mkcalendar.supported_components = set
+                .children
+                .iter()
+                .filter(|x| x.name == PropName::caldav("comp"))
+                .filter_map(|x| x.attributes.get("name"))
+                .filter_map(|x| component(x))
+                .collect();
+        }
+
+        Ok(mkcalendar)
+    }
+}
+
+#[cfg(feature = "server")]
+fn component(name: &str) -> Option<ikal::Components> {
+    let component = match name.to_ascii_uppercase().as_str() {
+        "VALARM" => ikal::Components::Alarm,
+        "VEVENT" => ikal::Components::Event,
+        "VFREEBUSY" => ikal::Components::Freebusy,
+        "VJOURNAL" => ikal::Components::Journal,
+        "VTIMEZONE" => ikal::Components::Timezone,
+        "VTODO" => ikal::Components::Todo,
+        _ => return None,
+    };
+
+    Some(component)
+}
@@ -1,8 +1,13 @@
+pub(crate) mod escape;
 pub mod filter;
 pub mod mkcalendar;
+pub mod multistatus;
+pub mod propfind;
 
 pub use filter::Filter;
 pub use mkcalendar::Mkcalendar;
+pub use multistatus::{Element, Multistatus, Properties, Property};
+pub use propfind::{Depth, PropName, Propfind};
 
 /// <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -11,6 +16,14 @@ pub struct TimeRange {
     pub end: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+impl std::str::FromStr for TimeRange {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_element(&Element::parse_fragment(s)?)
+    }
+}
+
 impl webdav::ToXml for TimeRange {
     fn to_xml(&self) -> String {
         let date_format = "%Y%m%dT%H%M%SZ";
@@ -30,3 +43,276 @@ impl webdav::ToXml for TimeRange {
         format!("<c:time-range start=\"{start}\" end=\"{end}\" />")
     }
 }
+
+impl TimeRange {
+    /**
+     * `[start, end[`, with bounds in any time zone.
+     */
+    pub fn new<Tz: chrono::TimeZone>(
+        start: chrono::DateTime<Tz>,
+        end: chrono::DateTime<Tz>,
+    ) -> crate::Result<Self> {
+        let time_range = Self {
+            start: Some(start.to_utc()),
+            end: Some(end.to_utc()),
+        };
+        time_range.validate()?;
+
+        Ok(time_range)
+    }
+
+    pub fn after<Tz: chrono::TimeZone>(start: chrono::DateTime<Tz>) -> Self {
+        Self {
+            start: Some(start.to_utc()),
+            end: None,
+        }
+    }
+
+    pub fn before<Tz: chrono::TimeZone>(end: chrono::DateTime<Tz>) -> Self {
+        Self {
+            start: None,
+            end: Some(end.to_utc()),
+        }
+    }
+
+    /**
+     * The whole days from `start` to `end` included, as seen in the `tz` time zone.
+     */
+    pub fn dates<Tz: chrono::TimeZone>(
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+        tz: &Tz,
+    ) -> crate::Result<Self> {
+        let end = end
+            .succ_opt()
+            .ok_or_else(|| crate::Error::Filter(format!("time-range: invalid end date {end}")))?;
+
+        Self::floating(
+            start.and_time(chrono::NaiveTime::MIN),
+            end.and_time(chrono::NaiveTime::MIN),
+            tz,
+        )
+    }
+
+    /**
+     * `[start, end[` with floating date-times, read in the `tz` reference time zone.
+     *
+     * Like RFC 5545 §3.3.5, a time repeated when the clocks go back is read as its first
+     * occurrence and a time skipped when they go forward with the offset before the gap.
+     */
+    pub fn floating<Tz: chrono::TimeZone>(
+        start: chrono::NaiveDateTime,
+        end: chrono::NaiveDateTime,
+        tz: &Tz,
+    ) -> crate::Result<Self> {
+        Self::new(local(start, tz)?, local(end, tz)?)
+    }
+
+    /**
+     * Checks the start is before the end.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
+     */
+    pub fn validate(&self) -> crate::Result {
+        if let (Some(start), Some(end)) = (self.start, self.end)
+            && start >= end
+        {
+            return Err(crate::Error::Filter(format!(
+                "time-range: the start {start} isn’t before the end {end}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Reads a `CALDAV:time-range` element, `-infinity` and `+infinity` are read as `None`.
+     */
+    pub fn from_element(element: &Element) -> crate::Result<Self> {
+        filter::expect(element, "time-range")?;
+
+        let date = |name: &str| match element.attributes.get(name).map(String::as_str) {
+            None | Some("-infinity" | "+infinity") => Ok(None),
+            Some(value) => chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+                .map(|x| Some(x.and_utc()))
+                .map_err(|_| crate::Error::Xml(format!("invalid date-time: {value}"))),
+        };
+
+        Ok(Self {
+            start: date("start")?,
+            end: date("end")?,
+        })
+    }
+}
+
+fn local<Tz: chrono::TimeZone>(
+    datetime: chrono::NaiveDateTime,
+    tz: &Tz,
+) -> crate::Result<chrono::DateTime<Tz>> {
+    use chrono::Offset as _;
+
+    if let Some(local) = tz.from_local_datetime(&datetime).earliest() {
+        return Ok(local);
+    }
+
+    let offset = (1..=24 * 60)
+        .find_map(|minutes| {
+            tz.offset_from_local_datetime(&(datetime - chrono::TimeDelta::minutes(minutes)))
+                .latest()
+        })
+        .ok_or_else(|| {
+            crate::Error::Filter(format!("time-range: invalid local time {datetime}"))
+        })?;
+
+    let utc = datetime - chrono::TimeDelta::seconds(offset.fix().local_minus_utc().into());
+
+    Ok(tz.from_utc_datetime(&utc))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeZone as _};
+
+    /**
+     * Europe/Paris in 2024: CET, then CEST from March 31 to October 27.
+     */
+    #[derive(Clone, Copy, Debug)]
+    struct Paris;
+
+    impl chrono::TimeZone for Paris {
+        type Offset = chrono::FixedOffset;
+
+        fn from_offset(_: &Self::Offset) -> Self {
+            Self
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> chrono::LocalResult<Self::Offset> {
+            self.offset_from_local_datetime(&local.and_time(chrono::NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &chrono::NaiveDateTime,
+        ) -> chrono::LocalResult<Self::Offset> {
+            let offsets = [2, 1]
+                .map(|hours| chrono::FixedOffset::east_opt(hours * 3600).unwrap())
+                .into_iter()
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect::<Vec<_>>();
+
+            match offsets[..] {
+                [offset] => chrono::LocalResult::Single(offset),
+                [earliest, latest] => chrono::LocalResult::Ambiguous(earliest, latest),
+                _ => chrono::LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> Self::Offset {
+            self.offset_from_utc_datetime(&utc.and_time(chrono::NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> Self::Offset {
+            let summer = chrono::Utc
+                .with_ymd_and_hms(2024, 3, 31, 1, 0, 0)
+                .unwrap()
+                .naive_utc()
+                ..chrono::Utc
+                    .with_ymd_and_hms(2024, 10, 27, 1, 0, 0)
+                    .unwrap()
+                    .naive_utc();
+            let hours = if summer.contains(utc) { 2 } else { 1 };
+
+            chrono::FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+    }
+
+    fn utc(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        Some(
+            chrono::NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+                .unwrap()
+                .and_utc(),
+        )
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn new() -> crate::Result {
+        let time_range = super::TimeRange::new(
+            Paris.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap(),
+            Paris.with_ymd_and_hms(2024, 7, 1, 18, 0, 0).unwrap(),
+        )?;
+
+        assert_eq!(time_range.start, utc("20240701T070000Z"));
+        assert_eq!(time_range.end, utc("20240701T160000Z"));
+
+        let now = chrono::Utc::now();
+        assert!(super::TimeRange::new(now, now).is_err());
+        assert!(
+            super::TimeRange {
+                start: Some(now),
+                end: Some(now - chrono::TimeDelta::hours(1)),
+            }
+            .validate()
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn dates() -> crate::Result {
+        // the week of the switch to summer time is an hour shorter
+        let week = super::TimeRange::dates(date(2024, 3, 25), date(2024, 3, 31), &Paris)?;
+        assert_eq!(week.start, utc("20240324T230000Z"));
+        assert_eq!(week.end, utc("20240331T220000Z"));
+
+        let day = super::TimeRange::dates(date(2024, 10, 27), date(2024, 10, 27), &Paris)?;
+        assert_eq!(day.start, utc("20241026T220000Z"));
+        assert_eq!(day.end, utc("20241027T230000Z"));
+
+        assert!(super::TimeRange::dates(date(2024, 1, 2), date(2024, 1, 1), &Paris).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn floating() -> crate::Result {
+        let datetime =
+            |month, day, hour, minute| date(2024, month, day).and_hms_opt(hour, minute, 0).unwrap();
+
+        // 02:30 doesn’t exist on March 31, it’s read with the winter offset
+        let time_range =
+            super::TimeRange::floating(datetime(3, 31, 2, 30), datetime(3, 31, 4, 0), &Paris)?;
+        assert_eq!(time_range.start, utc("20240331T013000Z"));
+        assert_eq!(time_range.end, utc("20240331T020000Z"));
+
+        // 02:30 happens twice on October 27, the first one is used
+        let time_range =
+            super::TimeRange::floating(datetime(10, 27, 2, 30), datetime(10, 27, 3, 30), &Paris)?;
+        assert_eq!(time_range.start, utc("20241027T003000Z"));
+        assert_eq!(time_range.end, utc("20241027T023000Z"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter() {
+        let now = chrono::Utc::now();
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    time_range: TimeRange {
+                        start: Some(now),
+                        end: Some(now),
+                    }
+                }
+            }
+        };
+
+        assert!(matches!(filter.validate(), Err(crate::Error::Filter(_))));
+    }
+}
@@ -0,0 +1,340 @@
+use super::PropName;
+use std::collections::BTreeMap;
+
+/// Properties of a resource, indexed by name.
+pub type Properties = BTreeMap<PropName, Property>;
+
+/// A generic XML element, used to represent property values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Element {
+    pub name: PropName,
+    pub attributes: BTreeMap<String, String>,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    pub fn child(&self, name: &PropName) -> Option<&Self> {
+        self.children.iter().find(|x| &x.name == name)
+    }
+
+    pub fn hrefs(&self) -> Vec<&str> {
+        let href = PropName::dav("href");
+
+        self.children
+            .iter()
+            .filter(|x| x.name == href)
+            .map(|x| x.text.as_str())
+            .collect()
+    }
+
+    /**
+     * Parses the root element of an XML document.
+     */
+    pub(crate) fn parse(s: &str) -> crate::Result<Self> {
+        let package =
+            sxd_document::parser::parse(s).map_err(|e| crate::Error::Xml(e.to_string()))?;
+        let document = package.as_document();
+        let root = document
+            .root()
+            .children()
+            .into_iter()
+            .find_map(|x| x.element())
+            .ok_or_else(|| crate::Error::Xml("empty document".to_string()))?;
+
+        Ok(Self::from_dom(root))
+    }
+
+    /**
+     * Parses an element whose `d`, `c`, `cs` and `x1` prefixes may be undeclared, like the
+     * fragments generated by [`webdav::ToXml`].
+     */
+    pub(crate) fn parse_fragment(s: &str) -> crate::Result<Self> {
+        let s = s.trim_start();
+        let s = match s.strip_prefix("<?xml") {
+            Some(declaration) => declaration
+                .split_once("?>")
+                .map(|(_, s)| s)
+                .unwrap_or_default(),
+            None => s,
+        };
+
+        let wrapper = Self::parse(&format!(
+            r#"<fragment xmlns:d="{}" xmlns:c="{}" xmlns:cs="{}" xmlns:x1="{}">{s}</fragment>"#,
+            super::propfind::DAV,
+            super::propfind::CALDAV,
+            super::propfind::CALENDARSERVER,
+            super::propfind::APPLE,
+        ))?;
+
+        let mut children = wrapper.children.into_iter();
+
+        match (children.next(), children.next()) {
+            (Some(element), None) => Ok(element),
+            _ => Err(crate::Error::Xml("expected one root element".to_string())),
+        }
+    }
+
+    pub(crate) fn from_dom(element: sxd_document::dom::Element) -> Self {
+        let name = element.name();
+
+        let attributes = element
+            .attributes()
+            .iter()
+            .map(|x| (x.name().local_part().to_string(), x.value().to_string()))
+            .collect();
+
+        let mut text = String::new();
+        let mut children = Vec::new();
+
+        for child in element.children() {
+            match child {
+                sxd_document::dom::ChildOfElement::Element(element) => {
+                    children.push(Self::from_dom(element))
+                }
+                sxd_document::dom::ChildOfElement::Text(x) => text.push_str(x.text()),
+                _ => (),
+            }
+        }
+
+        Self {
+            name: PropName::new(name.namespace_uri().unwrap_or_default(), name.local_part()),
+            attributes,
+            // keeps the text of leaf elements as is, it may be significant
+            text: if children.is_empty() {
+                text
+            } else {
+                text.trim().to_string()
+            },
+            children,
+        }
+    }
+}
+
+impl webdav::ToXml for Element {
+    fn to_xml(&self) -> String {
+        let name = super::escape::name(&self.name.name);
+
+        let (name, xmlns) = if self.name.namespace == super::propfind::DAV {
+            (format!("d:{name}"), String::new())
+        } else {
+            (
+                name,
+                format!(
+                    " xmlns=\"{}\"",
+                    super::escape::attribute(&self.name.namespace)
+                ),
+            )
+        };
+
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    " {}=\"{}\"",
+                    super::escape::name(name),
+                    super::escape::attribute(value)
+                )
+            })
+            .collect::<String>();
+
+        if self.text.is_empty() && self.children.is_empty() {
+            return format!("<{name}{xmlns}{attributes} />");
+        }
+
+        format!(
+            "<{name}{xmlns}{attributes}>{}{}</{name}>",
+            super::escape::text(&self.text),
+            self.children.to_xml(),
+        )
+    }
+}
+
+/// A property returned by the server, with the status of its propstat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Property {
+    pub status: u16,
+    pub value: Element,
+}
+
+impl Property {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4918#section-14.16>
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Multistatus {
+    pub responses: BTreeMap<String, Properties>,
+    /** Status of the responses without `propstat`, like the removed members of a sync report. */
+    pub statuses: BTreeMap<String, u16>,
+    /** <https://datatracker.ietf.org/doc/html/rfc6578#section-6.2> */
+    pub sync_token: Option<String>,
+}
+
+impl std::str::FromStr for Multistatus {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let root = Element::parse(s)?;
+
+        if root.name != PropName::dav("multistatus") {
+            return Err(crate::Error::Xml(format!(
+                "expected multistatus, found {}",
+                root.name
+            )));
+        }
+
+        let mut responses = BTreeMap::new();
+        let mut statuses = BTreeMap::new();
+
+        for response in &root.children {
+            let Some(href) = response.child(&PropName::dav("href")) else {
+                continue;
+            };
+
+            if let Some(status) = response.child(&PropName::dav("status")) {
+                statuses.insert(href.text.clone(), parse_status(&status.text));
+            }
+
+            let mut properties = Properties::new();
+
+            for propstat in &response.children {
+                if propstat.name != PropName::dav("propstat") {
+                    continue;
+                }
+
+                let status = propstat
+                    .child(&PropName::dav("status"))
+                    .map(|x| parse_status(&x.text))
+                    .unwrap_or(200);
+
+                if let Some(prop) = propstat.child(&PropName::dav("prop")) {
+                    for value in &prop.children {
+                        properties.insert(
+                            value.name.clone(),
+                            Property {
+                                status,
+                                value: value.clone(),
+                            },
+                        );
+                    }
+                }
+            }
+
+            responses.insert(href.text.clone(), properties);
+        }
+
+        let sync_token = root
+            .child(&PropName::dav("sync-token"))
+            .map(|x| x.text.trim().to_string());
+
+        Ok(Self {
+            responses,
+            statuses,
+            sync_token,
+        })
+    }
+}
+
+/**
+ * Extracts the code of a status line like `HTTP/1.1 200 OK`.
+ */
+fn parse_status(status: &str) -> u16 {
+    status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|x| x.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::elements::PropName;
+
+    #[test]
+    fn parse() -> crate::Result {
+        let multistatus: crate::elements::Multistatus = r#"
+<d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:response>
+        <d:href>/calendars/johndoe/home/</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:displayname>Home calendar</d:displayname>
+                <c:supported-calendar-component-set>
+                    <c:comp name="VEVENT" />
+                </c:supported-calendar-component-set>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+        <d:propstat>
+            <d:prop>
+                <cs:getctag />
+            </d:prop>
+            <d:status>HTTP/1.1 404 Not Found</d:status>
+        </d:propstat>
+    </d:response>
+</d:multistatus>
+"#
+        .parse()?;
+
+        let properties = &multistatus.responses["/calendars/johndoe/home/"];
+
+        let displayname = &properties[&PropName::dav("displayname")];
+        assert!(displayname.is_success());
+        assert_eq!(displayname.value.text, "Home calendar");
+
+        let components = &properties[&PropName::caldav("supported-calendar-component-set")];
+        assert_eq!(
+            components.value.children[0].attributes.get("name"),
+            Some(&"VEVENT".to_string())
+        );
+
+        let ctag = &properties[&PropName::calendarserver("getctag")];
+        assert_eq!(ctag.status, 404);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync() -> crate::Result {
+        let multistatus: crate::elements::Multistatus = r#"
+<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/calendars/johndoe/home/1.ics</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:getetag>"2"</d:getetag>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+    </d:response>
+    <d:response>
+        <d:href>/calendars/johndoe/home/2.ics</d:href>
+        <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:response>
+    <d:sync-token>urn:example:sync:2</d:sync-token>
+</d:multistatus>
+"#
+        .parse()?;
+
+        assert_eq!(multistatus.responses.len(), 2);
+        assert_eq!(
+            multistatus.statuses.get("/calendars/johndoe/home/2.ics"),
+            Some(&404)
+        );
+        assert!(
+            !multistatus
+                .statuses
+                .contains_key("/calendars/johndoe/home/1.ics")
+        );
+        assert_eq!(
+            multistatus.sync_token.as_deref(),
+            Some("urn:example:sync:2")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+pub const DAV: &str = "DAV:";
+pub const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+pub const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+pub const APPLE: &str = "http://apple.com/ns/ical/";
+
+/// <https://datatracker.ietf.org/doc/html/rfc4918#section-10.2>
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Depth {
+    #[default]
+    Zero,
+    One,
+    Infinity,
+}
+
+impl Depth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zero => "0",
+            Self::One => "1",
+            Self::Infinity => "infinity",
+        }
+    }
+}
+
+impl std::fmt::Display for Depth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A property name, qualified by its XML namespace.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn dav(name: &str) -> Self {
+        Self::new(DAV, name)
+    }
+
+    pub fn caldav(name: &str) -> Self {
+        Self::new(CALDAV, name)
+    }
+
+    pub fn calendarserver(name: &str) -> Self {
+        Self::new(CALENDARSERVER, name)
+    }
+
+    pub fn apple(name: &str) -> Self {
+        Self::new(APPLE, name)
+    }
+}
+
+impl std::fmt::Display for PropName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{{}}}{}", self.namespace, self.name)
+    }
+}
+
+impl webdav::ToXml for PropName {
+    fn to_xml(&self) -> String {
+        let name = super::escape::name(&self.name);
+
+        if self.namespace == DAV {
+            format!("<d:{name} />")
+        } else {
+            format!(
+                "<{name} xmlns=\"{}\" />",
+                super::escape::attribute(&self.namespace)
+            )
+        }
+    }
+}
+
+/// <https://datatracker.ietf.org/doc/html/rfc4918#section-14.20>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Propfind {
+    /// Retrieves the listed properties.
+    Prop(Vec<PropName>),
+    /// Retrieves all dead properties and the live properties defined by RFC
+    /// 4918, plus the listed ones.
+    AllProp(Vec<PropName>),
+    /// Retrieves the names of all properties, without their values.
+    PropName,
+}
+
+impl webdav::ToXml for Propfind {
+    fn to_xml(&self) -> String {
+        let body = match self {
+            Self::Prop(props) => format!("<d:prop>{}</d:prop>", props.to_xml()),
+            Self::AllProp(include) if include.is_empty() => "<d:allprop />".to_string(),
+            Self::AllProp(include) => {
+                format!("<d:allprop /><d:include>{}</d:include>", include.to_xml())
+            }
+            Self::PropName => "<d:propname />".to_string(),
+        };
+
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propfind xmlns:d="DAV:">{body}</d:propfind>
+"#
+        )
+    }
+}
+
+#[cfg(feature = "server")]
+impl Propfind {
+    /**
+     * Reads the properties asked by a `PROPFIND` or a `REPORT` body, `None` meaning `allprop`.
+     */
+    pub(crate) fn from_element(element: Option<&super::Element>) -> Self {
+        let Some(element) = element else {
+            return Self::AllProp(Vec::new());
+        };
+
+        let names = |name: &str| {
+            element
+                .child(&PropName::dav(name))
+                .map(|x| x.children.iter().map(|x| x.name.clone()).collect())
+                .unwrap_or_default()
+        };
+
+        if element.child(&PropName::dav("propname")).is_some() {
+            Self::PropName
+        } else if element.child(&PropName::dav("prop")).is_some() {
+            Self::Prop(names("prop"))
+        } else {
+            Self::AllProp(names("include"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use webdav::ToXml as _;
+
+    #[test]
+    fn prop() {
+        let propfind = crate::elements::Propfind::Prop(vec![
+            crate::elements::PropName::dav("displayname"),
+            crate::elements::PropName::calendarserver("getctag"),
+        ]);
+
+        assert_eq!(
+            propfind.to_xml(),
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:displayname /><getctag xmlns="http://calendarserver.org/ns/" /></d:prop></d:propfind>
+"#
+        );
+    }
+
+    #[test]
+    fn allprop() {
+        let propfind = crate::elements::Propfind::AllProp(vec![crate::elements::PropName::caldav(
+            "supported-calendar-component-set",
+        )]);
+
+        assert_eq!(
+            propfind.to_xml(),
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propfind xmlns:d="DAV:"><d:allprop /><d:include><supported-calendar-component-set xmlns="urn:ietf:params:xml:ns:caldav" /></d:include></d:propfind>
+"#
+        );
+    }
+
+    #[test]
+    fn propname() {
+        use crate::elements::{PropName, Propfind};
+
+        assert_eq!(
+            Propfind::PropName.to_xml(),
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propfind xmlns:d="DAV:"><d:propname /></d:propfind>
+"#
+        );
+
+        assert_eq!(
+            PropName::new("urn:x-\"", "a b").to_xml(),
+            "<a\u{fffd}b xmlns=\"urn:x-&quot;\" />"
+        );
+    }
+}
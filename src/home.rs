@@ -5,12 +5,12 @@ use std::collections::BTreeMap;
 #[derive(Clone, Debug, Default, crate::Object)]
 pub struct Home {
     url: String,
-    auth: Option<crate::Authorization>,
+    session: crate::Session,
 }
 
 impl Home {
     pub fn calendars(&self) -> crate::Result<BTreeMap<String, crate::Calendar>> {
-        let response = self.propfind(&self.url, r#"
+        let response = self.propfind_raw(&self.url, crate::elements::Depth::One, r#"
 <d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:x1="http://apple.com/ns/ical/">
   <d:prop>
      <d:resourcetype />
@@ -33,6 +33,31 @@ impl Home {
         ))
     }
 
+    /**
+     * URLs of the time zone distribution services the server suggests, the
+     * `CALDAV:timezone-service-set` property.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-5.1>
+     */
+    pub fn timezone_service_set(&self) -> crate::Result<Vec<String>> {
+        let name = crate::elements::PropName::caldav("timezone-service-set");
+        let properties = self.propfind(
+            &self.url,
+            crate::elements::Depth::Zero,
+            std::slice::from_ref(&name),
+        )?;
+
+        let hrefs = properties
+            .values()
+            .filter_map(|x| x.get(&name))
+            .filter(|x| x.is_success())
+            .flat_map(|x| x.value.hrefs())
+            .map(ToString::to_string)
+            .collect();
+
+        Ok(hrefs)
+    }
+
     pub fn new_calendar(
         &self,
         path: &str,
@@ -49,7 +74,7 @@ impl Home {
         self.mkcalendar(&url, &config.to_xml())?;
 
         let mut calendar = crate::Calendar::new(url, &Default::default());
-        calendar.set_auth(self.auth.clone());
+        calendar.set_session(self.session.clone());
 
         Ok(calendar)
     }
@@ -58,9 +83,8 @@ impl Home {
 #[cfg(test)]
 mod test {
     #[test]
-    #[ignore = "mock client doesn’t support MKCALENDAR method"]
     fn new_calendar() -> crate::Result {
-        let server = crate::test::server();
+        let server = kaldav_testserver::Server::start();
 
         let client = crate::Client::new(server.url(""));
         let mkcalendar = crate::elements::Mkcalendar {
@@ -97,7 +121,15 @@ mod test {
             supported_components: vec![crate::ical::Components::Event],
         };
 
-        assert!(client.new_calendar("events", &mkcalendar).is_ok());
+        client.new_calendar("events", &mkcalendar)?;
+
+        let calendar = server.calendar("events").unwrap();
+        assert_eq!(calendar.displayname.as_deref(), Some("Lisa's Events"));
+        assert_eq!(calendar.components, ["VEVENT"]);
+        assert!(calendar.timezone.unwrap().contains("TZID:US-Eastern"));
+
+        let calendars = client.calendars()?;
+        assert!(calendars.contains_key("Lisa's Events"));
 
         Ok(())
     }
@@ -1,20 +1,41 @@
+pub mod auth;
+pub mod cache;
 pub mod elements;
+pub mod retry;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod sync;
+pub mod timezone;
+pub mod trace;
+pub mod transport;
+pub mod tzdist;
 
 mod calendar;
 mod client;
+mod component;
 mod home;
 mod method;
 mod object;
 mod principal;
+mod query;
+mod recurrence;
 mod result;
+pub(crate) mod session;
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+mod tls;
 
+pub use auth::{Auth, Authorization};
 pub use calendar::*;
 pub use client::*;
 pub use home::*;
 pub use method::Method;
 pub use object::*;
 pub use principal::*;
+pub use query::Query;
 pub use result::*;
+pub use retry::Retry;
+pub use session::{Redirect, Session};
+pub use tzdist::Tzdist;
 
 pub use ikal as ical;
 pub use kaldav_derive::filter;
@@ -22,15 +43,11 @@ pub use kaldav_derive::filter;
 use kaldav_derive::Object;
 use std::collections::BTreeMap;
 
-#[derive(Clone, Debug)]
-pub struct Authorization {
-    pub username: String,
-    pub password: Option<String>,
-}
+pub(crate) type Headers<'a> = BTreeMap<&'static str, &'a str>;
 
 pub trait Requestable {
-    fn auth(&self) -> Option<Authorization>;
-    fn set_auth(&mut self, auth: Option<Authorization>);
+    fn session(&self) -> &Session;
+    fn set_session(&mut self, session: Session);
 
     fn delete<S>(&self, href: S) -> Result
     where
@@ -61,11 +78,84 @@ pub trait Requestable {
         self.request(Method::PUT, href, body, None)
     }
 
-    fn propfind<S>(&self, href: S, body: &str) -> Result<String>
+    /**
+     * Retrieves the `props` properties of `href` and, depending on `depth`, of its members.
+     */
+    fn propfind<S>(
+        &self,
+        href: S,
+        depth: elements::Depth,
+        props: &[elements::PropName],
+    ) -> Result<BTreeMap<String, elements::Properties>>
+    where
+        S: Into<String>,
+    {
+        let body = elements::Propfind::Prop(props.to_vec());
+
+        self.propfind_with(href, depth, &body)
+    }
+
+    /**
+     * Retrieves all properties of `href` and, depending on `depth`, of its members.
+     */
+    fn allprop<S>(
+        &self,
+        href: S,
+        depth: elements::Depth,
+    ) -> Result<BTreeMap<String, elements::Properties>>
+    where
+        S: Into<String>,
+    {
+        self.propfind_with(href, depth, &elements::Propfind::AllProp(Vec::new()))
+    }
+
+    /**
+     * Retrieves the names of the properties of `href` and, depending on `depth`, of its
+     * members. Values of the returned properties are empty.
+     */
+    fn propname<S>(
+        &self,
+        href: S,
+        depth: elements::Depth,
+    ) -> Result<BTreeMap<String, elements::Properties>>
+    where
+        S: Into<String>,
+    {
+        self.propfind_with(href, depth, &elements::Propfind::PropName)
+    }
+
+    /**
+     * Sends the `body` `PROPFIND` to `href` and parses the multistatus response, by href.
+     */
+    fn propfind_with<S>(
+        &self,
+        href: S,
+        depth: elements::Depth,
+        body: &elements::Propfind,
+    ) -> Result<BTreeMap<String, elements::Properties>>
     where
         S: Into<String>,
     {
-        self.request(Method::PROPFIND, href, Some(body), None)
+        use webdav::ToXml as _;
+
+        let response = self.propfind_raw(href, depth, &body.to_xml())?;
+        let multistatus = response.parse::<elements::Multistatus>()?;
+
+        Ok(multistatus.responses)
+    }
+
+    /**
+     * Sends a raw `PROPFIND` `body` to `href`, returning the response body unparsed.
+     */
+    fn propfind_raw<S>(&self, href: S, depth: elements::Depth, body: &str) -> Result<String>
+    where
+        S: Into<String>,
+    {
+        let mut headers = BTreeMap::new();
+
+        headers.insert("Depth", depth.as_str());
+
+        self.request(Method::PROPFIND, href, Some(body), Some(headers))
     }
 
     fn report<S>(&self, href: S, body: &str) -> Result<String>
@@ -84,34 +174,23 @@ pub trait Requestable {
         method: crate::Method,
         href: S,
         body: Option<&str>,
-        headers: Option<BTreeMap<&'static str, &'static str>>,
+        headers: Option<Headers>,
     ) -> Result<String>
     where
         S: Into<String>,
     {
         let href = href.into();
-        let mut request = attohttpc::RequestBuilder::new(
-            attohttpc::Method::from_bytes(method.to_string().as_bytes()).unwrap(),
+        let response = self.session().send(
+            &method.to_string(),
             &href,
-        )
-        .text(body.unwrap_or_default());
-
-        if let Some(headers) = headers {
-            for (key, value) in &headers {
-                request = request.header(*key, *value);
-            }
-        }
-
-        if let Some(auth) = self.auth() {
-            request = request.basic_auth(auth.username, auth.password);
-        }
-
-        let response = request.send()?;
+            body,
+            &headers.unwrap_or_default(),
+        )?;
 
         if response.is_success() {
-            Ok(response.text()?)
+            Ok(response.body)
         } else {
-            Err(Error::new(method, &href, response))
+            Err(Error::from_response(method, &href, response))
         }
     }
 }
@@ -184,7 +263,7 @@ pub trait Children: Requestable + Xmlable {
             .map(|x| {
                 let mut element = C::new(self.append_host(x.clone()), &BTreeMap::new());
 
-                element.set_auth(self.auth());
+                element.set_session(self.session().clone());
 
                 element
             })
@@ -205,14 +284,14 @@ pub trait Children: Requestable + Xmlable {
         let keys = Self::xml(response, key_xpath);
 
         for key in keys {
-            let xkey = key.replace("\'", "&apos;");
+            let xkey = xpath_literal(&key);
 
-            let xpath = value_xpath.replace("{}", xkey.as_str());
+            let xpath = value_xpath.replace("'{}'", xkey.as_str());
             let values = Self::xml(response, xpath.as_str());
 
             let mut params = BTreeMap::new();
             for (param_name, param_xpath) in &params_xpath {
-                let xpath = param_xpath.replace("{}", xkey.as_str());
+                let xpath = param_xpath.replace("'{}'", xkey.as_str());
                 if let Some(param) = Self::xml(response, &xpath).first() {
                     params.insert(param_name.to_string(), param.clone());
                 }
@@ -220,7 +299,7 @@ pub trait Children: Requestable + Xmlable {
 
             if let Some(value) = values.first() {
                 let mut element = C::new(self.append_host(value.clone()), &params);
-                element.set_auth(self.auth());
+                element.set_session(self.session().clone());
 
                 map.insert(key.to_string(), element);
             }
@@ -230,8 +309,70 @@ pub trait Children: Requestable + Xmlable {
     }
 }
 
+/**
+ * Quotes `s` as an XPath 1.0 string literal, which has no escape sequence.
+ */
+fn xpath_literal(s: &str) -> String {
+    if !s.contains('\'') {
+        format!("'{s}'")
+    } else if !s.contains('"') {
+        format!("\"{s}\"")
+    } else {
+        let parts = s
+            .split('\'')
+            .map(|x| format!("'{x}'"))
+            .collect::<Vec<_>>()
+            .join(", \"'\", ");
+
+        format!("concat({parts})")
+    }
+}
+
 #[cfg(test)]
 mod test {
+    /**
+     * A temporary path, removed with its content when dropped, even when the test fails.
+     */
+    pub(crate) struct TempPath(std::path::PathBuf);
+
+    impl TempPath {
+        pub fn new(prefix: &str, suffix: &str) -> Self {
+            let name = format!("{prefix}-{}{suffix}", uuid::Uuid::now_v7());
+
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl std::ops::Deref for TempPath {
+        type Target = std::path::Path;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl AsRef<std::path::Path> for TempPath {
+        fn as_ref(&self) -> &std::path::Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<std::ffi::OsStr> for TempPath {
+        fn as_ref(&self) -> &std::ffi::OsStr {
+            self.0.as_os_str()
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            if self.0.is_dir() {
+                std::fs::remove_dir_all(&self.0).ok();
+            } else {
+                std::fs::remove_file(&self.0).ok();
+            }
+        }
+    }
+
     pub(crate) fn server() -> httpmock::MockServer {
         env_logger::try_init().ok();
         let server = httpmock::MockServer::start();
@@ -363,6 +504,30 @@ mod test {
 "#);
         });
 
+        server.mock(|when, then| {
+            when.path("/calendars/johndoe/home/")
+                .header("Depth", "0")
+                .body(r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:displayname /><getctag xmlns="http://calendarserver.org/ns/" /></d:prop></d:propfind>
+"#);
+
+            then.status(207)
+                .body(r#"
+<d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+    <d:response>
+        <d:href>/calendars/johndoe/home/</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:displayname>Home calendar</d:displayname>
+                <cs:getctag>3145</cs:getctag>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+    </d:response>
+</d:multistatus>
+"#);
+        });
+
         server.mock(|when, then| {
             when.path("/calendars/johndoe/home/")
                 .header("Depth", "1")
@@ -528,7 +693,7 @@ END:VCALENDAR{eol}
     </d:set>
 </c:mkcalendar>
 "#,
-                    eol = "\r"
+                    eol = "]]>&#13;<![CDATA["
                 ));
 
             then.status(201);
@@ -536,4 +701,63 @@ END:VCALENDAR{eol}
 
         server
     }
+
+    #[test]
+    fn allprop() -> crate::Result {
+        use crate::Requestable as _;
+
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+        server.put("home", "1.ics", "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n");
+
+        let client = crate::Client::new(server.url(""));
+        let responses = client.allprop(
+            server.url("/calendars/johndoe/home/"),
+            crate::elements::Depth::One,
+        )?;
+
+        assert_eq!(
+            responses.keys().collect::<Vec<_>>(),
+            ["/calendars/johndoe/home/", "/calendars/johndoe/home/1.ics"]
+        );
+
+        let calendar = &responses["/calendars/johndoe/home/"];
+        let displayname = &calendar[&crate::elements::PropName::dav("displayname")];
+        assert_eq!(displayname.status, 200);
+        assert_eq!(displayname.value.text, "Home calendar");
+
+        let object = &responses["/calendars/johndoe/home/1.ics"];
+        assert!(object.contains_key(&crate::elements::PropName::dav("getetag")));
+        assert!(!object.contains_key(&crate::elements::PropName::caldav("calendar-data")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn propname() -> crate::Result {
+        use crate::Requestable as _;
+
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+
+        let client = crate::Client::new(server.url(""));
+        let responses = client.propname(
+            server.url("/calendars/johndoe/home/"),
+            crate::elements::Depth::Zero,
+        )?;
+
+        let calendar = &responses["/calendars/johndoe/home/"];
+        let displayname = &calendar[&crate::elements::PropName::dav("displayname")];
+        assert_eq!(displayname.status, 200);
+        assert!(displayname.value.text.is_empty());
+        assert!(calendar.contains_key(&crate::elements::PropName::calendarserver("getctag")));
+
+        Ok(())
+    }
 }
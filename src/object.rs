@@ -3,6 +3,7 @@ use crate::Requestable;
 pub struct Iterator {
     objects: Vec<Object>,
     current: usize,
+    resolver: crate::timezone::Resolver,
 }
 
 impl Iterator {
@@ -10,9 +11,19 @@ impl Iterator {
         Self {
             objects,
             current: 0,
+            resolver: crate::timezone::Resolver::default(),
         }
     }
 
+    /**
+     * Resolves the time zones of [`Self::zoned`] with `resolver`.
+     */
+    pub(crate) fn with_resolver(mut self, resolver: crate::timezone::Resolver) -> Self {
+        self.resolver = resolver;
+
+        self
+    }
+
     pub fn is_empty(&self) -> bool {
         self.objects.is_empty()
     }
@@ -21,9 +32,31 @@ impl Iterator {
         self.objects.len()
     }
 
+    /**
+     * The remaining calendars, with the time zones of their date-times.
+     *
+     * See [`crate::timezone::Calendar`].
+     */
+    pub fn zoned(
+        self,
+    ) -> impl std::iter::Iterator<Item = crate::Result<(ikal::VCalendar, crate::timezone::Calendar)>>
+    {
+        let resolver = self.resolver;
+
+        self.objects
+            .into_iter()
+            .skip(self.current)
+            .map(move |object| {
+                let data = object.data()?;
+                let zoned = crate::timezone::Calendar::new(&data, resolver.clone())?;
+
+                Ok((ikal::VCalendar::try_from(data)?, zoned))
+            })
+    }
+
     fn get(&self, index: usize) -> crate::Result<ikal::VCalendar> {
-        let object = &self.objects[index];
-        let contents = object.get(object.url.clone())?;
+        let contents = self.objects[index].data()?;
+
         ikal::VCalendar::try_from(contents).map_err(crate::Error::from)
     }
 }
@@ -44,12 +77,72 @@ impl std::iter::Iterator for Iterator {
 #[derive(Clone, Debug)]
 pub struct Object {
     url: String,
-    auth: Option<crate::Authorization>,
+    session: crate::Session,
+    data: Option<String>,
+    /** Requests the data without `VTIMEZONE` components, see RFC 7809. */
+    no_timezone: bool,
+    /** Etag listed by the calendar, the data is cached for this version. */
+    etag: Option<String>,
 }
 
 impl Object {
+    /**
+     * Uses the calendar data already returned by the server instead of requesting it.
+     */
+    pub(crate) fn with_data(mut self, data: Option<String>) -> Self {
+        self.data = data;
+
+        self
+    }
+
+    pub(crate) fn with_etag(mut self, etag: Option<String>) -> Self {
+        self.etag = etag;
+
+        self
+    }
+
+    pub(crate) fn without_timezones(mut self, no_timezone: bool) -> Self {
+        self.no_timezone = no_timezone;
+
+        self
+    }
+
+    fn data(&self) -> crate::Result<String> {
+        if let Some(data) = &self.data {
+            return Ok(data.clone());
+        }
+
+        let cache = self.session.cache().zip(self.etag.as_deref());
+
+        if let Some((cache, etag)) = cache
+            && let Some(data) = cache.object(&self.url, etag, self.no_timezone)
+        {
+            return Ok(data);
+        }
+
+        let mut headers = crate::Headers::new();
+
+        if self.no_timezone {
+            headers.insert("Prefer", "calendar-no-timezone");
+        }
+
+        let data = self.request(crate::Method::GET, &self.url, None, Some(headers))?;
+
+        if let Some((cache, etag)) = cache {
+            cache.set_object(&self.url, etag, self.no_timezone, &data)?;
+        }
+
+        Ok(data)
+    }
+
     pub fn delete(&self) -> crate::Result {
-        crate::Requestable::delete(self, &self.url)
+        crate::Requestable::delete(self, &self.url)?;
+
+        if let Some(cache) = self.session.cache() {
+            cache.invalidate(&self.url)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -60,7 +153,10 @@ impl crate::Children for Object {
     {
         Self {
             url: url.into(),
-            auth: None,
+            session: crate::Session::default(),
+            data: None,
+            no_timezone: false,
+            etag: None,
         }
     }
 }
@@ -72,12 +168,12 @@ impl crate::Xmlable for Object {
 }
 
 impl crate::Requestable for Object {
-    fn auth(&self) -> Option<crate::Authorization> {
-        self.auth.clone()
+    fn session(&self) -> &crate::Session {
+        &self.session
     }
 
-    fn set_auth(&mut self, auth: Option<crate::Authorization>) {
-        self.auth = auth;
+    fn set_session(&mut self, session: crate::Session) {
+        self.session = session;
     }
 }
 
@@ -5,13 +5,14 @@ use std::convert::Into;
 #[derive(Clone, Debug, Default, crate::Object)]
 pub struct Principal {
     url: String,
-    auth: Option<crate::Authorization>,
+    session: crate::Session,
 }
 
 impl Principal {
     pub fn home(&self) -> crate::Result<crate::Home> {
-        let response = self.propfind(
+        let response = self.propfind_raw(
             &self.url,
+            crate::elements::Depth::Zero,
             r#"
 <d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
   <d:prop>
@@ -0,0 +1,421 @@
+use crate::elements::filter::{CompChild, CompFilter, PropFilter, Test, TextMatch};
+use crate::elements::{Filter, TimeRange};
+
+/**
+ * A high-level search on a calendar, compiled to a [`Filter`].
+ *
+ * ```no_run
+ * # fn main() -> kaldav::Result {
+ * # let calendar = kaldav::Calendar::default();
+ * let start = chrono::Utc::now();
+ * let end = start + chrono::TimeDelta::days(7);
+ *
+ * let events = calendar
+ *     .query()
+ *     .events()
+ *     .between(start, end)
+ *     .summary_contains("standup")
+ *     .with_attendee("john@example.org")
+ *     .not_cancelled()
+ *     .search()?;
+ * # Ok(())
+ * # }
+ * ```
+ */
+#[derive(Clone, Debug)]
+pub struct Query<'a> {
+    calendar: &'a crate::Calendar,
+    component: Option<String>,
+    time_range: Option<TimeRange>,
+    conditions: Vec<Vec<CompChild>>,
+}
+
+impl<'a> Query<'a> {
+    pub(crate) fn new(calendar: &'a crate::Calendar) -> Self {
+        Self {
+            calendar,
+            component: None,
+            time_range: None,
+            conditions: Vec::new(),
+        }
+    }
+
+    /**
+     * Searches events, the query matches events, tasks and journal entries by default.
+     */
+    pub fn events(self) -> Self {
+        self.component("VEVENT")
+    }
+
+    pub fn tasks(self) -> Self {
+        self.component("VTODO")
+    }
+
+    pub fn journals(self) -> Self {
+        self.component("VJOURNAL")
+    }
+
+    pub fn component(mut self, name: &str) -> Self {
+        self.component = Some(name.to_string());
+
+        self
+    }
+
+    /**
+     * Keeps the components overlapping `[start, end[`, recurrences included.
+     */
+    pub fn between<Tz: chrono::TimeZone>(
+        self,
+        start: chrono::DateTime<Tz>,
+        end: chrono::DateTime<Tz>,
+    ) -> Self {
+        self.time_range(TimeRange {
+            start: Some(start.to_utc()),
+            end: Some(end.to_utc()),
+        })
+    }
+
+    pub fn after<Tz: chrono::TimeZone>(self, start: chrono::DateTime<Tz>) -> Self {
+        self.time_range(TimeRange::after(start))
+    }
+
+    pub fn before<Tz: chrono::TimeZone>(self, end: chrono::DateTime<Tz>) -> Self {
+        self.time_range(TimeRange::before(end))
+    }
+
+    /**
+     * Keeps the components overlapping `time_range`, like the whole days built by
+     * [`TimeRange::dates`].
+     *
+     * The query has a single time range: each bound is the one given by the last call setting
+     * it, so `.between(start, end).after(later)` searches from `later` to `end`.
+     */
+    pub fn time_range(mut self, time_range: TimeRange) -> Self {
+        let current = self.time_range.take().unwrap_or_default();
+
+        self.time_range = Some(TimeRange {
+            start: time_range.start.or(current.start),
+            end: time_range.end.or(current.end),
+        });
+
+        self
+    }
+
+    /**
+     * Keeps the components whose `SUMMARY` contains `text`, ignoring ASCII case.
+     */
+    pub fn summary_contains(self, text: &str) -> Self {
+        self.property_contains("SUMMARY", text)
+    }
+
+    pub fn description_contains(self, text: &str) -> Self {
+        self.property_contains("DESCRIPTION", text)
+    }
+
+    pub fn location_contains(self, text: &str) -> Self {
+        self.property_contains("LOCATION", text)
+    }
+
+    pub fn with_category(self, category: &str) -> Self {
+        self.property_contains("CATEGORIES", category)
+    }
+
+    /**
+     * Keeps the components with an `ATTENDEE` whose address contains `address`.
+     */
+    pub fn with_attendee(self, address: &str) -> Self {
+        self.property_contains("ATTENDEE", address)
+    }
+
+    pub fn property_contains(self, name: &str, text: &str) -> Self {
+        self.prop_filter(PropFilter::new(name).text_match(TextMatch::new(text)))
+    }
+
+    /**
+     * Excludes the components with a `CANCELLED` status.
+     *
+     * A missing `STATUS` can’t be matched with a text match, this adds an alternative to the
+     * filter, see [`Self::filter`].
+     */
+    pub fn not_cancelled(self) -> Self {
+        self.alternatives(vec![
+            CompChild::PropFilter(PropFilter::new("STATUS").is_not_defined(true)),
+            CompChild::PropFilter(
+                PropFilter::new("STATUS")
+                    .text_match(TextMatch::new("CANCELLED").negate_condition(true)),
+            ),
+        ])
+    }
+
+    /**
+     * Adds a custom condition on the searched components.
+     */
+    pub fn prop_filter(self, prop_filter: PropFilter) -> Self {
+        self.condition(CompChild::PropFilter(prop_filter))
+    }
+
+    fn condition(self, condition: CompChild) -> Self {
+        self.alternatives(vec![condition])
+    }
+
+    /**
+     * Adds a condition satisfied by any of `alternatives`.
+     */
+    fn alternatives(mut self, alternatives: Vec<CompChild>) -> Self {
+        self.conditions.push(alternatives);
+
+        self
+    }
+
+    /**
+     * Compiles the query to a single `VCALENDAR` filter, with one `comp-filter` per component
+     * and combination of conditions, combined with the `anyof` test when there are several.
+     * [`crate::Calendar::search`] sends a request per alternative, unless the calendar is read
+     * [`crate::Calendar::with_anyof`].
+     */
+    pub fn filter(&self) -> Filter {
+        let components = match &self.component {
+            Some(component) => vec![component.as_str()],
+            None if self.conditions.is_empty() && self.time_range.is_none() => {
+                return Filter::new().append(CompFilter::new("VCALENDAR"));
+            }
+            None => vec!["VEVENT", "VTODO", "VJOURNAL"],
+        };
+
+        let combinations =
+            crate::elements::filter::combine(Test::AllOf, self.conditions.iter().cloned());
+
+        let mut calendar = CompFilter::new("VCALENDAR");
+
+        for component in components {
+            for children in &combinations {
+                let mut comp_filter = CompFilter::new(component);
+                // The time range comes first in the RFC 4791 grammar
+                comp_filter.children = self
+                    .time_range
+                    .clone()
+                    .map(CompChild::TimeRange)
+                    .into_iter()
+                    .chain(children.iter().cloned())
+                    .collect();
+
+                calendar = calendar.append(comp_filter);
+            }
+        }
+
+        if calendar.children.len() > 1 {
+            calendar = calendar.test(Test::AnyOf);
+        }
+
+        Filter::new().append(calendar)
+    }
+
+    /**
+     * Runs the query. The calendar data of the searched component and its time zones are
+     * requested with the results, to avoid a request per object. The time zones are left out
+     * for a calendar read [`crate::Calendar::without_timezones`].
+     */
+    pub fn search(&self) -> crate::Result<crate::object::Iterator> {
+        let timezone = if self.calendar.has_timezones() {
+            r#"<c:comp name="VTIMEZONE"><c:allprop /><c:allcomp /></c:comp>"#
+        } else {
+            ""
+        };
+
+        let components = match &self.component {
+            Some(component) => format!(
+                r#"<c:comp name="{}"><c:allprop /><c:allcomp /></c:comp>{timezone}"#,
+                crate::elements::escape::attribute(component)
+            ),
+            None => "<c:allcomp />".to_string(),
+        };
+
+        let prop = format!(
+            r#"<d:getetag /><c:calendar-data><c:comp name="VCALENDAR"><c:allprop />{components}</c:comp></c:calendar-data>"#
+        );
+
+        self.calendar.search_with(&self.filter(), &prop)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone as _;
+
+    fn event(uid: &str, summary: &str, extra: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:{uid}\r
+DTSTAMP:20240101T090000Z\r
+DTSTART:20240108T090000Z\r
+DTEND:20240108T091500Z\r
+SUMMARY:{summary}\r
+{extra}END:VEVENT\r
+END:VCALENDAR\r
+"
+        )
+    }
+
+    #[test]
+    fn filter() {
+        use webdav::ToXml as _;
+
+        let calendar = crate::Calendar::default();
+
+        let filter = calendar
+            .query()
+            .events()
+            .summary_contains("standup")
+            .not_cancelled()
+            .filter();
+
+        assert_eq!(filter.comp_filters.len(), 1);
+        assert_eq!(
+            filter.comp_filters[0].test,
+            crate::elements::filter::Test::AnyOf
+        );
+        assert_eq!(
+            filter.alternatives()[1].to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"SUMMARY\"><c:text-match><![CDATA[standup]]></c:text-match></c:prop-filter><c:prop-filter name=\"STATUS\"><c:text-match negate-condition=\"yes\"><![CDATA[CANCELLED]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>",
+        );
+
+        let filter = calendar.query().summary_contains("standup").filter();
+        assert_eq!(filter.comp_filters.len(), 1);
+        assert_eq!(filter.comp_filters[0].children.len(), 3);
+
+        let filter = calendar
+            .query()
+            .events()
+            .summary_contains("standup")
+            .filter();
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:prop-filter name=\"SUMMARY\"><c:text-match><![CDATA[standup]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>"
+        );
+
+        assert_eq!(
+            calendar.query().filter().to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\" /></c:filter>"
+        );
+    }
+
+    #[test]
+    fn time_range() -> crate::Result {
+        use webdav::ToXml as _;
+
+        let calendar = crate::Calendar::default();
+        let date = |day| chrono::Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+
+        let filter = calendar
+            .query()
+            .events()
+            .summary_contains("standup")
+            .between(date(1), date(31))
+            .after(date(8))
+            .filter();
+        filter.validate()?;
+
+        assert_eq!(
+            filter.to_xml(),
+            "<c:filter><c:comp-filter name=\"VCALENDAR\"><c:comp-filter name=\"VEVENT\"><c:time-range start=\"20240108T000000Z\" end=\"20240131T000000Z\" /><c:prop-filter name=\"SUMMARY\"><c:text-match><![CDATA[standup]]></c:text-match></c:prop-filter></c:comp-filter></c:comp-filter></c:filter>"
+        );
+
+        let filter = calendar.query().after(date(8)).before(date(15)).filter();
+        filter.validate()?;
+        assert!(filter.comp_filters[0].children.iter().all(|x| matches!(
+            x,
+            crate::elements::filter::CompChild::CompFilter(x)
+                if x.children == [crate::elements::filter::CompChild::TimeRange(
+                    crate::elements::TimeRange::new(date(8), date(15)).unwrap()
+                )]
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn search() -> crate::Result {
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+
+        let attendee = "ATTENDEE:mailto:john@example.org\r\n";
+        server.put("home", "1.ics", &event("1", "Daily standup", attendee));
+        server.put(
+            "home",
+            "2.ics",
+            &event(
+                "2",
+                "Daily standup",
+                &format!("{attendee}STATUS:CANCELLED\r\n"),
+            ),
+        );
+        server.put(
+            "home",
+            "3.ics",
+            &event(
+                "3",
+                "Weekly standup",
+                &format!("{attendee}STATUS:CONFIRMED\r\n"),
+            ),
+        );
+        server.put("home", "4.ics", &event("4", "Daily standup", ""));
+        server.put("home", "5.ics", &event("5", "Lunch", attendee));
+
+        let client = crate::Client::new(server.url(""));
+        let calendars = client.calendars()?;
+        let calendar = &calendars["Home calendar"];
+
+        let query = || {
+            calendar
+                .query()
+                .events()
+                .between(
+                    chrono::Utc.with_ymd_and_hms(2024, 1, 8, 0, 0, 0).unwrap(),
+                    chrono::Utc.with_ymd_and_hms(2024, 1, 9, 0, 0, 0).unwrap(),
+                )
+                .summary_contains("STANDUP")
+                .with_attendee("john@example.org")
+                .not_cancelled()
+        };
+
+        let mut uids = query()
+            .search()?
+            .map(|x| x.events[0].uid.to_string())
+            .collect::<Vec<_>>();
+        uids.sort();
+
+        assert_eq!(uids, ["1", "3"]);
+        assert!(calendar.supports_anyof()?);
+
+        // A server ignoring the `test` attribute returns the same objects, the alternatives
+        // being sent separately.
+        server.set_ignore_test(true);
+        assert!(!calendar.supports_anyof()?);
+
+        let mut uids = query()
+            .search()?
+            .map(|x| x.events[0].uid.to_string())
+            .collect::<Vec<_>>();
+        uids.sort();
+
+        assert_eq!(uids, ["1", "3"]);
+
+        let anyof = calendar.clone().with_anyof();
+        let found = anyof.query().events().not_cancelled().search()?;
+        assert!(found.is_empty());
+
+        let found = calendar
+            .query()
+            .before(chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+            .search()?;
+        assert!(found.is_empty());
+
+        Ok(())
+    }
+}
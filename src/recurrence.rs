@@ -0,0 +1,511 @@
+/*!
+ * Expansion of the recurrence rules, shared by the filters and the time zone observances.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.10>
+ */
+
+use crate::timezone;
+use chrono::{
+    Datelike as _, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike as _, Weekday,
+};
+use std::ops::ControlFlow;
+
+/**
+ * Upper bound of the periods (years, months, days…) of a recurrence rule looked at, whether they
+ * contain an occurrence or not.
+ */
+const MAX_PERIODS: i64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/**
+ * A recurrence rule.
+ *
+ * The `BYxxx` parts left out default to the corresponding part of `DTSTART`, as needed by the
+ * frequency.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.10>
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<usize>,
+    pub until: Option<timezone::DateTime>,
+    pub by_second: Vec<u32>,
+    pub by_minute: Vec<u32>,
+    pub by_hour: Vec<u32>,
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+    pub by_year_day: Vec<i32>,
+    pub by_week_no: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub week_start: Weekday,
+}
+
+impl Rule {
+    pub fn parse(value: &str, start: &timezone::DateTime) -> crate::Result<Self> {
+        let invalid = |reason: &str| crate::Error::Recurrence(format!("{value}: {reason}"));
+        let unsigned = |name: &str, value: &str, min: u32, max: u32| {
+            value
+                .split(',')
+                .map(|x| x.parse().ok().filter(|x| (min..=max).contains(x)))
+                .collect::<Option<Vec<u32>>>()
+                .ok_or_else(|| invalid(&format!("invalid {name}")))
+        };
+        let signed = |name: &str, value: &str, max: i32| {
+            value
+                .split(',')
+                .map(|x| x.parse::<i32>().ok().filter(|x| *x != 0 && x.abs() <= max))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid(&format!("invalid {name}")))
+        };
+
+        let mut freq = None;
+        let mut rule = Self {
+            freq: Frequency::Yearly,
+            interval: 1,
+            count: None,
+            until: None,
+            by_second: Vec::new(),
+            by_minute: Vec::new(),
+            by_hour: Vec::new(),
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_year_day: Vec::new(),
+            by_week_no: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: Weekday::Mon,
+        };
+
+        for part in value.split(';').filter(|x| !x.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(&format!("invalid part {part}")))?;
+            let name = name.to_ascii_uppercase();
+
+            match name.as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "SECONDLY" => Frequency::Secondly,
+                        "MINUTELY" => Frequency::Minutely,
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid("invalid FREQ")),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|x| *x > 0)
+                        .ok_or_else(|| invalid("invalid INTERVAL"))?
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid("invalid COUNT"))?),
+                "UNTIL" => {
+                    rule.until = Some(
+                        timezone::DateTime::parse(value, None)
+                            .map_err(|_| invalid("invalid UNTIL"))?,
+                    )
+                }
+                "BYSECOND" => rule.by_second = unsigned(&name, value, 0, 60)?,
+                "BYMINUTE" => rule.by_minute = unsigned(&name, value, 0, 59)?,
+                "BYHOUR" => rule.by_hour = unsigned(&name, value, 0, 23)?,
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|x| {
+                            let (ordinal, day) = x.split_at(x.len().saturating_sub(2));
+                            let ordinal = match ordinal {
+                                "" => None,
+                                ordinal => Some(
+                                    ordinal
+                                        .parse::<i32>()
+                                        .ok()
+                                        .filter(|x| *x != 0 && x.abs() <= 53)?,
+                                ),
+                            };
+
+                            Some((ordinal, weekday(day)?))
+                        })
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| invalid("invalid BYDAY"))?
+                }
+                "BYMONTHDAY" => rule.by_month_day = signed(&name, value, 31)?,
+                "BYYEARDAY" => rule.by_year_day = signed(&name, value, 366)?,
+                "BYWEEKNO" => rule.by_week_no = signed(&name, value, 53)?,
+                "BYMONTH" => rule.by_month = unsigned(&name, value, 1, 12)?,
+                "BYSETPOS" => rule.by_set_pos = signed(&name, value, 366)?,
+                "WKST" => {
+                    rule.week_start = weekday(value).ok_or_else(|| invalid("invalid WKST"))?
+                }
+                _ => return Err(invalid(&format!("unknown part {name}"))),
+            }
+        }
+
+        rule.freq = freq.ok_or_else(|| invalid("missing FREQ"))?;
+
+        let ordinals = rule.by_day.iter().any(|(x, _)| x.is_some());
+
+        if ordinals
+            && (rule.freq < Frequency::Monthly
+                || rule.freq == Frequency::Yearly && !rule.by_week_no.is_empty())
+        {
+            return Err(invalid("BYDAY with an ordinal for this frequency"));
+        }
+        if !rule.by_week_no.is_empty() && rule.freq != Frequency::Yearly {
+            return Err(invalid("BYWEEKNO for this frequency"));
+        }
+        if !rule.by_year_day.is_empty()
+            && matches!(
+                rule.freq,
+                Frequency::Daily | Frequency::Weekly | Frequency::Monthly
+            )
+        {
+            return Err(invalid("BYYEARDAY for this frequency"));
+        }
+        if !rule.by_month_day.is_empty() && rule.freq == Frequency::Weekly {
+            return Err(invalid("BYMONTHDAY for this frequency"));
+        }
+        if matches!(start, timezone::DateTime::Date(_))
+            && (rule.freq < Frequency::Daily
+                || !rule.by_hour.is_empty()
+                || !rule.by_minute.is_empty()
+                || !rule.by_second.is_empty())
+        {
+            return Err(invalid("time parts with a DATE start"));
+        }
+
+        let start = start.naive();
+
+        if rule.by_week_no.is_empty()
+            && rule.by_year_day.is_empty()
+            && rule.by_month_day.is_empty()
+            && rule.by_day.is_empty()
+        {
+            match rule.freq {
+                Frequency::Yearly => {
+                    if rule.by_month.is_empty() {
+                        rule.by_month = vec![start.month()];
+                    }
+
+                    rule.by_month_day = vec![start.day() as i32];
+                }
+                Frequency::Monthly => rule.by_month_day = vec![start.day() as i32],
+                Frequency::Weekly => rule.by_day = vec![(None, start.weekday())],
+                _ => (),
+            }
+        }
+
+        if rule.freq > Frequency::Hourly && rule.by_hour.is_empty() {
+            rule.by_hour = vec![start.hour()];
+        }
+        if rule.freq > Frequency::Minutely && rule.by_minute.is_empty() {
+            rule.by_minute = vec![start.minute()];
+        }
+        if rule.freq > Frequency::Secondly && rule.by_second.is_empty() {
+            rule.by_second = vec![start.second()];
+        }
+
+        for list in [&mut rule.by_hour, &mut rule.by_minute, &mut rule.by_second] {
+            list.sort();
+            list.dedup();
+        }
+
+        Ok(rule)
+    }
+
+    /**
+     * Calls `f` with the local time of each occurrence at or after `start`, in order, until it
+     * breaks.
+     *
+     * The periods ending before `after` are skipped, without looking at their occurrences, so
+     * `after` must be `None` when they all count, like with `COUNT`. The `COUNT` and `UNTIL` parts
+     * are left to `f`.
+     */
+    pub fn occurrences<F>(
+        &self,
+        start: NaiveDateTime,
+        after: Option<NaiveDateTime>,
+        mut f: F,
+    ) -> crate::Result
+    where
+        F: FnMut(NaiveDateTime) -> crate::Result<ControlFlow<()>>,
+    {
+        let skipped = after.map_or(0, |x| self.skipped(start, x));
+
+        for n in skipped..skipped.saturating_add(MAX_PERIODS) {
+            let Some(period) = self.period(start, n * i64::from(self.interval)) else {
+                return Ok(());
+            };
+
+            // Date-times have 4 digit years
+            if period.year() > 9999 {
+                return Ok(());
+            }
+
+            let times = self.times(period);
+            let mut set = self
+                .days(period.date())
+                .into_iter()
+                .flat_map(|day| times.iter().map(move |x| day.and_time(*x)))
+                .collect::<Vec<_>>();
+
+            if !self.by_set_pos.is_empty() {
+                let length = set.len() as i32;
+
+                set = set
+                    .into_iter()
+                    .zip(1..)
+                    .filter(|(_, n)| {
+                        self.by_set_pos
+                            .iter()
+                            .any(|x| *x == *n || *x == *n - length - 1)
+                    })
+                    .map(|(x, _)| x)
+                    .collect();
+            }
+
+            for occurrence in set.into_iter().filter(|x| *x >= start) {
+                if f(occurrence)?.is_break() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(crate::Error::Recurrence(format!(
+            "more than {MAX_PERIODS} periods expanded"
+        )))
+    }
+
+    /**
+     * Number of periods, counted from the one of `start`, that end before `after`.
+     */
+    fn skipped(&self, start: NaiveDateTime, after: NaiveDateTime) -> i64 {
+        let Some(first) = self.period(start, 0) else {
+            return 0;
+        };
+
+        let units = match self.freq {
+            Frequency::Yearly => i64::from(after.year() - first.year()),
+            Frequency::Monthly => {
+                i64::from(after.year() - first.year()) * 12 + i64::from(after.month0())
+                    - i64::from(first.month0())
+            }
+            Frequency::Weekly => (after - first).num_weeks(),
+            Frequency::Daily => (after - first).num_days(),
+            Frequency::Hourly => (after - first).num_hours(),
+            Frequency::Minutely => (after - first).num_minutes(),
+            Frequency::Secondly => (after - first).num_seconds(),
+        };
+
+        // Keeps the period before, as the partial periods are rounded down
+        (units / i64::from(self.interval) - 1).max(0)
+    }
+
+    /**
+     * Start of the period `offset` frequency units after the one of `start`.
+     */
+    fn period(&self, start: NaiveDateTime, offset: i64) -> Option<NaiveDateTime> {
+        let date = start.date();
+
+        let period = match self.freq {
+            Frequency::Yearly => {
+                let year = i32::try_from(i64::from(start.year()) + offset).ok()?;
+
+                NaiveDate::from_ymd_opt(year, 1, 1)?.and_time(NaiveTime::MIN)
+            }
+            Frequency::Monthly => {
+                let months = i64::from(start.year()) * 12 + i64::from(start.month0()) + offset;
+                let year = i32::try_from(months.div_euclid(12)).ok()?;
+
+                NaiveDate::from_ymd_opt(year, months.rem_euclid(12) as u32 + 1, 1)?
+                    .and_time(NaiveTime::MIN)
+            }
+            Frequency::Weekly => {
+                let days = days_since(date.weekday(), self.week_start);
+
+                date.checked_sub_signed(TimeDelta::days(days))?
+                    .checked_add_signed(TimeDelta::try_weeks(offset)?)?
+                    .and_time(NaiveTime::MIN)
+            }
+            Frequency::Daily => date
+                .checked_add_signed(TimeDelta::try_days(offset)?)?
+                .and_time(NaiveTime::MIN),
+            Frequency::Hourly => date
+                .and_hms_opt(start.hour(), 0, 0)?
+                .checked_add_signed(TimeDelta::try_hours(offset)?)?,
+            Frequency::Minutely => date
+                .and_hms_opt(start.hour(), start.minute(), 0)?
+                .checked_add_signed(TimeDelta::try_minutes(offset)?)?,
+            Frequency::Secondly => start.checked_add_signed(TimeDelta::try_seconds(offset)?)?,
+        };
+
+        Some(period)
+    }
+
+    /**
+     * Days of the period starting on `period` selected by the rule.
+     */
+    fn days(&self, period: NaiveDate) -> Vec<NaiveDate> {
+        let length = match self.freq {
+            Frequency::Yearly => year_length(period.year()),
+            Frequency::Monthly => month_length(period.year(), period.month()),
+            Frequency::Weekly => 7,
+            _ => 1,
+        };
+
+        period
+            .iter_days()
+            .take(length as usize)
+            .filter(|x| self.selects(*x))
+            .collect()
+    }
+
+    fn selects(&self, day: NaiveDate) -> bool {
+        let year_length = year_length(day.year());
+        let month_length = month_length(day.year(), day.month());
+        let any = |list: &[i32], n: u32, length: i32| {
+            let n = n as i32;
+
+            list.iter().any(|x| *x == n || *x == n - length - 1)
+        };
+
+        let by_week_no = || {
+            week_no(day, self.week_start).is_some_and(|(week, weeks)| {
+                self.by_week_no
+                    .iter()
+                    .any(|x| *x == week || *x == week - weeks - 1)
+            })
+        };
+        let by_day = || {
+            // Ordinals count in the month for monthly rules and yearly ones restricted to months
+            let (n, length) = if self.freq == Frequency::Monthly || !self.by_month.is_empty() {
+                (day.day() as i32, month_length)
+            } else {
+                (day.ordinal() as i32, year_length)
+            };
+
+            self.by_day.iter().any(|(ordinal, weekday)| {
+                day.weekday() == *weekday
+                    && ordinal.is_none_or(|x| x == (n - 1) / 7 + 1 || x == -((length - n) / 7 + 1))
+            })
+        };
+
+        (self.by_month.is_empty() || self.by_month.contains(&day.month()))
+            && (self.by_week_no.is_empty() || by_week_no())
+            && (self.by_year_day.is_empty() || any(&self.by_year_day, day.ordinal(), year_length))
+            && (self.by_month_day.is_empty() || any(&self.by_month_day, day.day(), month_length))
+            && (self.by_day.is_empty() || by_day())
+    }
+
+    /**
+     * Times of the period starting at `period`: the units finer than the frequency are expanded,
+     * the others are limited.
+     */
+    fn times(&self, period: NaiveDateTime) -> Vec<NaiveTime> {
+        let values = |list: &[u32], unit: Frequency, current: u32| {
+            if self.freq > unit {
+                list.to_vec()
+            } else if list.is_empty() || list.contains(&current) {
+                vec![current]
+            } else {
+                Vec::new()
+            }
+        };
+
+        let hours = values(&self.by_hour, Frequency::Hourly, period.hour());
+        let minutes = values(&self.by_minute, Frequency::Minutely, period.minute());
+        let seconds = values(&self.by_second, Frequency::Secondly, period.second());
+
+        hours
+            .iter()
+            .flat_map(|hour| minutes.iter().map(move |minute| (*hour, *minute)))
+            .flat_map(|(hour, minute)| {
+                seconds
+                    .iter()
+                    .filter_map(move |second| NaiveTime::from_hms_opt(hour, minute, *second))
+            })
+            .collect()
+    }
+}
+
+/**
+ * The week of `day` and the number of weeks of its week-numbering year.
+ *
+ * The first week of a year is the first one with at least 4 days in this year.
+ */
+fn week_no(day: NaiveDate, week_start: Weekday) -> Option<(i32, i32)> {
+    let first_week = |year: i32| {
+        let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+        let days = days_since(first.weekday(), week_start);
+
+        if days <= 3 {
+            first.checked_sub_signed(TimeDelta::days(days))
+        } else {
+            first.checked_add_signed(TimeDelta::days(7 - days))
+        }
+    };
+
+    let mut year = day.year();
+
+    if day < first_week(year)? {
+        year -= 1;
+    } else if day >= first_week(year + 1)? {
+        year += 1;
+    }
+
+    let start = first_week(year)?;
+    let week = (day - start).num_days() / 7 + 1;
+    let weeks = (first_week(year + 1)? - start).num_days() / 7;
+
+    Some((week as i32, weeks as i32))
+}
+
+fn days_since(day: Weekday, week_start: Weekday) -> i64 {
+    i64::from((7 + day.num_days_from_monday() - week_start.num_days_from_monday()) % 7)
+}
+
+fn year_length(year: i32) -> i32 {
+    if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+        366
+    } else {
+        365
+    }
+}
+
+fn month_length(year: i32, month: u32) -> i32 {
+    (28..=31)
+        .rev()
+        .find(|x| NaiveDate::from_ymd_opt(year, month, *x).is_some())
+        .unwrap_or(31) as i32
+}
+
+fn weekday(value: &str) -> Option<Weekday> {
+    let weekday = match value.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+
+    Some(weekday)
+}
@@ -8,6 +8,14 @@ pub enum Error {
     Misc(String),
     #[error("Parser error: {0}")]
     Parser(#[from] ikal::Error),
+    #[error("XML error: {0}")]
+    Xml(String),
+    #[error("Invalid filter: {0}")]
+    Filter(String),
+    #[error("Invalid time zone: {0}")]
+    Timezone(String),
+    #[error("Recurrence error: {0}")]
+    Recurrence(String),
     #[error("HTTP error: {0}")]
     Http(#[from] attohttpc::Error),
     #[error("{method} {href}: {status}")]
@@ -28,4 +36,17 @@ impl Error {
             body: response.text().unwrap_or_default(),
         }
     }
+
+    pub(crate) fn from_response(
+        method: crate::Method,
+        href: &str,
+        response: crate::transport::Response,
+    ) -> Self {
+        Self::Request {
+            method: method.to_string(),
+            href: href.to_string(),
+            status: response.status,
+            body: response.body,
+        }
+    }
 }
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+/**
+ * Why a request is retried.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /** The server responded with a retryable status, like `429` or `503`. */
+    Status(u16),
+    /** The connection failed before a response was received. */
+    Connection(String),
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Status(status) => write!(f, "status {status}"),
+            Self::Connection(error) => write!(f, "connection error: {error}"),
+        }
+    }
+}
+
+/**
+ * A retry about to happen, passed to [`Retry::on_retry`] callback.
+ */
+#[derive(Clone, Debug)]
+pub struct Attempt<'a> {
+    pub method: &'a str,
+    pub href: &'a str,
+    /** Number of the retry, starting at 1. */
+    pub attempt: u32,
+    pub reason: Reason,
+    /** Time waited before sending the request again. */
+    pub delay: Duration,
+}
+
+type Callback = dyn Fn(&Attempt) + Send + Sync;
+
+/**
+ * Retry policy for transient failures.
+ *
+ * Only idempotent methods are retried by default. A `PUT` is considered safe when it is
+ * conditional, i.e. sent with an `If-Match` or `If-None-Match` header.
+ *
+ * The delay between attempts grows exponentially from `initial_backoff` to `max_backoff`, unless
+ * the server sent a `Retry-After` header. A `Retry-After` longer than `max_backoff` isn’t
+ * retried, the response is returned instead.
+ */
+#[derive(Clone)]
+pub struct Retry {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /** Response statuses triggering a retry. */
+    pub statuses: Vec<u16>,
+    /** Retries non idempotent requests too, like `POST` or `MKCALENDAR`. */
+    pub non_idempotent: bool,
+    on_retry: Option<Arc<Callback>>,
+}
+
+impl Retry {
+    /**
+     * Never retries.
+     */
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Calls `callback` before each retry.
+     */
+    pub fn on_retry<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Attempt) + Send + Sync + 'static,
+    {
+        self.on_retry = Some(Arc::new(callback));
+
+        self
+    }
+
+    pub(crate) fn allows(&self, method: &str, headers: &crate::Headers) -> bool {
+        if self.non_idempotent {
+            return true;
+        }
+
+        match method {
+            "GET" | "HEAD" | "OPTIONS" | "DELETE" | "PROPFIND" | "REPORT" => true,
+            "PUT" => headers.keys().any(|x| {
+                x.eq_ignore_ascii_case("If-Match") || x.eq_ignore_ascii_case("If-None-Match")
+            }),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn reason(
+        &self,
+        result: &crate::Result<crate::transport::Response>,
+    ) -> Option<Reason> {
+        match result {
+            Ok(response) => {
+                let status = response.status.as_u16();
+
+                self.statuses
+                    .contains(&status)
+                    .then_some(Reason::Status(status))
+            }
+            Err(crate::Error::Http(error)) => match error.kind() {
+                attohttpc::ErrorKind::Io(error) if is_transient(error) => {
+                    Some(Reason::Connection(error.to_string()))
+                }
+                _ => None,
+            },
+            Err(crate::Error::Io(error)) if is_transient(error) => {
+                Some(Reason::Connection(error.to_string()))
+            }
+            Err(_) => None,
+        }
+    }
+
+    /**
+     * Delay before the `attempt`th retry, `None` if the server asked to wait longer than
+     * `max_backoff`.
+     */
+    pub(crate) fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_backoff).then_some(retry_after);
+        }
+
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+
+        Some(delay)
+    }
+
+    pub(crate) fn notify(&self, attempt: &Attempt) {
+        if let Some(on_retry) = &self.on_retry {
+            on_retry(attempt);
+        }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            statuses: vec![429, 502, 503, 504],
+            non_idempotent: false,
+            on_retry: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Retry")
+            .field("max_retries", &self.max_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("statuses", &self.statuses)
+            .field("non_idempotent", &self.non_idempotent)
+            .finish_non_exhaustive()
+    }
+}
+
+fn is_transient(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+
+    matches!(
+        error.kind(),
+        ConnectionRefused
+            | ConnectionReset
+            | ConnectionAborted
+            | BrokenPipe
+            | TimedOut
+            | WouldBlock
+            | Interrupted
+            | UnexpectedEof
+    )
+}
+
+/**
+ * Parses the `Retry-After` header of `response`.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc9110#section-10.2.3>
+ */
+pub(crate) fn retry_after(response: &crate::transport::Response) -> Option<Duration> {
+    let value = response
+        .headers
+        .get(attohttpc::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+
+    parse_retry_after(value, chrono::Utc::now())
+}
+
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+
+    Some((date.to_utc() - now).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    #[test]
+    fn retry_after() {
+        let now = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:27:00 GMT")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            super::parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            super::parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            super::parse_retry_after("Wed, 21 Oct 2015 07:20:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(super::parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn backoff() {
+        let retry = crate::Retry::default();
+
+        assert_eq!(retry.delay(1, None), Some(Duration::from_millis(500)));
+        assert_eq!(retry.delay(3, None), Some(Duration::from_secs(2)));
+        assert_eq!(retry.delay(20, None), Some(Duration::from_secs(30)));
+        assert_eq!(
+            retry.delay(1, Some(Duration::from_secs(5))),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            retry.delay(1, Some(Duration::from_secs(30))),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(retry.delay(1, Some(Duration::from_secs(120))), None);
+    }
+
+    #[test]
+    fn throttled() -> crate::Result {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let server = crate::test::server();
+
+        let mock = server.mock(|when, then| {
+            when.path("/throttled/");
+            then.status(503).header("Retry-After", "0");
+        });
+
+        let retries = std::sync::Arc::new(AtomicU32::new(0));
+        let counter = retries.clone();
+        let retry = crate::Retry {
+            max_retries: 2,
+            ..Default::default()
+        }
+        .on_retry(move |attempt| {
+            assert_eq!(attempt.reason, super::Reason::Status(503));
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let client = crate::Client::builder(server.url("/throttled/"))
+            .retry(retry)
+            .build()?;
+
+        assert!(client.principals().is_err());
+        assert_eq!(retries.load(Ordering::SeqCst), 2);
+        mock.assert_calls(3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn retry_after_exceeded() -> crate::Result {
+        let server = crate::test::server();
+
+        let mock = server.mock(|when, then| {
+            when.path("/throttled/");
+            then.status(429).header("Retry-After", "3600");
+        });
+
+        let client = crate::Client::builder(server.url("/throttled/"))
+            .retry(crate::Retry::default())
+            .build()?;
+
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            client.principals(),
+            Err(crate::Error::Request { status, .. }) if status == 429
+        ));
+        assert!(start.elapsed() < Duration::from_secs(30));
+        mock.assert_calls(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_idempotent() -> crate::Result {
+        use crate::Requestable as _;
+
+        let server = crate::test::server();
+
+        let mock = server.mock(|when, then| {
+            when.path("/throttled/");
+            then.status(503).header("Retry-After", "0");
+        });
+
+        let client = crate::Client::builder(server.url(""))
+            .retry(crate::Retry::default())
+            .build()?;
+
+        assert!(client.mkcalendar(server.url("/throttled/"), "").is_err());
+        mock.assert_calls(1);
+
+        Ok(())
+    }
+}
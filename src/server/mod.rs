@@ -0,0 +1,1497 @@
+/*!
+ * Server side of the protocol, to expose calendars stored by the application.
+ *
+ * The [`Server`] is independent of any HTTP framework: convert the incoming request to a
+ * [`Request`], pass it to [`Server::handle`] and send back the returned [`Response`].
+ *
+ * It serves one principal with the usual layout:
+ *
+ * - `{principal}`: the principal, `/principal/` by default;
+ * - `{home}`: the calendar home, `/calendars/` by default;
+ * - `{home}{calendar}/`: the calendars;
+ * - `{home}{calendar}/{object}`: the calendar objects.
+ */
+
+use crate::elements::{Element, Filter, Mkcalendar, PropName, Propfind};
+use crate::timezone::{Resolver, Timezone};
+use webdav::ToXml as _;
+
+/**
+ * A received HTTP request.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /** Path of the requested resource, the query string is ignored. */
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/**
+ * The response to send back to the client.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+
+        self
+    }
+
+    fn xml(status: u16, body: String) -> Self {
+        Self {
+            status,
+            body,
+            ..Default::default()
+        }
+        .header("Content-Type", "application/xml; charset=utf-8")
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc4918#section-16>
+     */
+    fn precondition(status: u16, precondition: PropName) -> Self {
+        Self::xml(
+            status,
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:error xmlns:d="DAV:">{}</d:error>
+"#,
+                precondition.to_xml()
+            ),
+        )
+    }
+
+    /**
+     * The details of the error are logged, not sent to the client.
+     */
+    fn error(error: &crate::Error) -> Self {
+        log(error);
+
+        Self {
+            status: 500,
+            body: "Internal Server Error".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/**
+ * How to return the `calendar-data` property of a calendar object.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Data {
+    Omitted,
+    Full,
+    /** Without the `VTIMEZONE` components, see RFC 7809. */
+    WithoutTimezones,
+}
+
+impl Data {
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-3.2>
+     */
+    fn from(request: &Request) -> Self {
+        let no_timezone = request
+            .header("Prefer")
+            .unwrap_or_default()
+            .split(',')
+            .any(|x| x.trim().eq_ignore_ascii_case("calendar-no-timezone"));
+
+        if no_timezone {
+            Self::WithoutTimezones
+        } else {
+            Self::Full
+        }
+    }
+
+    fn applied(self, response: Response) -> Response {
+        if self == Self::WithoutTimezones {
+            response.header("Preference-Applied", "calendar-no-timezone")
+        } else {
+            response
+        }
+    }
+}
+
+/**
+ * A calendar collection, as returned by the [`Storage`].
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Collection {
+    pub displayname: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    /** A `VCALENDAR` object containing the calendar `VTIMEZONE`. */
+    pub timezone: Option<String>,
+    /** Supported component types, all of them if empty. */
+    pub components: Vec<ikal::Components>,
+    /** Changes each time an object of the calendar changes. */
+    pub ctag: Option<String>,
+}
+
+/**
+ * A calendar object resource.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    pub data: String,
+    pub etag: String,
+}
+
+/**
+ * Where the application keeps its calendars.
+ *
+ * Calendars and objects are identified by their resource name, the last segment of their path.
+ */
+pub trait Storage {
+    fn calendars(&self) -> crate::Result<Vec<String>>;
+    fn calendar(&self, name: &str) -> crate::Result<Option<Collection>>;
+    fn create_calendar(&self, name: &str, mkcalendar: &Mkcalendar) -> crate::Result;
+    fn delete_calendar(&self, name: &str) -> crate::Result;
+
+    fn objects(&self, calendar: &str) -> crate::Result<Vec<String>>;
+    fn object(&self, calendar: &str, name: &str) -> crate::Result<Option<Object>>;
+    /**
+     * Creates or replaces an object, returns its new etag. The calendar exists.
+     */
+    fn put(&self, calendar: &str, name: &str, data: &str) -> crate::Result<String>;
+    fn delete(&self, calendar: &str, name: &str) -> crate::Result;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Resource {
+    Root,
+    Principal,
+    Home,
+    Calendar(String),
+    Object(String, String),
+}
+
+/**
+ * A CalDAV endpoint on top of a [`Storage`].
+ */
+#[derive(Debug)]
+pub struct Server<S> {
+    storage: S,
+    principal: String,
+    home: String,
+    resolver: Resolver,
+}
+
+impl<S: Storage> Server<S> {
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            principal: "/principal/".to_string(),
+            home: "/calendars/".to_string(),
+            resolver: Resolver::default(),
+        }
+    }
+
+    /**
+     * Path of the principal, must end with a slash.
+     */
+    pub fn principal(mut self, path: &str) -> Self {
+        self.principal = path.to_string();
+
+        self
+    }
+
+    /**
+     * Path of the calendar home, must end with a slash.
+     */
+    pub fn home(mut self, path: &str) -> Self {
+        self.home = path.to_string();
+
+        self
+    }
+
+    /**
+     * Resolves the time zones of the `calendar-query` reports. Floating date-times are in the
+     * time zone of the query, then in the one of the calendar, then in the floating one of
+     * `resolver`.
+     */
+    pub fn resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+
+        self
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        let Some(resource) = self.resolve(&request.path) else {
+            return Response::new(404);
+        };
+
+        let result = match request.method.as_str() {
+            "OPTIONS" => Ok(Response::new(200)
+                .header("DAV", "1, 3, calendar-access, calendar-no-timezone")
+                .header(
+                    "Allow",
+                    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT, MKCALENDAR",
+                )),
+            "PROPFIND" => self.propfind(request, &resource),
+            "REPORT" => self.report(request, &resource),
+            "MKCALENDAR" => self.mkcalendar(request, &resource),
+            "GET" | "HEAD" => self.get(request, &resource),
+            "PUT" => self.put(request, &resource),
+            "DELETE" => self.delete(request, &resource),
+            _ => Ok(Response::new(405)),
+        };
+
+        result.unwrap_or_else(|error| match error {
+            crate::Error::Xml(_) | crate::Error::Parser(_) => Response {
+                status: 400,
+                body: error.to_string(),
+                ..Default::default()
+            },
+            crate::Error::Filter(_) => {
+                Response::precondition(403, PropName::caldav("valid-filter"))
+            }
+            _ => Response::error(&error),
+        })
+    }
+
+    fn resolve(&self, path: &str) -> Option<Resource> {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments = |path: &str| {
+            path.split('/')
+                .filter(|x| !x.is_empty())
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let path = segments(path);
+
+        if path.is_empty() {
+            return Some(Resource::Root);
+        }
+
+        if path == segments(&self.principal) {
+            return Some(Resource::Principal);
+        }
+
+        let remaining = path.strip_prefix(segments(&self.home).as_slice())?;
+
+        let resource = match remaining {
+            [] => Resource::Home,
+            [calendar] => Resource::Calendar(calendar.clone()),
+            [calendar, object] => Resource::Object(calendar.clone(), object.clone()),
+            _ => return None,
+        };
+
+        Some(resource)
+    }
+
+    fn href(&self, resource: &Resource) -> String {
+        match resource {
+            Resource::Root => "/".to_string(),
+            Resource::Principal => self.principal.clone(),
+            Resource::Home => self.home.clone(),
+            Resource::Calendar(calendar) => format!("{}{calendar}/", self.home),
+            Resource::Object(calendar, object) => format!("{}{calendar}/{object}", self.home),
+        }
+    }
+
+    fn exists(&self, resource: &Resource) -> crate::Result<bool> {
+        let exists = match resource {
+            Resource::Calendar(calendar) => self.storage.calendar(calendar)?.is_some(),
+            Resource::Object(calendar, object) => self.storage.object(calendar, object)?.is_some(),
+            _ => true,
+        };
+
+        Ok(exists)
+    }
+
+    fn children(&self, resource: &Resource) -> crate::Result<Vec<Resource>> {
+        let children = match resource {
+            Resource::Home => self
+                .storage
+                .calendars()?
+                .into_iter()
+                .map(Resource::Calendar)
+                .collect(),
+            Resource::Calendar(calendar) => self
+                .storage
+                .objects(calendar)?
+                .into_iter()
+                .map(|x| Resource::Object(calendar.clone(), x))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(children)
+    }
+
+    /**
+     * Live properties of a resource. `calendar-data` is only returned when `data` isn’t
+     * [`Data::Omitted`], it isn’t part of `DAV:allprop`.
+     */
+    fn properties(&self, resource: &Resource, data: Data) -> crate::Result<Vec<Element>> {
+        let href = |resource: &Resource| self.href(resource);
+
+        let mut properties = vec![element(
+            PropName::dav("current-user-principal"),
+            "",
+            vec![element(
+                PropName::dav("href"),
+                &href(&Resource::Principal),
+                Vec::new(),
+            )],
+        )];
+
+        match resource {
+            Resource::Root | Resource::Home => properties.push(resourcetype(&["collection"])),
+            Resource::Principal => {
+                properties.push(resourcetype(&["principal"]));
+                properties.push(element(
+                    PropName::dav("principal-URL"),
+                    "",
+                    vec![element(PropName::dav("href"), &href(resource), Vec::new())],
+                ));
+                properties.push(element(
+                    PropName::caldav("calendar-home-set"),
+                    "",
+                    vec![element(
+                        PropName::dav("href"),
+                        &href(&Resource::Home),
+                        Vec::new(),
+                    )],
+                ));
+            }
+            Resource::Calendar(calendar) => {
+                let Some(calendar) = self.storage.calendar(calendar)? else {
+                    return Ok(Vec::new());
+                };
+
+                let mut resourcetype = resourcetype(&["collection"]);
+                resourcetype
+                    .children
+                    .push(element(PropName::caldav("calendar"), "", Vec::new()));
+                properties.push(resourcetype);
+
+                let components = calendar
+                    .components
+                    .iter()
+                    .map(|x| {
+                        let mut comp = element(PropName::caldav("comp"), "", Vec::new());
+                        comp.attributes.insert("name".to_string(), x.to_string());
+                        comp
+                    })
+                    .collect();
+                properties.push(element(
+                    PropName::caldav("supported-calendar-component-set"),
+                    "",
+                    components,
+                ));
+
+                let optional = [
+                    (PropName::dav("displayname"), &calendar.displayname),
+                    (
+                        PropName::caldav("calendar-description"),
+                        &calendar.description,
+                    ),
+                    (PropName::caldav("calendar-timezone"), &calendar.timezone),
+                    (PropName::apple("calendar-color"), &calendar.color),
+                    (PropName::calendarserver("getctag"), &calendar.ctag),
+                ];
+
+                for (name, value) in optional {
+                    if let Some(value) = value {
+                        properties.push(element(name, value, Vec::new()));
+                    }
+                }
+            }
+            Resource::Object(calendar, object) => {
+                let Some(object) = self.storage.object(calendar, object)? else {
+                    return Ok(Vec::new());
+                };
+
+                properties.push(resourcetype(&[]));
+                properties.push(element(PropName::dav("getetag"), &object.etag, Vec::new()));
+                properties.push(element(
+                    PropName::dav("getcontenttype"),
+                    "text/calendar; charset=utf-8",
+                    Vec::new(),
+                ));
+                properties.push(element(
+                    PropName::dav("getcontentlength"),
+                    &object.data.len().to_string(),
+                    Vec::new(),
+                ));
+
+                let data = match data {
+                    Data::Omitted => None,
+                    Data::Full => Some(object.data),
+                    Data::WithoutTimezones => Some(without_timezones(&object.data)),
+                };
+
+                if let Some(data) = data {
+                    properties.push(element(
+                        PropName::caldav("calendar-data"),
+                        &data,
+                        Vec::new(),
+                    ));
+                }
+            }
+        }
+
+        Ok(properties)
+    }
+
+    fn render(
+        &self,
+        multistatus: &mut Multistatus,
+        propfind: &Propfind,
+        resource: &Resource,
+        data: Data,
+    ) -> crate::Result {
+        let href = self.href(resource);
+
+        match propfind {
+            Propfind::PropName => {
+                let names = self
+                    .properties(resource, Data::Omitted)?
+                    .into_iter()
+                    .map(|x| element(x.name, "", Vec::new()))
+                    .collect();
+
+                multistatus.propstat(&href, names, Vec::new());
+            }
+            Propfind::AllProp(include) => {
+                let mut found = self.properties(resource, Data::Omitted)?;
+
+                for property in self.properties(resource, data)? {
+                    if include.contains(&property.name) && !found.contains(&property) {
+                        found.push(property);
+                    }
+                }
+
+                multistatus.propstat(&href, found, Vec::new());
+            }
+            Propfind::Prop(names) => {
+                let properties = self.properties(resource, data)?;
+                let mut found = Vec::new();
+                let mut missing = Vec::new();
+
+                for name in names {
+                    match properties.iter().find(|x| &x.name == name) {
+                        Some(property) => found.push(property.clone()),
+                        None => missing.push(name.clone()),
+                    }
+                }
+
+                multistatus.propstat(&href, found, missing);
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc4918#section-9.1>
+     */
+    fn propfind(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        if !self.exists(resource)? {
+            return Ok(Response::new(404));
+        }
+
+        let body = parse(&request.body)?;
+        let propfind = Propfind::from_element(body.as_ref());
+        let data = Data::from(request);
+        let mut multistatus = Multistatus::default();
+
+        self.render(&mut multistatus, &propfind, resource, data)?;
+
+        if request.header("Depth") != Some("0") {
+            for child in self.children(resource)? {
+                self.render(&mut multistatus, &propfind, &child, data)?;
+            }
+        }
+
+        Ok(data.applied(multistatus.into()))
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-7>
+     */
+    fn report(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        if !self.exists(resource)? {
+            return Ok(Response::new(404));
+        }
+
+        let Some(body) = parse(&request.body)? else {
+            return Ok(Response::new(400));
+        };
+
+        let propfind = Propfind::from_element(Some(&body));
+        let data = Data::from(request);
+        let mut multistatus = Multistatus::default();
+
+        if body.name == PropName::caldav("calendar-query") {
+            let filter = body
+                .child(&PropName::caldav("filter"))
+                .map(Filter::from_element)
+                .transpose()?;
+
+            let resolver = match self.query_resolver(&body, resource)? {
+                Some(resolver) => resolver,
+                None => {
+                    return Ok(Response::precondition(
+                        403,
+                        PropName::caldav("valid-calendar-data"),
+                    ));
+                }
+            };
+
+            let objects = match resource {
+                Resource::Object(..) => vec![resource.clone()],
+                _ => self.children(resource)?,
+            };
+
+            for object in objects {
+                let Resource::Object(calendar, name) = &object else {
+                    continue;
+                };
+                let Some(stored) = self.storage.object(calendar, name)? else {
+                    continue;
+                };
+
+                let matches = match &filter {
+                    Some(filter) => filter.matches_with(&stored.data, &resolver),
+                    None => Ok(true),
+                };
+
+                match matches {
+                    Ok(true) => self.render(&mut multistatus, &propfind, &object, data)?,
+                    Ok(false) => (),
+                    // An object that can’t be evaluated doesn’t fail the whole report.
+                    Err(error) => {
+                        log(&error);
+                        multistatus.status(&self.href(&object), 500);
+                    }
+                }
+            }
+        } else if body.name == PropName::caldav("calendar-multiget") {
+            for href in body.hrefs() {
+                match self.resolve(href) {
+                    Some(object @ Resource::Object(..)) if self.exists(&object)? => {
+                        self.render(&mut multistatus, &propfind, &object, data)?
+                    }
+                    _ => multistatus.status(href, 404),
+                }
+            }
+        } else {
+            return Ok(Response::precondition(
+                403,
+                PropName::dav("supported-report"),
+            ));
+        }
+
+        Ok(data.applied(multistatus.into()))
+    }
+
+    /**
+     * The resolver of a `calendar-query`, with the time zone of the query or of the calendar as
+     * floating time zone. `None` if the time zone of the query is invalid.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.8>
+     */
+    fn query_resolver(
+        &self,
+        query: &Element,
+        resource: &Resource,
+    ) -> crate::Result<Option<Resolver>> {
+        if let Some(timezone) = query.child(&PropName::caldav("timezone")) {
+            let resolver = vtimezone(&timezone.text)
+                .ok()
+                .map(|x| self.resolver.clone().floating(x));
+
+            return Ok(resolver);
+        }
+
+        let calendar = match resource {
+            Resource::Calendar(name) | Resource::Object(name, _) => self.storage.calendar(name)?,
+            _ => None,
+        };
+
+        let resolver = match calendar.and_then(|x| x.timezone) {
+            Some(timezone) => self.resolver.clone().floating(vtimezone(&timezone)?),
+            None => self.resolver.clone(),
+        };
+
+        Ok(Some(resolver))
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-5.3.1>
+     */
+    fn mkcalendar(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        let Resource::Calendar(name) = resource else {
+            return Ok(Response::new(405));
+        };
+
+        if self.exists(resource)? {
+            return Ok(Response::precondition(
+                403,
+                PropName::dav("resource-must-be-null"),
+            ));
+        }
+
+        let mkcalendar = match parse(&request.body)? {
+            Some(body) => Mkcalendar::from_element(&body)?,
+            None => Mkcalendar::default(),
+        };
+
+        self.storage.create_calendar(name, &mkcalendar)?;
+
+        Ok(Response::new(201))
+    }
+
+    fn get(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        let data = Data::from(request);
+
+        let response = match resource {
+            Resource::Object(calendar, name) => match self.storage.object(calendar, name)? {
+                Some(object) => {
+                    let body = match data {
+                        Data::WithoutTimezones => without_timezones(&object.data),
+                        _ => object.data,
+                    };
+
+                    data.applied(Response {
+                        status: 200,
+                        body,
+                        ..Default::default()
+                    })
+                    .header("Content-Type", "text/calendar; charset=utf-8")
+                    .header("ETag", &object.etag)
+                }
+                None => Response::new(404),
+            },
+            _ if self.exists(resource)? => Response::new(405),
+            _ => Response::new(404),
+        };
+
+        Ok(response)
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc4791#section-5.3.2>
+     */
+    fn put(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        let Resource::Object(calendar, name) = resource else {
+            return Ok(Response::new(405));
+        };
+
+        let Some(collection) = self.storage.calendar(calendar)? else {
+            return Ok(Response::new(409));
+        };
+
+        let existing = self.storage.object(calendar, name)?.map(|x| x.etag);
+
+        if !precondition(request, existing.as_deref()) {
+            return Ok(Response::new(412));
+        }
+
+        let Some(component) =
+            crate::component::parse(&request.body).filter(|x| x.name == "VCALENDAR")
+        else {
+            return Ok(Response::precondition(
+                403,
+                PropName::caldav("valid-calendar-data"),
+            ));
+        };
+
+        let supported = collection.components.is_empty()
+            || component
+                .components
+                .iter()
+                .filter(|x| x.name != "VTIMEZONE")
+                .all(|x| {
+                    collection
+                        .components
+                        .iter()
+                        .any(|c| c.to_string() == x.name)
+                });
+
+        if !supported {
+            return Ok(Response::precondition(
+                403,
+                PropName::caldav("supported-calendar-component"),
+            ));
+        }
+
+        let status = if existing.is_some() { 204 } else { 201 };
+        let etag = self.storage.put(calendar, name, &request.body)?;
+
+        Ok(Response::new(status).header("ETag", &etag))
+    }
+
+    fn delete(&self, request: &Request, resource: &Resource) -> crate::Result<Response> {
+        let response = match resource {
+            Resource::Object(calendar, name) => {
+                let Some(existing) = self.storage.object(calendar, name)? else {
+                    return Ok(Response::new(404));
+                };
+
+                if !precondition(request, Some(&existing.etag)) {
+                    return Ok(Response::new(412));
+                }
+
+                self.storage.delete(calendar, name)?;
+
+                Response::new(204)
+            }
+            Resource::Calendar(calendar) => {
+                if !self.exists(resource)? {
+                    return Ok(Response::new(404));
+                }
+
+                self.storage.delete_calendar(calendar)?;
+
+                Response::new(204)
+            }
+            _ => Response::new(405),
+        };
+
+        Ok(response)
+    }
+}
+
+/**
+ * Removes the `VTIMEZONE` components of an iCalendar object.
+ */
+fn without_timezones(data: &str) -> String {
+    let mut nested = false;
+
+    data.split_inclusive('\n')
+        .filter(|line| {
+            let line = line.trim_end();
+
+            if line.eq_ignore_ascii_case("BEGIN:VTIMEZONE") {
+                nested = true;
+            }
+
+            let keep = !nested;
+
+            if line.eq_ignore_ascii_case("END:VTIMEZONE") {
+                nested = false;
+            }
+
+            keep
+        })
+        .collect()
+}
+
+fn log(error: &crate::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(%error, "unable to handle request");
+    #[cfg(not(feature = "tracing"))]
+    let _ = error;
+}
+
+fn parse(body: &str) -> crate::Result<Option<Element>> {
+    if body.trim().is_empty() {
+        Ok(None)
+    } else {
+        Element::parse(body).map(Some)
+    }
+}
+
+fn element(name: PropName, text: &str, children: Vec<Element>) -> Element {
+    Element {
+        name,
+        attributes: Default::default(),
+        text: text.to_string(),
+        children,
+    }
+}
+
+fn resourcetype(types: &[&str]) -> Element {
+    let children = types
+        .iter()
+        .map(|x| element(PropName::dav(x), "", Vec::new()))
+        .collect();
+
+    element(PropName::dav("resourcetype"), "", children)
+}
+
+/**
+ * The time zone of a `VCALENDAR` object with a single `VTIMEZONE` component, like the
+ * `CALDAV:calendar-timezone` property.
+ */
+fn vtimezone(calendar: &str) -> crate::Result<Timezone> {
+    crate::component::parse(calendar)
+        .filter(|x| x.name == "VCALENDAR")
+        .and_then(|x| x.components.into_iter().find(|x| x.name == "VTIMEZONE"))
+        .ok_or_else(|| crate::Error::Timezone("expected a VTIMEZONE".to_string()))
+        .and_then(|x| Timezone::from_component(&x))
+}
+
+/**
+ * Checks the `If-Match` and `If-None-Match` headers against the current `etag`.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc9110#section-13.1>
+ */
+fn precondition(request: &Request, etag: Option<&str>) -> bool {
+    let matches = |header: &str| {
+        header == "*" && etag.is_some() || header.split(',').any(|x| Some(x.trim()) == etag)
+    };
+
+    if let Some(header) = request.header("If-Match")
+        && !matches(header)
+    {
+        return false;
+    }
+
+    if let Some(header) = request.header("If-None-Match")
+        && matches(header)
+    {
+        return false;
+    }
+
+    true
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4918#section-14.16>
+ */
+#[derive(Debug, Default)]
+struct Multistatus {
+    responses: Vec<String>,
+}
+
+impl Multistatus {
+    fn propstat(&mut self, href: &str, found: Vec<Element>, missing: Vec<PropName>) {
+        let mut response = format!(
+            "<d:response><d:href>{}</d:href>",
+            crate::elements::escape::text(href)
+        );
+
+        if !found.is_empty() {
+            response.push_str(&format!(
+                "<d:propstat><d:prop>{}</d:prop><d:status>{}</d:status></d:propstat>",
+                found.to_xml(),
+                status(200)
+            ));
+        }
+
+        if !missing.is_empty() {
+            response.push_str(&format!(
+                "<d:propstat><d:prop>{}</d:prop><d:status>{}</d:status></d:propstat>",
+                missing.to_xml(),
+                status(404)
+            ));
+        }
+
+        response.push_str("</d:response>");
+        self.responses.push(response);
+    }
+
+    fn status(&mut self, href: &str, code: u16) {
+        self.responses.push(format!(
+            "<d:response><d:href>{}</d:href><d:status>{}</d:status></d:response>",
+            crate::elements::escape::text(href),
+            status(code)
+        ));
+    }
+}
+
+impl From<Multistatus> for Response {
+    fn from(multistatus: Multistatus) -> Self {
+        Self::xml(
+            207,
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:">{}</d:multistatus>
+"#,
+                multistatus.responses.join("")
+            ),
+        )
+    }
+}
+
+fn status(code: u16) -> String {
+    let reason = attohttpc::StatusCode::from_u16(code)
+        .ok()
+        .and_then(|x| x.canonical_reason())
+        .unwrap_or_default();
+
+    format!("HTTP/1.1 {code} {reason}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    const EVENT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:kaldav\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20120101T120000Z\r
+DTSTART:20120101T120000Z\r
+SUMMARY:Weekly meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    type Calendars = BTreeMap<String, (super::Collection, BTreeMap<String, super::Object>)>;
+
+    #[derive(Debug, Default)]
+    struct Storage {
+        calendars: Mutex<Calendars>,
+    }
+
+    impl super::Storage for Storage {
+        fn calendars(&self) -> crate::Result<Vec<String>> {
+            Ok(self.calendars.lock().unwrap().keys().cloned().collect())
+        }
+
+        fn calendar(&self, name: &str) -> crate::Result<Option<super::Collection>> {
+            Ok(self
+                .calendars
+                .lock()
+                .unwrap()
+                .get(name)
+                .map(|(x, _)| x.clone()))
+        }
+
+        fn create_calendar(
+            &self,
+            name: &str,
+            mkcalendar: &crate::elements::Mkcalendar,
+        ) -> crate::Result {
+            let collection = super::Collection {
+                displayname: mkcalendar.name.clone(),
+                description: mkcalendar.description.clone(),
+                timezone: mkcalendar.timezone.as_ref().map(ikal::ser::ical),
+                components: mkcalendar.supported_components.clone(),
+                ..Default::default()
+            };
+
+            self.calendars
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), (collection, BTreeMap::new()));
+
+            Ok(())
+        }
+
+        fn delete_calendar(&self, name: &str) -> crate::Result {
+            self.calendars.lock().unwrap().remove(name);
+
+            Ok(())
+        }
+
+        fn objects(&self, calendar: &str) -> crate::Result<Vec<String>> {
+            Ok(self.calendars.lock().unwrap()[calendar]
+                .1
+                .keys()
+                .cloned()
+                .collect())
+        }
+
+        fn object(&self, calendar: &str, name: &str) -> crate::Result<Option<super::Object>> {
+            Ok(self
+                .calendars
+                .lock()
+                .unwrap()
+                .get(calendar)
+                .and_then(|(_, x)| x.get(name))
+                .cloned())
+        }
+
+        fn put(&self, calendar: &str, name: &str, data: &str) -> crate::Result<String> {
+            let mut calendars = self.calendars.lock().unwrap();
+            let (collection, objects) = calendars.get_mut(calendar).unwrap();
+
+            let ctag = collection
+                .ctag
+                .as_ref()
+                .and_then(|x| x.parse::<u32>().ok())
+                .unwrap_or_default()
+                + 1;
+            collection.ctag = Some(ctag.to_string());
+
+            let etag = format!("\"{ctag}\"");
+            objects.insert(
+                name.to_string(),
+                super::Object {
+                    data: data.to_string(),
+                    etag: etag.clone(),
+                },
+            );
+
+            Ok(etag)
+        }
+
+        fn delete(&self, calendar: &str, name: &str) -> crate::Result {
+            self.calendars
+                .lock()
+                .unwrap()
+                .get_mut(calendar)
+                .map(|(_, x)| x.remove(name));
+
+            Ok(())
+        }
+    }
+
+    fn server() -> super::Server<Storage> {
+        let server = super::Server::new(Storage::default())
+            .principal("/principals/johndoe/")
+            .home("/calendars/johndoe/");
+
+        let mkcalendar = crate::elements::Mkcalendar {
+            name: Some("Home calendar".to_string()),
+            supported_components: vec![ikal::Components::Event],
+            ..Default::default()
+        };
+        crate::server::Storage::create_calendar(server.storage(), "home", &mkcalendar).unwrap();
+
+        server
+    }
+
+    fn request(
+        server: &super::Server<Storage>,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> super::Response {
+        server.handle(&super::Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.to_string(),
+        })
+    }
+
+    /**
+     * Sends the client requests to the server, without network.
+     */
+    #[derive(Debug)]
+    struct Transport(super::Server<Storage>);
+
+    impl crate::transport::Transport for Transport {
+        fn send(
+            &self,
+            request: &crate::transport::Request,
+            _: &crate::transport::Http,
+        ) -> crate::Result<crate::transport::Response> {
+            let url = url::Url::parse(&request.href).unwrap();
+
+            let response = self.0.handle(&super::Request {
+                method: request.method.clone(),
+                path: url.path().to_string(),
+                headers: request.headers.clone(),
+                body: request.body.clone().unwrap_or_default(),
+            });
+
+            let mut headers = attohttpc::header::HeaderMap::new();
+            for (name, value) in response.headers {
+                headers.append(
+                    attohttpc::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                    value.parse().unwrap(),
+                );
+            }
+
+            Ok(crate::transport::Response {
+                status: attohttpc::StatusCode::from_u16(response.status).unwrap(),
+                headers,
+                body: response.body,
+            })
+        }
+    }
+
+    #[test]
+    fn propfind() {
+        let server = server();
+
+        let response = request(
+            &server,
+            "PROPFIND",
+            "/calendars/johndoe/",
+            &[("Depth", "1")],
+            r#"<d:propfind xmlns:d="DAV:" xmlns:x="urn:example"><d:prop><d:displayname /><x:unknown /></d:prop></d:propfind>"#,
+        );
+
+        assert_eq!(response.status, 207);
+        assert!(response.body.contains(
+            "<d:response><d:href>/calendars/johndoe/home/</d:href><d:propstat><d:prop><d:displayname>Home calendar</d:displayname></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>"
+        ));
+        assert!(response.body.contains(r#"<unknown xmlns="urn:example" />"#));
+
+        let response = request(&server, "PROPFIND", "/calendars/jane/", &[], "");
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn mkcalendar() {
+        let server = server();
+
+        let body = r#"<c:mkcalendar xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:set><d:prop>
+        <d:displayname>Tasks</d:displayname>
+        <c:supported-calendar-component-set><c:comp name="VTODO" /></c:supported-calendar-component-set>
+    </d:prop></d:set>
+</c:mkcalendar>"#;
+
+        let response = request(
+            &server,
+            "MKCALENDAR",
+            "/calendars/johndoe/tasks/",
+            &[],
+            body,
+        );
+        assert_eq!(response.status, 201);
+
+        let calendar = crate::server::Storage::calendar(server.storage(), "tasks")
+            .unwrap()
+            .unwrap();
+        assert_eq!(calendar.displayname.as_deref(), Some("Tasks"));
+        assert_eq!(calendar.components, [ikal::Components::Todo]);
+
+        let response = request(
+            &server,
+            "MKCALENDAR",
+            "/calendars/johndoe/tasks/",
+            &[],
+            body,
+        );
+        assert_eq!(response.status, 403);
+
+        let response = request(&server, "PUT", "/calendars/johndoe/tasks/1.ics", &[], EVENT);
+        assert_eq!(response.status, 403);
+    }
+
+    #[test]
+    fn put() {
+        let server = server();
+        let path = "/calendars/johndoe/home/1.ics";
+
+        let response = request(&server, "PUT", path, &[("If-None-Match", "*")], EVENT);
+        assert_eq!(response.status, 201);
+
+        let response = request(&server, "PUT", path, &[("If-None-Match", "*")], EVENT);
+        assert_eq!(response.status, 412);
+
+        let response = request(&server, "GET", path, &[], "");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, EVENT);
+
+        let response = request(&server, "DELETE", path, &[("If-Match", "\"0\"")], "");
+        assert_eq!(response.status, 412);
+
+        let response = request(&server, "DELETE", path, &[], "");
+        assert_eq!(response.status, 204);
+    }
+
+    #[test]
+    fn calendar_query() {
+        let server = server();
+        request(&server, "PUT", "/calendars/johndoe/home/1.ics", &[], EVENT);
+
+        let query = |start: &str| {
+            format!(
+                r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /><c:calendar-data /></d:prop>
+    <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"><c:time-range start="{start}" /></c:comp-filter></c:comp-filter></c:filter>
+</c:calendar-query>"#
+            )
+        };
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query("20110101T000000Z"),
+        );
+        assert_eq!(response.status, 207);
+        assert!(
+            response
+                .body
+                .contains("<d:href>/calendars/johndoe/home/1.ics</d:href>")
+        );
+        assert!(response.body.contains("SUMMARY:Weekly meeting"));
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query("20130101T000000Z"),
+        );
+        assert!(!response.body.contains("1.ics"));
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <c:filter><c:comp-filter name="VCALENDAR"><c:is-not-defined /><c:comp-filter name="VEVENT" /></c:comp-filter></c:filter>
+</c:calendar-query>"#,
+        );
+        assert_eq!(response.status, 403);
+        assert!(response.body.contains("valid-filter"));
+    }
+
+    #[test]
+    fn evaluation_error() {
+        let server = server();
+        request(&server, "PUT", "/calendars/johndoe/home/1.ics", &[], EVENT);
+        crate::server::Storage::put(
+            server.storage(),
+            "home",
+            "2.ics",
+            &EVENT.replace("SUMMARY", "RRULE:FREQ=SOMETIMES\r\nSUMMARY"),
+        )
+        .unwrap();
+
+        let query = r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /></d:prop>
+    <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"><c:time-range start="20110101T000000Z" /></c:comp-filter></c:comp-filter></c:filter>
+</c:calendar-query>"#;
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            query,
+        );
+        assert_eq!(response.status, 207);
+        assert!(
+            response
+                .body
+                .contains("<d:response><d:href>/calendars/johndoe/home/1.ics</d:href><d:propstat>")
+        );
+        assert!(response.body.contains("<d:response><d:href>/calendars/johndoe/home/2.ics</d:href><d:status>HTTP/1.1 500 Internal Server Error</d:status></d:response>"));
+
+        server
+            .storage()
+            .calendars
+            .lock()
+            .unwrap()
+            .get_mut("home")
+            .unwrap()
+            .0
+            .timezone =
+            Some("BEGIN:VCALENDAR\r\nTZID:Europe/Paris\r\nEND:VCALENDAR\r\n".to_string());
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            query,
+        );
+        assert_eq!(response.status, 500);
+        assert_eq!(response.body, "Internal Server Error");
+    }
+
+    #[test]
+    fn no_timezone() {
+        let server = server();
+        let path = "/calendars/johndoe/home/1.ics";
+        let event = EVENT.replace(
+            "BEGIN:VEVENT",
+            "BEGIN:VTIMEZONE\r\nTZID:UTC\r\nBEGIN:STANDARD\r\nTZOFFSETFROM:+0000\r\nTZOFFSETTO:+0000\r\nDTSTART:19700101T000000\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT",
+        );
+        request(&server, "PUT", path, &[], &event);
+
+        let response = request(&server, "OPTIONS", path, &[], "");
+        assert!(response.headers.contains(&(
+            "DAV".to_string(),
+            "1, 3, calendar-access, calendar-no-timezone".to_string()
+        )));
+
+        let response = request(&server, "GET", path, &[], "");
+        assert!(response.body.contains("BEGIN:VTIMEZONE"));
+
+        let prefer = [("Prefer", "return=minimal, calendar-no-timezone")];
+        let applied = (
+            "Preference-Applied".to_string(),
+            "calendar-no-timezone".to_string(),
+        );
+
+        let response = request(&server, "GET", path, &prefer, "");
+        assert_eq!(response.body, EVENT);
+        assert!(response.headers.contains(&applied));
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &prefer,
+            &format!(
+                r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><c:calendar-data /></d:prop>
+    <d:href>{path}</d:href>
+</c:calendar-multiget>"#
+            ),
+        );
+        assert!(response.body.contains("BEGIN:VEVENT"));
+        assert!(!response.body.contains("BEGIN:VTIMEZONE"));
+        assert!(response.headers.contains(&applied));
+    }
+
+    #[test]
+    fn floating_time_zone() {
+        let server = server().resolver(crate::timezone::Resolver::new().floating(chrono::Utc));
+        let event = EVENT.replace(
+            "DTSTART:20120101T120000Z",
+            "DTSTART:20120101T120000\r\nDURATION:PT1H",
+        );
+        request(&server, "PUT", "/calendars/johndoe/home/1.ics", &[], &event);
+
+        let query = |timezone: &str| {
+            format!(
+                r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /></d:prop>
+    <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"><c:time-range start="20120101T070000Z" end="20120101T073000Z" /></c:comp-filter></c:comp-filter></c:filter>
+    {timezone}
+</c:calendar-query>"#
+            )
+        };
+        let timezone = "<c:timezone><![CDATA[BEGIN:VCALENDAR\r
+BEGIN:VTIMEZONE\r
+TZID:Asia/Karachi\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0500\r
+TZOFFSETTO:+0500\r
+DTSTART:19700101T000000\r
+END:STANDARD\r
+END:VTIMEZONE\r
+END:VCALENDAR\r
+]]></c:timezone>";
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query(""),
+        );
+        assert!(!response.body.contains("1.ics"));
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query(timezone),
+        );
+        assert!(response.body.contains("1.ics"));
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query("<c:timezone>Asia/Karachi</c:timezone>"),
+        );
+        assert_eq!(response.status, 403);
+        assert!(response.body.contains("valid-calendar-data"));
+    }
+
+    #[test]
+    fn multiget() {
+        let server = server();
+        request(&server, "PUT", "/calendars/johndoe/home/1.ics", &[], EVENT);
+
+        let response = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[],
+            r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /></d:prop>
+    <d:href>/calendars/johndoe/home/1.ics</d:href>
+    <d:href>/calendars/johndoe/home/2.ics</d:href>
+</c:calendar-multiget>"#,
+        );
+
+        assert!(
+            response
+                .body
+                .contains("<d:getetag>&quot;1&quot;</d:getetag>")
+        );
+        assert!(response.body.contains("<d:response><d:href>/calendars/johndoe/home/2.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>"));
+    }
+
+    #[test]
+    fn client() -> crate::Result {
+        let client = crate::Client::builder("http://calendar.invalid/")
+            .transport(Transport(server()))
+            .build()?;
+
+        let calendars = client.calendars()?;
+        let calendar = &calendars["Home calendar"];
+
+        calendar.create(&crate::ical::vcalendar! {
+            prodid: "-//kaldav//EN",
+            version: "2.0",
+            events: [{
+                uid: "1",
+                dtstamp: "20120101T120000Z",
+                dtstart: "20120101T120000Z",
+                summary: "Weekly meeting",
+            }],
+        }?)?;
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VEVENT") {
+                    prop_filter: PropFilter::new("SUMMARY") {
+                        text_match: TextMatch::new("weekly"),
+                    }
+                }
+            }
+        };
+        assert_eq!(calendar.search(&filter)?.len(), 1);
+
+        let filter = crate::filter! {
+            CompFilter::new("VCALENDAR") {
+                CompFilter::new("VTODO") {}
+            }
+        };
+        assert!(calendar.search(&filter)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[cfg(feature = "native-tls")]
+pub(crate) type Certificate = native_tls::Certificate;
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+pub(crate) type Certificate = rustls_pki_types::CertificateDer<'static>;
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+pub(crate) type Certificate = ();
+
+#[cfg(any(feature = "native-tls", feature = "rustls"))]
+pub(crate) type Identity = crate::tls::Identity;
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+pub(crate) type Identity = ();
+
+/**
+ * How HTTP redirections are handled.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Redirect {
+    /** Redirections are returned as errors. */
+    None,
+    /** Follows up to this number of redirections. */
+    Limited(u32),
+}
+
+impl Default for Redirect {
+    fn default() -> Self {
+        Self::Limited(5)
+    }
+}
+
+/**
+ * Settings applied to every request, see [`crate::ClientBuilder`].
+ */
+#[derive(Clone)]
+pub struct Config {
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub proxy: Option<attohttpc::ProxySettings>,
+    pub user_agent: String,
+    pub headers: attohttpc::header::HeaderMap,
+    pub redirect: Redirect,
+    pub accept_invalid_certs: bool,
+    pub accept_invalid_hostnames: bool,
+    pub root_certificates: Vec<Certificate>,
+    /** Client certificate and its private key. */
+    pub identity: Option<Identity>,
+    pub retry: crate::Retry,
+    pub observers: Vec<Arc<dyn crate::trace::Observer>>,
+    pub trace_bodies: bool,
+    pub redact_bodies: Option<Arc<crate::trace::Redact>>,
+    /** Headers redacted from the traces, in addition to the standard credential ones. */
+    pub sensitive_headers: Vec<String>,
+    pub transport: Option<Arc<dyn crate::transport::Transport>>,
+    pub cache: Option<crate::cache::Cache>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
+            proxy: None,
+            user_agent: concat!("kaldav/", env!("CARGO_PKG_VERSION")).to_string(),
+            headers: attohttpc::header::HeaderMap::new(),
+            redirect: Redirect::default(),
+            accept_invalid_certs: false,
+            accept_invalid_hostnames: false,
+            root_certificates: Vec::new(),
+            identity: None,
+            retry: crate::Retry::none(),
+            observers: Vec::new(),
+            trace_bodies: false,
+            redact_bodies: None,
+            sensitive_headers: Vec::new(),
+            transport: None,
+            cache: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("read_timeout", &self.read_timeout)
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("user_agent", &self.user_agent)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("redirect", &self.redirect)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("accept_invalid_hostnames", &self.accept_invalid_hostnames)
+            .field("root_certificates", &self.root_certificates.len())
+            .field("identity", &self.identity.is_some())
+            .field("retry", &self.retry)
+            .field("observers", &self.observers.len())
+            .field("trace_bodies", &self.trace_bodies)
+            .field("redact_bodies", &self.redact_bodies.is_some())
+            .field("sensitive_headers", &self.sensitive_headers)
+            .field("transport", &self.transport)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+/**
+ * State shared by a client and every object derived from it.
+ *
+ * Cloning a session is cheap and gives a handle to the same state: changing the credentials of
+ * one handle changes them for the whole tree.
+ */
+#[derive(Clone, Default)]
+pub struct Session {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    auth: RwLock<Option<crate::Auth>>,
+    config: Config,
+}
+
+impl Session {
+    pub fn new(auth: Option<crate::Auth>) -> Self {
+        let session = Self::default();
+        session.set_auth(auth);
+
+        session
+    }
+
+    pub(crate) fn with_config(auth: Option<crate::Auth>, config: Config) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                auth: RwLock::new(auth),
+                config,
+            }),
+        }
+    }
+
+    pub fn has_auth(&self) -> bool {
+        self.inner.auth.read().unwrap().is_some()
+    }
+
+    /**
+     * A handle to the current credentials, released from the lock so that the provider can call
+     * [`Self::set_auth`].
+     */
+    fn auth(&self) -> Option<crate::Auth> {
+        self.inner.auth.read().unwrap().clone()
+    }
+
+    /**
+     * Replaces the credentials of every object sharing this session.
+     */
+    pub fn set_auth(&self, auth: Option<crate::Auth>) {
+        *self.inner.auth.write().unwrap() = auth;
+    }
+
+    /**
+     * Returns `true` if `other` is a handle to the same session.
+     */
+    pub fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub(crate) fn cache(&self) -> Option<&crate::cache::Cache> {
+        self.inner.config.cache.as_ref()
+    }
+
+    /**
+     * Creates a request configured with the network settings of the session.
+     */
+    pub(crate) fn builder(
+        &self,
+        method: &str,
+        href: &str,
+    ) -> crate::Result<attohttpc::RequestBuilder> {
+        let config = &self.inner.config;
+
+        let method = attohttpc::Method::from_bytes(method.as_bytes())
+            .map_err(|_| crate::Error::Misc(format!("Invalid method: {method}")))?;
+
+        let mut request = attohttpc::RequestBuilder::try_new(method, href)?
+            .danger_accept_invalid_certs(config.accept_invalid_certs)
+            .danger_accept_invalid_hostnames(config.accept_invalid_hostnames);
+
+        request = match config.redirect {
+            Redirect::None => request.follow_redirects(false),
+            Redirect::Limited(max) => request.follow_redirects(true).max_redirections(max),
+        };
+
+        if let Some(timeout) = config.connect_timeout {
+            request = request.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.read_timeout {
+            request = request.read_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.timeout {
+            request = request.timeout(timeout);
+        }
+
+        if let Some(proxy) = &config.proxy {
+            request = request.proxy_settings(proxy.clone());
+        }
+
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        for certificate in &config.root_certificates {
+            request = request.add_root_certificate(certificate.clone());
+        }
+
+        Ok(request)
+    }
+
+    /**
+     * Sends a request, authenticating and retrying it according to the session settings.
+     */
+    pub(crate) fn send(
+        &self,
+        method: &str,
+        href: &str,
+        body: Option<&str>,
+        headers: &crate::Headers,
+    ) -> crate::Result<crate::transport::Response> {
+        let retry = &self.inner.config.retry;
+        let mut attempt = 0;
+
+        loop {
+            let result = self.send_once(method, href, body, headers);
+
+            let retry_after = result.as_ref().ok().and_then(crate::retry::retry_after);
+
+            if attempt < retry.max_retries
+                && retry.allows(method, headers)
+                && let Some(reason) = retry.reason(&result)
+                && let Some(delay) = retry.delay(attempt + 1, retry_after)
+            {
+                attempt += 1;
+
+                retry.notify(&crate::retry::Attempt {
+                    method,
+                    href,
+                    attempt,
+                    reason,
+                    delay,
+                });
+
+                std::thread::sleep(delay);
+                continue;
+            }
+
+            return result;
+        }
+    }
+
+    fn send_once(
+        &self,
+        method: &str,
+        href: &str,
+        body: Option<&str>,
+        headers: &crate::Headers,
+    ) -> crate::Result<crate::transport::Response> {
+        use crate::auth::Provider as _;
+
+        let send = || -> crate::Result<crate::transport::Response> {
+            let config = &self.inner.config;
+
+            let mut request = crate::transport::Request {
+                method: method.to_string(),
+                href: href.to_string(),
+                headers: vec![("User-Agent".to_string(), config.user_agent.clone())],
+                body: body.map(str::to_string),
+                sensitive_headers: config.sensitive_headers.clone(),
+            };
+
+            for (name, value) in &config.headers {
+                request.headers.push((
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                ));
+            }
+
+            for (name, value) in headers {
+                request.headers.push((name.to_string(), value.to_string()));
+            }
+
+            if let Some(header) = self.header(method, href)? {
+                request.sensitive_headers.push(header.0.clone());
+                request.headers.push(header);
+            }
+
+            self.execute(&request)
+        };
+
+        let mut response = send()?;
+
+        if response.status == attohttpc::StatusCode::UNAUTHORIZED
+            && self.refresh(&challenge(&response))?
+        {
+            response = send()?;
+        }
+
+        Ok(response)
+    }
+
+    /**
+     * Sends `request` through the transport and reports the exchange to the observers.
+     */
+    fn execute(
+        &self,
+        request: &crate::transport::Request,
+    ) -> crate::Result<crate::transport::Response> {
+        let config = &self.inner.config;
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "request",
+            method = request.method,
+            href = request.href,
+            status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+        .entered();
+
+        let start = std::time::Instant::now();
+        let result = match &config.transport {
+            Some(transport) => transport.send(request, &|x| self.http(x)),
+            None => self.http(request),
+        };
+        let duration = start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("duration_ms", duration.as_millis() as u64);
+
+            if let Ok(response) = &result {
+                span.record("status", response.status.as_u16());
+            }
+        }
+
+        if cfg!(feature = "tracing") || !config.observers.is_empty() {
+            let bodies = config.trace_bodies;
+            let body = |body: &str| match &config.redact_bodies {
+                Some(redact) => redact(body).into_owned(),
+                None => body.to_string(),
+            };
+            let exchange = crate::trace::Exchange {
+                method: request.method.clone(),
+                href: request.href.clone(),
+                request_headers: request
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        crate::trace::redact(name, value, &request.sensitive_headers)
+                    })
+                    .collect(),
+                request_body: request.body.as_deref().filter(|_| bodies).map(body),
+                status: result.as_ref().ok().map(|x| x.status.as_u16()),
+                response_headers: result
+                    .as_ref()
+                    .map(|x| crate::trace::headers(&x.headers, &request.sensitive_headers))
+                    .unwrap_or_default(),
+                response_body: result
+                    .as_ref()
+                    .ok()
+                    .filter(|_| bodies)
+                    .map(|x| body(&x.body)),
+                error: result.as_ref().err().map(ToString::to_string),
+                duration,
+            };
+
+            #[cfg(feature = "tracing")]
+            crate::trace::emit(&exchange);
+
+            for observer in &config.observers {
+                observer.observe(&exchange);
+            }
+        }
+
+        result
+    }
+
+    /**
+     * Sends `request` over the network.
+     */
+    fn http(
+        &self,
+        request: &crate::transport::Request,
+    ) -> crate::Result<crate::transport::Response> {
+        #[cfg(any(feature = "native-tls", feature = "rustls"))]
+        if let Some(identity) = &self.inner.config.identity
+            && request.href.starts_with("https:")
+        {
+            return crate::tls::send(&self.inner.config, identity, request);
+        }
+
+        let mut builder = self
+            .builder(&request.method, &request.href)?
+            .text(request.body.as_deref().unwrap_or_default());
+
+        for (name, value) in &request.headers {
+            let name = attohttpc::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| crate::Error::Misc(format!("Invalid header name: {name}")))?;
+
+            builder = builder.try_header_append(name, value.as_str())?;
+        }
+
+        crate::transport::Response::read(builder.send()?)
+    }
+}
+
+/**
+ * Content of the `WWW-Authenticate` headers of `response`.
+ */
+fn challenge(response: &crate::transport::Response) -> String {
+    response
+        .headers
+        .get_all(attohttpc::header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("auth", &self.inner.auth.read().unwrap())
+            .field("config", &self.inner.config)
+            .finish()
+    }
+}
+
+impl crate::auth::Provider for Session {
+    fn header(&self, method: &str, href: &str) -> crate::Result<Option<(String, String)>> {
+        match self.auth() {
+            Some(auth) => auth.header(method, href),
+            None => Ok(None),
+        }
+    }
+
+    fn refresh(&self, challenge: &str) -> crate::Result<bool> {
+        match self.auth() {
+            Some(auth) => auth.refresh(challenge),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Requestable as _;
+
+    #[test]
+    fn shared() -> crate::Result {
+        let server = crate::test::server();
+
+        let client = crate::Client::new(server.url(""));
+        let calendars = client.calendars()?;
+        let calendar = calendars.get("Home calendar").unwrap();
+
+        assert!(calendar.session().same(client.session()));
+
+        client
+            .session()
+            .set_auth(Some(crate::Auth::basic("johndoe", Some("s3cr3t"))));
+        assert!(calendar.session().has_auth());
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_set_auth() -> crate::Result {
+        use crate::auth::Provider as _;
+
+        let session = crate::Session::new(None);
+        let handle = session.clone();
+        let bearer = crate::auth::Bearer::new("expired").with_refresh(move || {
+            handle.set_auth(Some(crate::Auth::basic("johndoe", None)));
+
+            Ok("s3cr3t".into())
+        });
+        session.set_auth(Some(bearer.into()));
+
+        assert!(session.refresh("")?);
+        assert!(format!("{session:?}").contains("johndoe"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn debug() {
+        let session = crate::Session::new(Some(crate::Auth::basic("johndoe", Some("s3cr3t"))));
+
+        let debug = format!("{session:?}");
+        assert!(debug.contains("johndoe"));
+        assert!(!debug.contains("s3cr3t"));
+    }
+}
@@ -0,0 +1,1071 @@
+/*!
+ * Two-way synchronization of a calendar with a local [`Store`].
+ *
+ * Remote changes are detected with a `sync-collection` report (RFC 6578), falling back to
+ * comparing the etags of every object when the server doesn’t support it or forgot the sync
+ * token. Local changes are detected by comparing the revisions of the store with the ones saved
+ * in the [`State`] at the end of the previous run.
+ *
+ * Changes are pushed with conditional requests. An object modified on both sides is a
+ * [`Conflict`], resolved by a [`Strategy`]; by default it’s left untouched.
+ *
+ * ```no_run
+ * let client = kaldav::Client::new("https://caldav.example.org/");
+ * let calendars = client.calendars()?;
+ * let mut store = kaldav::sync::Memory::default();
+ *
+ * let report = calendars["Home calendar"].sync().run(&mut store)?;
+ *
+ * for change in &report.changes {
+ *     println!("{change}");
+ * }
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+
+pub mod strategy;
+pub mod vdir;
+
+pub use strategy::Strategy;
+
+use crate::Requestable as _;
+use crate::Xmlable as _;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/**
+ * Where the synchronized objects are kept locally.
+ *
+ * Objects are identified by their name, the last segment of their href, like `1.ics`.
+ */
+pub trait Store {
+    /**
+     * Names of the local objects, with a revision changing whenever the object does, like a
+     * modification time or a hash of the data.
+     */
+    fn list(&self) -> crate::Result<BTreeMap<String, String>>;
+
+    fn get(&self, name: &str) -> crate::Result<String>;
+
+    /**
+     * Creates or replaces an object, returns its new revision.
+     */
+    fn put(&mut self, name: &str, data: &str) -> crate::Result<String>;
+
+    /**
+     * Deletes an object, deleting a missing one isn’t an error.
+     */
+    fn delete(&mut self, name: &str) -> crate::Result;
+
+    /**
+     * State saved by the previous run, the default one before the first run.
+     */
+    fn state(&self) -> crate::Result<State>;
+
+    fn set_state(&mut self, state: &State) -> crate::Result;
+}
+
+/**
+ * What was synchronized by the previous run.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub sync_token: Option<String>,
+    /** Synchronized objects by name. */
+    pub items: BTreeMap<String, Item>,
+}
+
+impl State {
+    pub fn to_json(&self) -> String {
+        let items = self
+            .items
+            .iter()
+            .map(|(name, item)| {
+                let item = serde_json::json!({
+                    "etag": item.etag,
+                    "revision": item.revision,
+                    "base": item.base,
+                });
+
+                (name.clone(), item)
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        serde_json::json!({
+            "sync-token": self.sync_token,
+            "items": items,
+        })
+        .to_string()
+    }
+}
+
+impl std::str::FromStr for State {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let invalid = || crate::Error::Misc("Invalid synchronization state".to_string());
+
+        let json = serde_json::from_str::<serde_json::Value>(s).map_err(|_| invalid())?;
+        let text = |value: &serde_json::Value, name: &str| {
+            value
+                .get(name)
+                .and_then(|x| x.as_str())
+                .map(ToString::to_string)
+        };
+
+        let items = json
+            .get("items")
+            .and_then(|x| x.as_object())
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|(name, item)| {
+                let item = Item {
+                    etag: text(item, "etag"),
+                    revision: text(item, "revision").ok_or_else(invalid)?,
+                    base: text(item, "base"),
+                };
+
+                Ok((name.clone(), item))
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok(Self {
+            sync_token: text(&json, "sync-token"),
+            items,
+        })
+    }
+}
+
+/**
+ * A synchronized object.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Item {
+    /** Remote etag, `None` if the server didn’t return it. */
+    pub etag: Option<String>,
+    /** Local revision, see [`Store::list`]. */
+    pub revision: String,
+    /** Synchronized data, the common ancestor of a three-way merge. */
+    pub base: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Create,
+    Update,
+    Delete,
+}
+
+/**
+ * A change applied on `side`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub name: String,
+    pub side: Side,
+    pub kind: Kind,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} {} on the {:?} side",
+            self.kind, self.name, self.side
+        )
+    }
+}
+
+/**
+ * An object changed on both sides since the previous run.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub name: String,
+    pub local: Kind,
+    pub remote: Kind,
+    pub decision: Decision,
+}
+
+/**
+ * How a conflict was resolved, see [`strategy::Resolution`].
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /** Left untouched, because of the strategy or a concurrent modification. */
+    Skipped,
+    Local,
+    Remote,
+    Merged,
+    /** The local version was copied to this object. */
+    KeptBoth(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub changes: Vec<Change>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.conflicts.is_empty()
+    }
+
+    fn change(&mut self, name: &str, side: Side, kind: Kind) {
+        self.changes.push(Change {
+            name: name.to_string(),
+            side,
+            kind,
+        });
+    }
+}
+
+/**
+ * Synchronizes a calendar, see [`crate::Calendar::sync`].
+ */
+pub struct Synchronizer<'a> {
+    calendar: &'a crate::Calendar,
+    strategy: Arc<dyn Strategy>,
+}
+
+impl<'a> Synchronizer<'a> {
+    pub(crate) fn new(calendar: &'a crate::Calendar) -> Self {
+        Self {
+            calendar,
+            strategy: Arc::new(strategy::Skip),
+        }
+    }
+
+    /**
+     * Resolves the conflicts with `strategy`, see [`strategy`].
+     */
+    pub fn strategy<S>(mut self, strategy: S) -> Self
+    where
+        S: Strategy + 'static,
+    {
+        self.strategy = Arc::new(strategy);
+
+        self
+    }
+
+    /**
+     * Applies the remote changes to `store` and the local ones to the calendar, then saves the
+     * new state in `store`.
+     */
+    pub fn run<S>(&self, store: &mut S) -> crate::Result<Report>
+    where
+        S: Store + ?Sized,
+    {
+        let mut state = store.state()?;
+        let remote = self.remote(&state)?;
+        let local = store.list()?;
+        let mut report = Report::default();
+
+        let result = self.reconcile(store, &mut state, &local, &remote, &mut report);
+
+        // the objects already synchronized are saved even if another one failed
+        if result.is_ok() {
+            state.sync_token = remote.sync_token;
+        }
+        store.set_state(&state)?;
+
+        result.map(|()| report)
+    }
+
+    fn reconcile<S>(
+        &self,
+        store: &mut S,
+        state: &mut State,
+        local: &BTreeMap<String, String>,
+        remote: &Remote,
+        report: &mut Report,
+    ) -> crate::Result
+    where
+        S: Store + ?Sized,
+    {
+        let names = local
+            .keys()
+            .chain(state.items.keys())
+            .chain(remote.etags.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        for name in &names {
+            let item = state.items.get(name).cloned();
+            let href = remote
+                .etags
+                .get(name)
+                .map(|(href, _)| href.clone())
+                .unwrap_or_else(|| self.href(name));
+
+            let local_change = match (local.get(name), &item) {
+                (Some(_), None) => Some(Kind::Create),
+                (Some(revision), Some(item)) if *revision != item.revision => Some(Kind::Update),
+                (None, Some(_)) => Some(Kind::Delete),
+                _ => None,
+            };
+
+            match (local_change, remote.change(name, item.as_ref())) {
+                (None, None) => (),
+                (None, Some(Kind::Delete)) => {
+                    store.delete(name)?;
+                    state.items.remove(name);
+                    report.change(name, Side::Local, Kind::Delete);
+                }
+                (None, Some(kind)) => {
+                    // deleted since it was listed, the next run reports it
+                    let Some((data, etag)) = self.download(&href)? else {
+                        continue;
+                    };
+                    let etag = etag.or_else(|| remote.etags[name].1.clone());
+
+                    save(store, state, name, Some((data, etag)))?;
+                    report.change(name, Side::Local, kind);
+                }
+                (Some(Kind::Delete), None) => {
+                    if self.remove(&href, Expected::from(item.as_ref()))? {
+                        state.items.remove(name);
+                        report.change(name, Side::Remote, Kind::Delete);
+                    } else {
+                        let pending = Pending::new(name, &href, local, Kind::Delete, None);
+                        report.conflicts.push(self.resolve(store, state, pending)?);
+                    }
+                }
+                (Some(kind), None) => {
+                    let data = store.get(name)?;
+
+                    if let Some(etag) = self.upload(&href, &data, Expected::from(item.as_ref()))? {
+                        let item = Item {
+                            etag,
+                            revision: local[name].clone(),
+                            base: Some(data),
+                        };
+
+                        state.items.insert(name.clone(), item);
+                        report.change(name, Side::Remote, kind);
+                    } else {
+                        let pending = Pending::new(name, &href, local, kind, None);
+                        report.conflicts.push(self.resolve(store, state, pending)?);
+                    }
+                }
+                (Some(Kind::Delete), Some(Kind::Delete)) => {
+                    state.items.remove(name);
+                }
+                (Some(kind), Some(remote)) => {
+                    let pending = Pending::new(name, &href, local, kind, Some(remote));
+                    report.conflicts.push(self.resolve(store, state, pending)?);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Resolves a conflict with the strategy, against the current remote version.
+     */
+    fn resolve<S>(
+        &self,
+        store: &mut S,
+        state: &mut State,
+        pending: Pending<'_>,
+    ) -> crate::Result<Conflict>
+    where
+        S: Store + ?Sized,
+    {
+        let Pending { name, href, .. } = pending;
+        let item = state.items.get(name).cloned();
+
+        let local = match pending.local {
+            Kind::Delete => None,
+            _ => Some(store.get(name)?),
+        };
+        let remote = self.download(href)?;
+
+        let remote_kind = pending.remote.unwrap_or(match (&remote, &item) {
+            (None, _) => Kind::Delete,
+            (Some(_), None) => Kind::Create,
+            (Some(_), Some(_)) => Kind::Update,
+        });
+        let expected = match &remote {
+            Some((_, etag)) => Expected::At(etag.as_deref()),
+            None => Expected::Absent,
+        };
+
+        let conflicted = strategy::Conflicted {
+            name: name.to_string(),
+            local: local.clone(),
+            remote: remote.as_ref().map(|(data, _)| data.clone()),
+            base: item.and_then(|x| x.base),
+        };
+
+        let decision = match self.strategy.resolve(&conflicted)? {
+            strategy::Resolution::Skip => Decision::Skipped,
+            strategy::Resolution::Remote => {
+                save(store, state, name, remote)?;
+                Decision::Remote
+            }
+            strategy::Resolution::Local => match (local, pending.revision) {
+                (Some(data), Some(revision)) => match self.upload(href, &data, expected)? {
+                    Some(etag) => {
+                        let item = Item {
+                            etag,
+                            revision: revision.to_string(),
+                            base: Some(data),
+                        };
+
+                        state.items.insert(name.to_string(), item);
+                        Decision::Local
+                    }
+                    None => Decision::Skipped,
+                },
+                _ if self.remove(href, expected)? => {
+                    state.items.remove(name);
+                    Decision::Local
+                }
+                _ => Decision::Skipped,
+            },
+            strategy::Resolution::Merged(data) => match self.upload(href, &data, expected)? {
+                Some(etag) => {
+                    save(store, state, name, Some((data, etag)))?;
+                    Decision::Merged
+                }
+                None => Decision::Skipped,
+            },
+            strategy::Resolution::KeepBoth(copy) => {
+                let copy_name = format!("{}.ics", uuid::Uuid::now_v7());
+
+                match self.upload(&self.href(&copy_name), &copy, Expected::Absent)? {
+                    Some(etag) => {
+                        save(store, state, &copy_name, Some((copy, etag)))?;
+                        save(store, state, name, remote)?;
+                        Decision::KeptBoth(copy_name)
+                    }
+                    None => Decision::Skipped,
+                }
+            }
+        };
+
+        Ok(Conflict {
+            name: name.to_string(),
+            local: pending.local,
+            remote: remote_kind,
+            decision,
+        })
+    }
+
+    /**
+     * Remote changes since the state sync token.
+     */
+    fn remote(&self, state: &State) -> crate::Result<Remote> {
+        let result = match self.sync_collection(state.sync_token.as_deref()) {
+            Err(crate::Error::Request { status, .. })
+                if is_rejected(status) && state.sync_token.is_some() =>
+            {
+                self.sync_collection(None)
+            }
+            result => result,
+        };
+
+        let mut remote = match result {
+            Err(crate::Error::Request { status, .. }) if is_rejected(status) => {
+                let etags = self
+                    .calendar
+                    .etags(None)?
+                    .into_iter()
+                    .filter_map(|(href, etag)| Some((name(&href)?.to_string(), (href, etag))))
+                    .collect();
+
+                Remote {
+                    etags,
+                    complete: true,
+                    ..Default::default()
+                }
+            }
+            result => result?,
+        };
+
+        if remote.complete {
+            remote.removed = state
+                .items
+                .keys()
+                .filter(|x| !remote.etags.contains_key(*x))
+                .cloned()
+                .collect();
+        }
+
+        Ok(remote)
+    }
+
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc6578#section-3.2>
+     */
+    fn sync_collection(&self, sync_token: Option<&str>) -> crate::Result<Remote> {
+        let body = format!(
+            r#"
+<d:sync-collection xmlns:d="DAV:">
+    <d:sync-token>{}</d:sync-token>
+    <d:sync-level>1</d:sync-level>
+    <d:prop>
+        <d:getetag />
+    </d:prop>
+</d:sync-collection>"#,
+            crate::elements::escape::text(sync_token.unwrap_or_default()),
+        );
+
+        let mut headers = crate::Headers::new();
+        headers.insert("Depth", "0");
+
+        let response = self.calendar.request(
+            crate::Method::REPORT,
+            self.calendar.url(),
+            Some(&body),
+            Some(headers),
+        )?;
+        let multistatus = response.parse::<crate::elements::Multistatus>()?;
+        let getetag = crate::elements::PropName::dav("getetag");
+
+        let mut remote = Remote {
+            complete: sync_token.is_none(),
+            sync_token: multistatus.sync_token,
+            ..Default::default()
+        };
+
+        for (href, properties) in multistatus.responses {
+            let Some(name) = name(&href).map(ToString::to_string) else {
+                continue;
+            };
+
+            if multistatus.statuses.get(&href) == Some(&404) {
+                remote.removed.insert(name);
+            } else {
+                let etag = properties
+                    .get(&getetag)
+                    .filter(|x| x.is_success())
+                    .map(|x| x.value.text.clone());
+
+                remote
+                    .etags
+                    .insert(name, (self.calendar.append_host(href), etag));
+            }
+        }
+
+        Ok(remote)
+    }
+
+    /**
+     * Data and etag of the object, `None` if it doesn’t exist.
+     */
+    fn download(&self, href: &str) -> crate::Result<Option<(String, Option<String>)>> {
+        let response = self
+            .calendar
+            .session()
+            .send("GET", href, None, &crate::Headers::new())?;
+
+        if response.status == attohttpc::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !response.is_success() {
+            return Err(crate::Error::from_response(
+                crate::Method::GET,
+                href,
+                response,
+            ));
+        }
+
+        let etag = etag(&response);
+
+        Ok(Some((response.body, etag)))
+    }
+
+    /**
+     * Creates or replaces the object unless it isn’t `expected`, returns the new etag or `None`
+     * on conflict.
+     */
+    fn upload(
+        &self,
+        href: &str,
+        data: &str,
+        expected: Expected<'_>,
+    ) -> crate::Result<Option<Option<String>>> {
+        let mut headers = crate::Headers::new();
+        headers.insert("Content-Type", "text/calendar; charset=utf-8");
+        expected.apply(&mut headers);
+
+        let response = self
+            .calendar
+            .session()
+            .send("PUT", href, Some(data), &headers)?;
+
+        if response.status == attohttpc::StatusCode::PRECONDITION_FAILED {
+            return Ok(None);
+        }
+
+        if !response.is_success() {
+            return Err(crate::Error::from_response(
+                crate::Method::PUT,
+                href,
+                response,
+            ));
+        }
+
+        Ok(Some(etag(&response)))
+    }
+
+    /**
+     * Deletes the object unless it isn’t `expected`, returns `false` on conflict.
+     */
+    fn remove(&self, href: &str, expected: Expected<'_>) -> crate::Result<bool> {
+        let mut headers = crate::Headers::new();
+        expected.apply(&mut headers);
+
+        let response = self
+            .calendar
+            .session()
+            .send("DELETE", href, None, &headers)?;
+
+        match response.status {
+            attohttpc::StatusCode::PRECONDITION_FAILED => Ok(false),
+            attohttpc::StatusCode::NOT_FOUND => Ok(true),
+            _ if response.is_success() => Ok(true),
+            _ => Err(crate::Error::from_response(
+                crate::Method::DELETE,
+                href,
+                response,
+            )),
+        }
+    }
+
+    fn href(&self, name: &str) -> String {
+        format!("{}/{name}", self.calendar.url().trim_end_matches('/'))
+    }
+}
+
+/**
+ * A conflicting object, before it’s resolved.
+ */
+struct Pending<'a> {
+    name: &'a str,
+    href: &'a str,
+    /** Local revision, `None` if it was deleted. */
+    revision: Option<&'a str>,
+    local: Kind,
+    /** `None` when a conditional request found a concurrent change. */
+    remote: Option<Kind>,
+}
+
+impl<'a> Pending<'a> {
+    fn new(
+        name: &'a str,
+        href: &'a str,
+        local: &'a BTreeMap<String, String>,
+        kind: Kind,
+        remote: Option<Kind>,
+    ) -> Self {
+        Self {
+            name,
+            href,
+            revision: local.get(name).map(String::as_str),
+            local: kind,
+            remote,
+        }
+    }
+}
+
+/**
+ * What the remote object must be for a conditional request to succeed.
+ */
+#[derive(Clone, Copy, Debug)]
+enum Expected<'a> {
+    Absent,
+    /** At this etag, the request is unconditional when it’s unknown. */
+    At(Option<&'a str>),
+}
+
+impl<'a> Expected<'a> {
+    fn from(item: Option<&'a Item>) -> Self {
+        match item {
+            None => Self::Absent,
+            Some(item) => Self::At(item.etag.as_deref()),
+        }
+    }
+
+    fn apply(self, headers: &mut crate::Headers<'a>) {
+        match self {
+            Self::Absent => {
+                headers.insert("If-None-Match", "*");
+            }
+            Self::At(Some(etag)) => {
+                headers.insert("If-Match", etag);
+            }
+            Self::At(None) => (),
+        }
+    }
+}
+
+/**
+ * Stores the remote version locally, or deletes the local one if it doesn’t exist.
+ */
+fn save<S>(
+    store: &mut S,
+    state: &mut State,
+    name: &str,
+    remote: Option<(String, Option<String>)>,
+) -> crate::Result
+where
+    S: Store + ?Sized,
+{
+    match remote {
+        Some((data, etag)) => {
+            let revision = store.put(name, &data)?;
+            let item = Item {
+                etag,
+                revision,
+                base: Some(data),
+            };
+
+            state.items.insert(name.to_string(), item);
+        }
+        None => {
+            store.delete(name)?;
+            state.items.remove(name);
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Remote objects changed since the previous run.
+ */
+#[derive(Debug, Default)]
+struct Remote {
+    /** URL and etag by name. */
+    etags: BTreeMap<String, (String, Option<String>)>,
+    removed: BTreeSet<String>,
+    /** Whether `etags` lists every object, instead of the changed ones. */
+    complete: bool,
+    sync_token: Option<String>,
+}
+
+impl Remote {
+    fn change(&self, name: &str, item: Option<&Item>) -> Option<Kind> {
+        if let Some((_, etag)) = self.etags.get(name) {
+            match item {
+                None => Some(Kind::Create),
+                Some(item) if etag.is_none() || item.etag != *etag => Some(Kind::Update),
+                Some(_) => None,
+            }
+        } else if self.removed.contains(name) && item.is_some() {
+            Some(Kind::Delete)
+        } else {
+            None
+        }
+    }
+}
+
+/**
+ * A store keeping the objects in memory, mostly useful for tests.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    objects: BTreeMap<String, (String, u64)>,
+    state: State,
+    revision: u64,
+}
+
+impl Store for Memory {
+    fn list(&self) -> crate::Result<BTreeMap<String, String>> {
+        let list = self
+            .objects
+            .iter()
+            .map(|(name, (_, revision))| (name.clone(), revision.to_string()))
+            .collect();
+
+        Ok(list)
+    }
+
+    fn get(&self, name: &str) -> crate::Result<String> {
+        self.objects
+            .get(name)
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| crate::Error::Misc(format!("Unknown object: {name}")))
+    }
+
+    fn put(&mut self, name: &str, data: &str) -> crate::Result<String> {
+        self.revision += 1;
+        self.objects
+            .insert(name.to_string(), (data.to_string(), self.revision));
+
+        Ok(self.revision.to_string())
+    }
+
+    fn delete(&mut self, name: &str) -> crate::Result {
+        self.objects.remove(name);
+
+        Ok(())
+    }
+
+    fn state(&self) -> crate::Result<State> {
+        Ok(self.state.clone())
+    }
+
+    fn set_state(&mut self, state: &State) -> crate::Result {
+        self.state = state.clone();
+
+        Ok(())
+    }
+}
+
+/**
+ * Name of the object at `href`, `None` for a collection.
+ */
+fn name(href: &str) -> Option<&str> {
+    href.rsplit('/').next().filter(|x| !x.is_empty())
+}
+
+fn etag(response: &crate::transport::Response) -> Option<String> {
+    response
+        .headers
+        .get("ETag")
+        .and_then(|x| x.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/**
+ * Whether the server refused the report, because it doesn’t support it or the sync token is no
+ * longer valid.
+ */
+fn is_rejected(status: attohttpc::StatusCode) -> bool {
+    matches!(
+        status,
+        attohttpc::StatusCode::BAD_REQUEST
+            | attohttpc::StatusCode::FORBIDDEN
+            | attohttpc::StatusCode::METHOD_NOT_ALLOWED
+            | attohttpc::StatusCode::CONFLICT
+            | attohttpc::StatusCode::NOT_IMPLEMENTED
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Conflict, Decision, Kind, Side, Store as _};
+
+    fn event(uid: &str, summary: &str) -> String {
+        calendar(uid, &format!("SUMMARY:{summary}\r\n"))
+    }
+
+    fn calendar(uid: &str, properties: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:{uid}\r
+DTSTAMP:20240101T090000Z\r
+DTSTART:20240701T100000Z\r
+{properties}END:VEVENT\r
+END:VCALENDAR\r
+"
+        )
+    }
+
+    fn server() -> kaldav_testserver::Server {
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+
+        server
+    }
+
+    fn change(name: &str, side: Side, kind: Kind) -> Change {
+        Change {
+            name: name.to_string(),
+            side,
+            kind,
+        }
+    }
+
+    #[test]
+    fn sync() -> crate::Result {
+        let server = server();
+        server.put("home", "1.ics", &event("1", "Remote"));
+
+        let client = crate::Client::new(server.url(""));
+        let calendar = client.calendars()?.remove("Home calendar").unwrap();
+
+        let mut store = super::Memory::default();
+        store.put("a.ics", &event("a", "Local"))?;
+
+        let report = calendar.sync().run(&mut store)?;
+        assert_eq!(
+            report.changes,
+            [
+                change("1.ics", Side::Local, Kind::Create),
+                change("a.ics", Side::Remote, Kind::Create),
+            ]
+        );
+        assert_eq!(store.get("1.ics")?, event("1", "Remote"));
+        assert_eq!(
+            server.object("home", "a.ics").unwrap().data,
+            event("a", "Local")
+        );
+
+        assert!(calendar.sync().run(&mut store)?.is_empty());
+
+        server.put("home", "1.ics", &event("1", "Updated"));
+        server.delete("home", "a.ics");
+        store.put("b.ics", &event("b", "Local"))?;
+
+        let report = calendar.sync().run(&mut store)?;
+        assert_eq!(
+            report.changes,
+            [
+                change("1.ics", Side::Local, Kind::Update),
+                change("a.ics", Side::Local, Kind::Delete),
+                change("b.ics", Side::Remote, Kind::Create),
+            ]
+        );
+        assert_eq!(store.get("1.ics")?, event("1", "Updated"));
+        assert!(store.get("a.ics").is_err());
+
+        store.delete("b.ics")?;
+        let mut state = store.state()?;
+        state.sync_token = Some("urn:example:expired".to_string());
+        store.set_state(&state)?;
+
+        let report = calendar.sync().run(&mut store)?;
+        assert_eq!(
+            report.changes,
+            [change("b.ics", Side::Remote, Kind::Delete)]
+        );
+        assert!(server.object("home", "b.ics").is_none());
+
+        server.put("home", "1.ics", &event("1", "Remote change"));
+        store.put("1.ics", &event("1", "Local change"))?;
+
+        let report = calendar.sync().run(&mut store)?;
+        assert!(report.changes.is_empty());
+        assert_eq!(
+            report.conflicts,
+            [Conflict {
+                name: "1.ics".to_string(),
+                local: Kind::Update,
+                remote: Kind::Update,
+                decision: Decision::Skipped,
+            }]
+        );
+        assert_eq!(store.get("1.ics")?, event("1", "Local change"));
+        assert_eq!(
+            server.object("home", "1.ics").unwrap().data,
+            event("1", "Remote change")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn strategies() -> crate::Result {
+        use super::strategy;
+
+        let server = server();
+        server.put("home", "1.ics", &event("1", "Meeting"));
+
+        let client = crate::Client::new(server.url(""));
+        let home = client.calendars()?.remove("Home calendar").unwrap();
+
+        let mut store = super::Memory::default();
+        home.sync().run(&mut store)?;
+
+        let mut conflict =
+            |local: &str, remote: &str, strategy: &dyn Fn() -> Box<dyn super::Strategy>| {
+                store.put("1.ics", local)?;
+                server.put("home", "1.ics", remote);
+
+                let strategy = strategy();
+                let report = home
+                    .sync()
+                    .strategy(move |x: &strategy::Conflicted| strategy.resolve(x))
+                    .run(&mut store)?;
+                assert!(report.changes.is_empty());
+                assert_eq!(report.conflicts.len(), 1);
+
+                Ok::<_, crate::Error>((report.conflicts[0].decision.clone(), store.clone()))
+            };
+
+        let (decision, store) = conflict(&event("1", "L1"), &event("1", "R1"), &|| {
+            Box::new(strategy::RemoteWins)
+        })?;
+        assert_eq!(decision, Decision::Remote);
+        assert_eq!(store.get("1.ics")?, event("1", "R1"));
+
+        let (decision, _) = conflict(&event("1", "L2"), &event("1", "R2"), &|| {
+            Box::new(strategy::LocalWins)
+        })?;
+        assert_eq!(decision, Decision::Local);
+        assert_eq!(
+            server.object("home", "1.ics").unwrap().data,
+            event("1", "L2")
+        );
+
+        let (decision, store) = conflict(
+            &calendar("1", "SUMMARY:L2\r\nLOCATION:Room 1\r\n"),
+            &event("1", "R3"),
+            &|| Box::new(strategy::Merge::default()),
+        )?;
+        let merged = calendar("1", "SUMMARY:R3\r\nLOCATION:Room 1\r\n");
+        assert_eq!(decision, Decision::Merged);
+        assert_eq!(store.get("1.ics")?, merged);
+        assert_eq!(server.object("home", "1.ics").unwrap().data, merged);
+
+        let (decision, mut store) = conflict(&event("1", "L4"), &event("1", "R4"), &|| {
+            Box::new(strategy::KeepBoth)
+        })?;
+        let Decision::KeptBoth(copy) = decision else {
+            panic!("expected a copy, got {decision:?}");
+        };
+        assert_eq!(store.get("1.ics")?, event("1", "R4"));
+        assert_eq!(
+            server.object("home", &copy).unwrap().data,
+            store.get(&copy)?
+        );
+
+        assert!(home.sync().run(&mut store)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn state() -> crate::Result {
+        let mut state = super::State {
+            sync_token: Some("urn:example:sync:1".to_string()),
+            ..Default::default()
+        };
+        state.items.insert(
+            "1.ics".to_string(),
+            super::Item {
+                etag: Some("\"1\"".to_string()),
+                revision: "1".to_string(),
+                base: Some("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n".to_string()),
+            },
+        );
+        state
+            .items
+            .insert("2.ics".to_string(), super::Item::default());
+
+        assert_eq!(state.to_json().parse::<super::State>()?, state);
+        assert!("{}".parse::<super::State>().is_err());
+
+        Ok(())
+    }
+}
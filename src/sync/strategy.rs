@@ -0,0 +1,430 @@
+/*!
+ * How an object changed on both sides is resolved, see [`crate::sync::Synchronizer::strategy`].
+ *
+ * ```no_run
+ * use kaldav::sync::strategy;
+ *
+ * let client = kaldav::Client::new("https://caldav.example.org/");
+ * let calendars = client.calendars()?;
+ * let mut store = kaldav::sync::Memory::default();
+ *
+ * let report = calendars["Home calendar"]
+ *     .sync()
+ *     .strategy(strategy::Merge::new(strategy::Newest))
+ *     .run(&mut store)?;
+ *
+ * for conflict in &report.conflicts {
+ *     println!("{}: {:?}", conflict.name, conflict.decision);
+ * }
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+
+use crate::component::Component;
+use std::sync::Arc;
+
+/**
+ * The two versions of a conflicting object, `None` on the side it was deleted.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Conflicted {
+    pub name: String,
+    pub local: Option<String>,
+    pub remote: Option<String>,
+    /** Version synchronized by the previous run, the common ancestor of the two others. */
+    pub base: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /** Leaves both sides untouched, the conflict is reported again by the next run. */
+    Skip,
+    /** Overwrites the remote object with the local one. */
+    Local,
+    /** Overwrites the local object with the remote one. */
+    Remote,
+    /** Replaces both sides with this data. */
+    Merged(String),
+    /** Keeps the remote object and creates this copy of the local one, with another `UID`. */
+    KeepBoth(String),
+}
+
+pub trait Strategy: Send + Sync {
+    fn resolve(&self, conflicted: &Conflicted) -> crate::Result<Resolution>;
+}
+
+impl<F> Strategy for F
+where
+    F: Fn(&Conflicted) -> crate::Result<Resolution> + Send + Sync,
+{
+    fn resolve(&self, conflicted: &Conflicted) -> crate::Result<Resolution> {
+        self(conflicted)
+    }
+}
+
+/**
+ * Reports the conflicts without resolving them, the default.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Skip;
+
+impl Strategy for Skip {
+    fn resolve(&self, _: &Conflicted) -> crate::Result<Resolution> {
+        Ok(Resolution::Skip)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalWins;
+
+impl Strategy for LocalWins {
+    fn resolve(&self, _: &Conflicted) -> crate::Result<Resolution> {
+        Ok(Resolution::Local)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RemoteWins;
+
+impl Strategy for RemoteWins {
+    fn resolve(&self, _: &Conflicted) -> crate::Result<Resolution> {
+        Ok(Resolution::Remote)
+    }
+}
+
+/**
+ * Keeps the version with the greatest `SEQUENCE`, then the most recent `LAST-MODIFIED`. A
+ * modification wins over a deletion and the remote version wins a tie.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Newest;
+
+impl Strategy for Newest {
+    fn resolve(&self, conflicted: &Conflicted) -> crate::Result<Resolution> {
+        let resolution = match (&conflicted.local, &conflicted.remote) {
+            (Some(local), Some(remote)) if revision(local) > revision(remote) => Resolution::Local,
+            (Some(_), None) => Resolution::Local,
+            _ => Resolution::Remote,
+        };
+
+        Ok(resolution)
+    }
+}
+
+/**
+ * Merges the properties changed on each side since the previous run, the `fallback` strategy
+ * resolves the conflicts it can’t merge: a property changed on both sides, an object deleted on
+ * one side or missing its previous version.
+ *
+ * `DTSTAMP`, `LAST-MODIFIED` and `SEQUENCE` take the greatest value instead of conflicting.
+ */
+#[derive(Clone)]
+pub struct Merge {
+    fallback: Arc<dyn Strategy>,
+}
+
+impl Merge {
+    pub fn new<S>(fallback: S) -> Self
+    where
+        S: Strategy + 'static,
+    {
+        Self {
+            fallback: Arc::new(fallback),
+        }
+    }
+}
+
+impl Default for Merge {
+    fn default() -> Self {
+        Self::new(Skip)
+    }
+}
+
+impl std::fmt::Debug for Merge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Merge").finish_non_exhaustive()
+    }
+}
+
+impl Strategy for Merge {
+    fn resolve(&self, conflicted: &Conflicted) -> crate::Result<Resolution> {
+        let parse = |data: &Option<String>| data.as_deref().and_then(crate::component::parse);
+
+        let merged = match (
+            parse(&conflicted.base),
+            parse(&conflicted.local),
+            parse(&conflicted.remote),
+        ) {
+            (Some(base), Some(local), Some(remote)) => merge(&base, &local, &remote),
+            _ => None,
+        };
+
+        match merged {
+            Some(merged) => Ok(Resolution::Merged(merged.to_string())),
+            None => self.fallback.resolve(conflicted),
+        }
+    }
+}
+
+/**
+ * Keeps both versions, the local one under a new `UID`. When one side deleted the object, the
+ * other version is kept.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeepBoth;
+
+impl Strategy for KeepBoth {
+    fn resolve(&self, conflicted: &Conflicted) -> crate::Result<Resolution> {
+        let resolution = match (&conflicted.local, &conflicted.remote) {
+            (Some(local), Some(_)) => {
+                let mut calendar = crate::component::parse(local).ok_or_else(|| {
+                    crate::Error::Misc(format!("{}: invalid calendar data", conflicted.name))
+                })?;
+                let uid = uuid::Uuid::now_v7().to_string();
+
+                for component in &mut calendar.components {
+                    if let Some(property) =
+                        component.properties.iter_mut().find(|x| x.name == "UID")
+                    {
+                        property.value = uid.clone();
+                    }
+                }
+
+                Resolution::KeepBoth(calendar.to_string())
+            }
+            (Some(_), None) => Resolution::Local,
+            _ => Resolution::Remote,
+        };
+
+        Ok(resolution)
+    }
+}
+
+/**
+ * The greatest `SEQUENCE` and `LAST-MODIFIED` of the components.
+ */
+fn revision(data: &str) -> (i64, String) {
+    let Some(calendar) = crate::component::parse(data) else {
+        return Default::default();
+    };
+
+    calendar
+        .components
+        .iter()
+        .filter(|x| x.name != "VTIMEZONE")
+        .map(|x| {
+            let sequence = x
+                .property("SEQUENCE")
+                .and_then(|x| x.value.trim().parse().ok())
+                .unwrap_or_default();
+            let last_modified = x
+                .property("LAST-MODIFIED")
+                .map(|x| x.value.clone())
+                .unwrap_or_default();
+
+            (sequence, last_modified)
+        })
+        .max()
+        .unwrap_or_default()
+}
+
+/**
+ * Three-way merge of `local` and `remote`, `None` if they changed the same property or
+ * component differently.
+ */
+fn merge(base: &Component, local: &Component, remote: &Component) -> Option<Component> {
+    let mut names = Vec::<&str>::new();
+
+    for property in local.properties.iter().chain(&remote.properties) {
+        if !names.contains(&property.name.as_str()) {
+            names.push(&property.name);
+        }
+    }
+
+    let mut properties = Vec::new();
+
+    for name in names {
+        let values = |component: &Component| {
+            component
+                .properties
+                .iter()
+                .filter(|x| x.name == name)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let (base, local, remote) = (values(base), values(local), values(remote));
+
+        let merged = if local == remote || remote == base {
+            local
+        } else if local == base {
+            remote
+        } else if matches!(name, "DTSTAMP" | "LAST-MODIFIED" | "SEQUENCE")
+            && local.len() == 1
+            && remote.len() == 1
+        {
+            let greatest = if name == "SEQUENCE" {
+                let sequence = |x: &[crate::component::Property]| {
+                    x[0].value.trim().parse::<i64>().unwrap_or_default()
+                };
+
+                sequence(&local) >= sequence(&remote)
+            } else {
+                local[0].value >= remote[0].value
+            };
+
+            if greatest { local } else { remote }
+        } else {
+            return None;
+        };
+
+        properties.extend(merged);
+    }
+
+    let mut keys = Vec::new();
+
+    for component in local.components.iter().chain(&remote.components) {
+        let key = key(component);
+
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let mut components = Vec::new();
+
+    for key in keys {
+        let find = |parent: &'_ Component| -> Option<Component> {
+            parent
+                .components
+                .iter()
+                .find(|x| self::key(x) == key)
+                .cloned()
+        };
+
+        let merged = match (find(base), find(local), find(remote)) {
+            (_, local, remote) if local == remote => local,
+            (base, local, remote) if local == base => remote,
+            (base, local, remote) if remote == base => local,
+            (Some(base), Some(local), Some(remote)) => Some(merge(&base, &local, &remote)?),
+            _ => return None,
+        };
+
+        components.extend(merged);
+    }
+
+    Some(Component {
+        name: local.name.clone(),
+        properties,
+        components,
+    })
+}
+
+/**
+ * Identifies a component in the three versions: its name, `UID`, `RECURRENCE-ID` and `TZID`.
+ */
+fn key(component: &Component) -> (String, Option<String>, Option<String>, Option<String>) {
+    let value = |name: &str| component.property(name).map(|x| x.value.clone());
+
+    (
+        component.name.clone(),
+        value("UID"),
+        value("RECURRENCE-ID"),
+        value("TZID"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Conflicted, Resolution, Strategy as _};
+
+    fn event(properties: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:1\r
+{properties}END:VEVENT\r
+END:VCALENDAR\r
+"
+        )
+    }
+
+    fn conflicted(local: Option<&str>, remote: Option<&str>) -> Conflicted {
+        Conflicted {
+            name: "1.ics".to_string(),
+            local: local.map(event),
+            remote: remote.map(event),
+            base: Some(event("SUMMARY:Meeting\r\nSEQUENCE:0\r\n")),
+        }
+    }
+
+    #[test]
+    fn newest() -> crate::Result {
+        let strategy = super::Newest;
+
+        let newer = conflicted(
+            Some("SUMMARY:Local\r\nSEQUENCE:2\r\n"),
+            Some("SUMMARY:Remote\r\nSEQUENCE:1\r\nLAST-MODIFIED:20250101T000000Z\r\n"),
+        );
+        assert_eq!(strategy.resolve(&newer)?, Resolution::Local);
+
+        let modified = conflicted(
+            Some("SUMMARY:Local\r\nLAST-MODIFIED:20240101T000000Z\r\n"),
+            Some("SUMMARY:Remote\r\nLAST-MODIFIED:20250101T000000Z\r\n"),
+        );
+        assert_eq!(strategy.resolve(&modified)?, Resolution::Remote);
+
+        let deleted = conflicted(Some("SUMMARY:Local\r\n"), None);
+        assert_eq!(strategy.resolve(&deleted)?, Resolution::Local);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge() -> crate::Result {
+        let strategy = super::Merge::new(super::RemoteWins);
+
+        let merged = conflicted(
+            Some("SUMMARY:Meeting\r\nSEQUENCE:1\r\nLOCATION:Room 1\r\n"),
+            Some("SUMMARY:Planning\r\nSEQUENCE:2\r\n"),
+        );
+        assert_eq!(
+            strategy.resolve(&merged)?,
+            Resolution::Merged(event(
+                "SUMMARY:Planning\r\nSEQUENCE:2\r\nLOCATION:Room 1\r\n"
+            ))
+        );
+
+        let clash = conflicted(Some("SUMMARY:Local\r\n"), Some("SUMMARY:Remote\r\n"));
+        assert_eq!(strategy.resolve(&clash)?, Resolution::Remote);
+
+        assert_eq!(super::Merge::default().resolve(&clash)?, Resolution::Skip);
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_both() -> crate::Result {
+        let strategy = super::KeepBoth;
+
+        let Resolution::KeepBoth(copy) = strategy.resolve(&conflicted(
+            Some("SUMMARY:Local\r\n"),
+            Some("SUMMARY:Remote\r\n"),
+        ))?
+        else {
+            panic!("expected a copy");
+        };
+        let copy = crate::component::parse(&copy).unwrap();
+        let event = &copy.components[0];
+        assert_ne!(event.property("UID").unwrap().value, "1");
+        assert_eq!(event.property("SUMMARY").unwrap().value, "Local");
+
+        assert_eq!(
+            strategy.resolve(&conflicted(None, Some("SUMMARY:Remote\r\n")))?,
+            Resolution::Remote
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,425 @@
+/*!
+ * A [`Store`](super::Store) keeping a calendar in a
+ * [vdir](https://vdirsyncer.pimutils.org/en/stable/vdir.html), the layout read by khal and
+ * todoman.
+ *
+ * Each object is an `.ics` file named after the object, the `displayname` and `color` files
+ * hold the metadata of the calendar. A name that isn’t a plain `.ics` file name, like the
+ * `abc123` of an `/calendars/home/abc123` href, is percent-encoded into one, and the mapping kept
+ * in the synchronization state. The revision of a file is its modification time and inode,
+ * like the etags of vdirsyncer, so both see the same modifications of the files.
+ *
+ * The synchronization state is kaldav’s own, kept in a hidden file that vdirsyncer and khal
+ * ignore. Interoperating with the state of vdirsyncer isn’t supported yet: vdirsyncer keeps its
+ * status in a SQLite database, which kaldav can neither read nor write without a SQLite
+ * dependency. Until then, a directory shouldn’t be synchronized by both.
+ *
+ * ```no_run
+ * let client = kaldav::Client::new("https://caldav.example.org/");
+ * let calendars = client.calendars()?;
+ * let calendar = &calendars["Home calendar"];
+ *
+ * let mut vdir = kaldav::sync::vdir::Vdir::new("/home/johndoe/.calendars/home")?;
+ * vdir.set_displayname("Home calendar")?;
+ * if let Some(color) = &calendar.color {
+ *     vdir.set_color(color)?;
+ * }
+ *
+ * calendar.sync().run(&mut vdir)?;
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const EXTENSION: &str = "ics";
+const STATE: &str = ".kaldav-sync.json";
+
+#[derive(Clone, Debug)]
+pub struct Vdir {
+    path: PathBuf,
+    /** File names of the objects whose name isn’t one. */
+    files: BTreeMap<String, String>,
+}
+
+impl Vdir {
+    /**
+     * Uses the `path` directory, creating it if needed.
+     */
+    pub fn new<P>(path: P) -> crate::Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        std::fs::create_dir_all(&path)?;
+
+        let files = read_state(&path)?
+            .as_ref()
+            .and_then(|x| x.get("files"))
+            .and_then(|x| x.as_object())
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, file)| Some((name.clone(), file.as_str()?.to_string())))
+            .filter(|(_, file)| valid(file))
+            .collect();
+
+        Ok(Self { path, files })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn displayname(&self) -> crate::Result<Option<String>> {
+        self.metadata("displayname")
+    }
+
+    pub fn set_displayname(&self, displayname: &str) -> crate::Result {
+        write(&self.path.join("displayname"), displayname)
+    }
+
+    /**
+     * Color of the calendar, like `#ffd4a5`.
+     */
+    pub fn color(&self) -> crate::Result<Option<String>> {
+        self.metadata("color")
+    }
+
+    pub fn set_color(&self, color: &str) -> crate::Result {
+        write(&self.path.join("color"), color)
+    }
+
+    fn metadata(&self, name: &str) -> crate::Result<Option<String>> {
+        match std::fs::read_to_string(self.path.join(name)) {
+            Ok(value) => Ok(Some(value.trim().to_string()).filter(|x| !x.is_empty())),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /**
+     * Path of the object file, without allocating one for an unknown name.
+     */
+    fn file(&self, name: &str) -> crate::Result<PathBuf> {
+        if let Some(file) = self.files.get(name) {
+            Ok(self.path.join(file))
+        } else if valid(name) && !self.is_mapped(name) {
+            Ok(self.path.join(name))
+        } else {
+            Err(crate::Error::Misc(format!(
+                "Unknown vdir object name: {name}"
+            )))
+        }
+    }
+
+    /**
+     * Path of the object file, encoding the name into a new file name if needed.
+     */
+    fn allocate(&mut self, name: &str) -> crate::Result<PathBuf> {
+        if let Ok(path) = self.file(name) {
+            return Ok(path);
+        }
+
+        let encoded = encode(name);
+        let file = (0..)
+            .map(|n| {
+                if n == 0 {
+                    format!("{encoded}.{EXTENSION}")
+                } else {
+                    format!("{encoded}-{n}.{EXTENSION}")
+                }
+            })
+            .find(|x| !self.is_mapped(x) && !self.path.join(x).exists())
+            .unwrap_or_default();
+
+        self.files.insert(name.to_string(), file.clone());
+        self.save_files()?;
+
+        Ok(self.path.join(file))
+    }
+
+    fn is_mapped(&self, file: &str) -> bool {
+        self.files.values().any(|x| x == file)
+    }
+
+    /**
+     * Saves the file names in the state file, before any object is written with them.
+     */
+    fn save_files(&self) -> crate::Result {
+        let state = read_state(&self.path)?.unwrap_or_else(|| serde_json::json!({ "items": {} }));
+
+        self.write_state(state)
+    }
+
+    fn write_state(&self, mut state: serde_json::Value) -> crate::Result {
+        if let Some(state) = state.as_object_mut() {
+            state.insert("files".to_string(), serde_json::json!(self.files));
+        }
+
+        write(&self.path.join(STATE), &state.to_string())
+    }
+}
+
+impl super::Store for Vdir {
+    fn list(&self) -> crate::Result<BTreeMap<String, String>> {
+        let mut list = BTreeMap::new();
+
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata()?;
+
+            if !metadata.is_file() || !valid(&name) {
+                continue;
+            }
+
+            let name = self
+                .files
+                .iter()
+                .find(|(_, file)| **file == name)
+                .map_or(name, |(name, _)| name.clone());
+
+            list.insert(name, revision(&metadata)?);
+        }
+
+        Ok(list)
+    }
+
+    fn get(&self, name: &str) -> crate::Result<String> {
+        Ok(std::fs::read_to_string(self.file(name)?)?)
+    }
+
+    fn put(&mut self, name: &str, data: &str) -> crate::Result<String> {
+        let path = self.allocate(name)?;
+        write(&path, data)?;
+
+        revision(&std::fs::metadata(path)?)
+    }
+
+    fn delete(&mut self, name: &str) -> crate::Result {
+        let Ok(path) = self.file(name) else {
+            return Ok(());
+        };
+
+        match std::fs::remove_file(path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                return Err(error.into());
+            }
+            _ => (),
+        }
+
+        if self.files.remove(name).is_some() {
+            self.save_files()?;
+        }
+
+        Ok(())
+    }
+
+    fn state(&self) -> crate::Result<super::State> {
+        match std::fs::read_to_string(self.path.join(STATE)) {
+            Ok(state) => state.parse(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn set_state(&mut self, state: &super::State) -> crate::Result {
+        let json = serde_json::from_str(&state.to_json())
+            .map_err(|error| crate::Error::Misc(error.to_string()))?;
+
+        self.write_state(json)
+    }
+}
+
+/**
+ * Whether `name` is a plain `.ics` file name, one khal would read.
+ */
+fn valid(name: &str) -> bool {
+    !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && Path::new(name).extension().is_some_and(|x| x == EXTENSION)
+}
+
+/**
+ * Percent-encodes every byte but alphanumerics, `-`, `_`, `@` and a `.` that doesn’t start the
+ * name.
+ */
+fn encode(name: &str) -> String {
+    name.bytes()
+        .enumerate()
+        .map(|(n, x)| match x {
+            b'.' if n > 0 => ".".to_string(),
+            x if x.is_ascii_alphanumeric() || matches!(x, b'-' | b'_' | b'@') => {
+                char::from(x).to_string()
+            }
+            x => format!("%{x:02X}"),
+        })
+        .collect()
+}
+
+/**
+ * Content of the state file, `None` if there is none yet.
+ */
+fn read_state(path: &Path) -> crate::Result<Option<serde_json::Value>> {
+    match std::fs::read_to_string(path.join(STATE)) {
+        Ok(state) => serde_json::from_str(&state)
+            .map(Some)
+            .map_err(|_| crate::Error::Misc("Invalid synchronization state".to_string())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/**
+ * Modification time and inode of the file, the etag of vdirsyncer.
+ */
+fn revision(metadata: &std::fs::Metadata) -> crate::Result<String> {
+    let mtime = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+
+    Ok(format!("{mtime:.9};{inode}"))
+}
+
+/**
+ * Replaces the file atomically, through a hidden temporary file that readers ignore, unique so
+ * that concurrent writers don’t share it.
+ */
+fn write(path: &Path, contents: &str) -> crate::Result {
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.{}.tmp", uuid::Uuid::now_v7().simple()));
+
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(tmp, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sync::Store as _;
+
+    #[test]
+    fn vdir() -> crate::Result {
+        let root = crate::test::TempPath::new("kaldav-vdir", "");
+        let mut vdir = super::Vdir::new(&root)?;
+
+        assert_eq!(vdir.displayname()?, None);
+        vdir.set_displayname("Home calendar")?;
+        vdir.set_color("#ffd4a5")?;
+        assert_eq!(vdir.displayname()?.as_deref(), Some("Home calendar"));
+        assert_eq!(vdir.color()?.as_deref(), Some("#ffd4a5"));
+
+        let server = kaldav_testserver::Server::start();
+        server.add_calendar(
+            "home",
+            kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]),
+        );
+        let event = |summary: &str| {
+            format!(
+                "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20240101T090000Z\r
+DTSTART:20240701T100000Z\r
+SUMMARY:{summary}\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+            )
+        };
+        server.put("home", "1.ics", &event("Meeting"));
+
+        let client = crate::Client::new(server.url(""));
+        let calendar = client.calendars()?.remove("Home calendar").unwrap();
+
+        assert_eq!(calendar.sync().run(&mut vdir)?.changes.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(root.join("1.ics"))?,
+            event("Meeting")
+        );
+        assert_eq!(vdir.list()?.keys().collect::<Vec<_>>(), ["1.ics"]);
+
+        // as khal would edit it
+        std::fs::write(root.join("1.ics"), event("Updated"))?;
+
+        let mut vdir = super::Vdir::new(&root)?;
+        let report = calendar.sync().run(&mut vdir)?;
+        assert_eq!(
+            report.changes,
+            [crate::sync::Change {
+                name: "1.ics".to_string(),
+                side: crate::sync::Side::Remote,
+                kind: crate::sync::Kind::Update,
+            }]
+        );
+        assert_eq!(
+            server.object("home", "1.ics").unwrap().data,
+            event("Updated")
+        );
+        assert!(calendar.sync().run(&mut vdir)?.is_empty());
+
+        server.put(
+            "home",
+            "abc123",
+            &event("Without extension").replace("UID:1", "UID:2"),
+        );
+        let report = calendar.sync().run(&mut vdir)?;
+        assert_eq!(report.changes.len(), 1);
+        assert_eq!(report.changes[0].name, "abc123");
+        assert!(root.join("abc123.ics").is_file());
+        assert_eq!(vdir.list()?.keys().collect::<Vec<_>>(), ["1.ics", "abc123"]);
+
+        std::fs::write(
+            root.join("abc123.ics"),
+            event("Updated").replace("UID:1", "UID:2"),
+        )?;
+
+        let mut vdir = super::Vdir::new(&root)?;
+        let report = calendar.sync().run(&mut vdir)?;
+        assert_eq!(
+            report.changes,
+            [crate::sync::Change {
+                name: "abc123".to_string(),
+                side: crate::sync::Side::Remote,
+                kind: crate::sync::Kind::Update,
+            }]
+        );
+        assert!(server.object("home", "abc123.ics").is_none());
+        assert!(calendar.sync().run(&mut vdir)?.is_empty());
+
+        for name in ["../1.ics", ".hidden.ics", "1.vcf", "abc123.ics"] {
+            vdir.put(name, name)?;
+            assert_eq!(vdir.get(name)?, name);
+        }
+        assert_eq!(
+            std::fs::read_to_string(root.join("abc123.ics.ics"))?,
+            "abc123.ics"
+        );
+        assert!(root.join("%2E.%2F1.ics.ics").is_file());
+        assert!(root.join("%2Ehidden.ics.ics").is_file());
+        assert!(root.join("1.vcf.ics").is_file());
+
+        vdir.delete("1.vcf")?;
+        assert!(!root.join("1.vcf.ics").exists());
+        assert_eq!(
+            super::Vdir::new(&root)?.list()?.keys().collect::<Vec<_>>(),
+            ["../1.ics", ".hidden.ics", "1.ics", "abc123", "abc123.ics"]
+        );
+
+        Ok(())
+    }
+}
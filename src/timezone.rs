@@ -0,0 +1,1128 @@
+/*!
+ * Time zones of the calendar data.
+ *
+ * `ikal` drops the `TZID` parameters of the date-times, so the calendar data is read again by
+ * [`Calendar`] to keep them. The time zones are resolved from the `VTIMEZONE` components of the
+ * calendar first, then from a fallback: the IANA database with the `chrono-tz` feature, or any
+ * other source:
+ *
+ * ```no_run
+ * use kaldav::timezone::{Calendar, DateTimes as _, Resolver};
+ *
+ * # fn main() -> kaldav::Result {
+ * # let data = String::new();
+ * # let event = kaldav::ical::VEvent::default();
+ * let resolver = Resolver::new().fallback(|name| {
+ *     (name == "Etc/GMT-1").then(|| chrono::FixedOffset::east_opt(3_600).unwrap())
+ * });
+ * let calendar = Calendar::new(&data, resolver)?;
+ *
+ * if let Some(start) = event.start(&calendar) {
+ *     println!("{}", start.to_utc(calendar.resolver())?);
+ * }
+ * # Ok(())
+ * # }
+ * ```
+ */
+
+use crate::component::{Component, Property};
+use crate::recurrence::{Frequency, Rule};
+use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+/**
+ * Years after the time of a lookup for which the recurrence rules are expanded.
+ */
+const LOOKAHEAD: i32 = 1;
+
+/**
+ * Windows time zone names, as used by Exchange, to their IANA equivalent, plus the legacy
+ * `Kamchatka` and `Mid-Atlantic` names.
+ *
+ * <https://github.com/unicode-org/cldr/blob/main/common/supplemental/windowsZones.xml>
+ */
+const WINDOWS: &[(&str, &str)] = &[
+    ("AUS Central Standard Time", "Australia/Darwin"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+    ("Afghanistan Standard Time", "Asia/Kabul"),
+    ("Alaskan Standard Time", "America/Anchorage"),
+    ("Aleutian Standard Time", "America/Adak"),
+    ("Altai Standard Time", "Asia/Barnaul"),
+    ("Arab Standard Time", "Asia/Riyadh"),
+    ("Arabian Standard Time", "Asia/Dubai"),
+    ("Arabic Standard Time", "Asia/Baghdad"),
+    ("Argentina Standard Time", "America/Buenos_Aires"),
+    ("Astrakhan Standard Time", "Europe/Astrakhan"),
+    ("Atlantic Standard Time", "America/Halifax"),
+    ("Aus Central W. Standard Time", "Australia/Eucla"),
+    ("Azerbaijan Standard Time", "Asia/Baku"),
+    ("Azores Standard Time", "Atlantic/Azores"),
+    ("Bahia Standard Time", "America/Bahia"),
+    ("Bangladesh Standard Time", "Asia/Dhaka"),
+    ("Belarus Standard Time", "Europe/Minsk"),
+    ("Bougainville Standard Time", "Pacific/Bougainville"),
+    ("Canada Central Standard Time", "America/Regina"),
+    ("Cape Verde Standard Time", "Atlantic/Cape_Verde"),
+    ("Caucasus Standard Time", "Asia/Yerevan"),
+    ("Cen. Australia Standard Time", "Australia/Adelaide"),
+    ("Central America Standard Time", "America/Guatemala"),
+    ("Central Asia Standard Time", "Asia/Bishkek"),
+    ("Central Brazilian Standard Time", "America/Cuiaba"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("Central Pacific Standard Time", "Pacific/Guadalcanal"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Central Standard Time (Mexico)", "America/Mexico_City"),
+    ("Chatham Islands Standard Time", "Pacific/Chatham"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("Cuba Standard Time", "America/Havana"),
+    ("Dateline Standard Time", "Etc/GMT+12"),
+    ("E. Africa Standard Time", "Africa/Nairobi"),
+    ("E. Australia Standard Time", "Australia/Brisbane"),
+    ("E. Europe Standard Time", "Europe/Chisinau"),
+    ("E. South America Standard Time", "America/Sao_Paulo"),
+    ("Easter Island Standard Time", "Pacific/Easter"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Eastern Standard Time (Mexico)", "America/Cancun"),
+    ("Egypt Standard Time", "Africa/Cairo"),
+    ("Ekaterinburg Standard Time", "Asia/Yekaterinburg"),
+    ("FLE Standard Time", "Europe/Kiev"),
+    ("Fiji Standard Time", "Pacific/Fiji"),
+    ("GMT Standard Time", "Europe/London"),
+    ("GTB Standard Time", "Europe/Bucharest"),
+    ("Georgian Standard Time", "Asia/Tbilisi"),
+    ("Greenland Standard Time", "America/Godthab"),
+    ("Greenwich Standard Time", "Atlantic/Reykjavik"),
+    ("Haiti Standard Time", "America/Port-au-Prince"),
+    ("Hawaiian Standard Time", "Pacific/Honolulu"),
+    ("India Standard Time", "Asia/Calcutta"),
+    ("Iran Standard Time", "Asia/Tehran"),
+    ("Israel Standard Time", "Asia/Jerusalem"),
+    ("Jordan Standard Time", "Asia/Amman"),
+    ("Kaliningrad Standard Time", "Europe/Kaliningrad"),
+    ("Kamchatka Standard Time", "Asia/Kamchatka"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("Libya Standard Time", "Africa/Tripoli"),
+    ("Line Islands Standard Time", "Pacific/Kiritimati"),
+    ("Lord Howe Standard Time", "Australia/Lord_Howe"),
+    ("Magadan Standard Time", "Asia/Magadan"),
+    ("Magallanes Standard Time", "America/Punta_Arenas"),
+    ("Marquesas Standard Time", "Pacific/Marquesas"),
+    ("Mauritius Standard Time", "Indian/Mauritius"),
+    ("Mid-Atlantic Standard Time", "Etc/GMT+2"),
+    ("Middle East Standard Time", "Asia/Beirut"),
+    ("Montevideo Standard Time", "America/Montevideo"),
+    ("Morocco Standard Time", "Africa/Casablanca"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Mountain Standard Time (Mexico)", "America/Mazatlan"),
+    ("Myanmar Standard Time", "Asia/Rangoon"),
+    ("N. Central Asia Standard Time", "Asia/Novosibirsk"),
+    ("Namibia Standard Time", "Africa/Windhoek"),
+    ("Nepal Standard Time", "Asia/Katmandu"),
+    ("New Zealand Standard Time", "Pacific/Auckland"),
+    ("Newfoundland Standard Time", "America/St_Johns"),
+    ("Norfolk Standard Time", "Pacific/Norfolk"),
+    ("North Asia East Standard Time", "Asia/Irkutsk"),
+    ("North Asia Standard Time", "Asia/Krasnoyarsk"),
+    ("North Korea Standard Time", "Asia/Pyongyang"),
+    ("Omsk Standard Time", "Asia/Omsk"),
+    ("Pacific SA Standard Time", "America/Santiago"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("Pacific Standard Time (Mexico)", "America/Tijuana"),
+    ("Pakistan Standard Time", "Asia/Karachi"),
+    ("Paraguay Standard Time", "America/Asuncion"),
+    ("Qyzylorda Standard Time", "Asia/Qyzylorda"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("Russia Time Zone 10", "Asia/Srednekolymsk"),
+    ("Russia Time Zone 11", "Asia/Kamchatka"),
+    ("Russia Time Zone 3", "Europe/Samara"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("SA Eastern Standard Time", "America/Cayenne"),
+    ("SA Pacific Standard Time", "America/Bogota"),
+    ("SA Western Standard Time", "America/La_Paz"),
+    ("SE Asia Standard Time", "Asia/Bangkok"),
+    ("Saint Pierre Standard Time", "America/Miquelon"),
+    ("Sakhalin Standard Time", "Asia/Sakhalin"),
+    ("Samoa Standard Time", "Pacific/Apia"),
+    ("Sao Tome Standard Time", "Africa/Sao_Tome"),
+    ("Saratov Standard Time", "Europe/Saratov"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("South Africa Standard Time", "Africa/Johannesburg"),
+    ("South Sudan Standard Time", "Africa/Juba"),
+    ("Sri Lanka Standard Time", "Asia/Colombo"),
+    ("Sudan Standard Time", "Africa/Khartoum"),
+    ("Syria Standard Time", "Asia/Damascus"),
+    ("Taipei Standard Time", "Asia/Taipei"),
+    ("Tasmania Standard Time", "Australia/Hobart"),
+    ("Tocantins Standard Time", "America/Araguaina"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("Tomsk Standard Time", "Asia/Tomsk"),
+    ("Tonga Standard Time", "Pacific/Tongatapu"),
+    ("Transbaikal Standard Time", "Asia/Chita"),
+    ("Turkey Standard Time", "Europe/Istanbul"),
+    ("Turks And Caicos Standard Time", "America/Grand_Turk"),
+    ("US Eastern Standard Time", "America/Indianapolis"),
+    ("US Mountain Standard Time", "America/Phoenix"),
+    ("UTC", "Etc/UTC"),
+    ("UTC+12", "Etc/GMT-12"),
+    ("UTC+13", "Etc/GMT-13"),
+    ("UTC-02", "Etc/GMT+2"),
+    ("UTC-08", "Etc/GMT+8"),
+    ("UTC-09", "Etc/GMT+9"),
+    ("UTC-11", "Etc/GMT+11"),
+    ("Ulaanbaatar Standard Time", "Asia/Ulaanbaatar"),
+    ("Venezuela Standard Time", "America/Caracas"),
+    ("Vladivostok Standard Time", "Asia/Vladivostok"),
+    ("Volgograd Standard Time", "Europe/Volgograd"),
+    ("W. Australia Standard Time", "Australia/Perth"),
+    ("W. Central Africa Standard Time", "Africa/Lagos"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("W. Mongolia Standard Time", "Asia/Hovd"),
+    ("West Asia Standard Time", "Asia/Tashkent"),
+    ("West Bank Standard Time", "Asia/Hebron"),
+    ("West Pacific Standard Time", "Pacific/Port_Moresby"),
+    ("Yakutsk Standard Time", "Asia/Yakutsk"),
+    ("Yukon Standard Time", "America/Whitehorse"),
+];
+
+/**
+ * Areas of the IANA time zone names.
+ */
+const AREAS: &[&str] = &[
+    "Africa",
+    "America",
+    "Antarctica",
+    "Arctic",
+    "Asia",
+    "Atlantic",
+    "Australia",
+    "Europe",
+    "Etc",
+    "Indian",
+    "Pacific",
+];
+
+/**
+ * Offsets of a time zone, as needed to convert date-times.
+ *
+ * It’s implemented for every [`chrono::TimeZone`], like `chrono_tz::Tz`.
+ */
+pub trait Zone: std::fmt::Debug + Send + Sync {
+    fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset>;
+    fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset;
+}
+
+impl<Tz> Zone for Tz
+where
+    Tz: chrono::TimeZone + std::fmt::Debug + Send + Sync,
+{
+    fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        use chrono::Offset as _;
+
+        self.offset_from_local_datetime(local).map(|x| x.fix())
+    }
+
+    fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset {
+        use chrono::Offset as _;
+
+        self.offset_from_utc_datetime(utc).fix()
+    }
+}
+
+/**
+ * A time zone defined by a `VTIMEZONE` component.
+ *
+ * The `STANDARD` and `DAYLIGHT` observances are expanded with their `DTSTART`, `RDATE` and
+ * yearly `RRULE`, which is what time zone definitions use.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.6.5>
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timezone {
+    tzid: String,
+    observances: Vec<Observance>,
+}
+
+impl Timezone {
+    pub fn tzid(&self) -> &str {
+        &self.tzid
+    }
+
+    pub(crate) fn from_component(component: &Component) -> crate::Result<Self> {
+        let tzid = component
+            .property("TZID")
+            .map(|x| x.value.clone())
+            .ok_or_else(|| crate::Error::Timezone("missing TZID".to_string()))?;
+
+        let observances = component
+            .components
+            .iter()
+            .filter(|x| x.name == "STANDARD" || x.name == "DAYLIGHT")
+            .map(Observance::from_component)
+            .collect::<crate::Result<Vec<_>>>()
+            .map_err(|error| match error {
+                crate::Error::Timezone(message) => {
+                    crate::Error::Timezone(format!("{tzid}: {message}"))
+                }
+                error => error,
+            })?;
+
+        if observances.is_empty() {
+            return Err(crate::Error::Timezone(format!(
+                "{tzid}: missing STANDARD or DAYLIGHT"
+            )));
+        }
+
+        Ok(Self { tzid, observances })
+    }
+}
+
+impl std::str::FromStr for Timezone {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match crate::component::parse(s) {
+            Some(component) if component.name == "VTIMEZONE" => Self::from_component(&component),
+            _ => Err(crate::Error::Timezone("expected a VTIMEZONE".to_string())),
+        }
+    }
+}
+
+impl Zone for Timezone {
+    fn offset_from_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        let mut offsets = self
+            .observances
+            .iter()
+            .flat_map(|x| [x.from, x.to])
+            .filter(|offset| self.offset_from_utc(&(*local - delta(*offset))) == *offset)
+            .collect::<Vec<_>>();
+
+        // The earliest instant has the greatest offset
+        offsets.sort_by_key(|x| std::cmp::Reverse(x.local_minus_utc()));
+        offsets.dedup();
+
+        match offsets.as_slice() {
+            [] => LocalResult::None,
+            [offset] => LocalResult::Single(*offset),
+            [earliest, .., latest] => LocalResult::Ambiguous(*earliest, *latest),
+        }
+    }
+
+    fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset {
+        let last = self
+            .observances
+            .iter()
+            .filter_map(|x| Some((x.last_onset(*utc)?, x.to)))
+            .max_by_key(|(onset, _)| *onset);
+
+        match last {
+            Some((_, offset)) => offset,
+            // Before the first onset, the offset is the one the time zone switched from
+            None => self
+                .observances
+                .iter()
+                .min_by_key(|x| x.start)
+                .map_or(FixedOffset::east_opt(0).unwrap(), |x| x.from),
+        }
+    }
+}
+
+/**
+ * A `STANDARD` or `DAYLIGHT` sub-component.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Observance {
+    /** Local time of the first onset, in the `from` offset. */
+    start: NaiveDateTime,
+    from: FixedOffset,
+    to: FixedOffset,
+    rule: Option<Rule>,
+    dates: Vec<NaiveDateTime>,
+}
+
+impl Observance {
+    fn from_component(component: &Component) -> crate::Result<Self> {
+        let property = |name: &str| {
+            component
+                .property(name)
+                .ok_or_else(|| crate::Error::Timezone(format!("missing {name}")))
+        };
+
+        let start = parse_local(&property("DTSTART")?.value)?;
+        let rule = component
+            .property("RRULE")
+            .map(
+                |x| match Rule::parse(&x.value, &DateTime::Floating(start)) {
+                    // The onsets are looked for year by year
+                    Ok(rule) if rule.freq == Frequency::Yearly => Ok(rule),
+                    Ok(_) => Err(crate::Error::Timezone(format!(
+                        "unsupported RRULE {}",
+                        x.value
+                    ))),
+                    Err(crate::Error::Recurrence(message)) => Err(crate::Error::Timezone(message)),
+                    Err(error) => Err(error),
+                },
+            )
+            .transpose()?;
+        let dates = component
+            .properties
+            .iter()
+            .filter(|x| x.name == "RDATE")
+            .flat_map(|x| x.value.split(','))
+            .map(|x| parse_local(x.split('/').next().unwrap_or(x)))
+            .collect::<crate::Result<_>>()?;
+
+        Ok(Self {
+            start,
+            from: parse_offset(&property("TZOFFSETFROM")?.value)?,
+            to: parse_offset(&property("TZOFFSETTO")?.value)?,
+            rule,
+            dates,
+        })
+    }
+
+    /**
+     * The last onset at or before `utc`, in UTC.
+     */
+    fn last_onset(&self, utc: NaiveDateTime) -> Option<NaiveDateTime> {
+        use chrono::Datelike as _;
+
+        let to_utc = |local: NaiveDateTime| local - delta(self.from);
+        let until = utc.year() + LOOKAHEAD;
+
+        let occurrences = match &self.rule {
+            Some(rule) => self.occurrences(rule, until),
+            None => vec![self.start],
+        };
+
+        occurrences
+            .into_iter()
+            .chain(self.dates.iter().copied())
+            .map(to_utc)
+            .filter(|x| *x <= utc)
+            .max()
+    }
+
+    /**
+     * Local times of the onsets of `rule`, up to the end of the `until` year.
+     */
+    fn occurrences(&self, rule: &Rule, until: i32) -> Vec<NaiveDateTime> {
+        use chrono::Datelike as _;
+
+        let mut occurrences = Vec::new();
+
+        // The year limit stops the expansion long before it could fail
+        let _ = rule.occurrences(self.start, None, |local| {
+            let after_until = match &rule.until {
+                // The onsets are in the offset the observance switches from
+                Some(DateTime::Utc(until)) => local - delta(self.from) > *until,
+                Some(DateTime::Date(until)) => local.date() > *until,
+                Some(until) => local > until.naive(),
+                None => false,
+            };
+
+            if local.year() > until
+                || after_until
+                || rule.count.is_some_and(|x| occurrences.len() >= x)
+            {
+                return Ok(ControlFlow::Break(()));
+            }
+
+            occurrences.push(local);
+
+            Ok(ControlFlow::Continue(()))
+        });
+
+        occurrences
+    }
+}
+
+type Fallback = dyn Fn(&str) -> Option<Arc<dyn Zone>> + Send + Sync;
+
+/**
+ * Resolves the `TZID` of the date-times.
+ *
+ * The `VTIMEZONE` definitions come first, then the UTC aliases, then the fallback called with the
+ * IANA name of the time zone (see [`iana_name`]). The fallback is the `chrono-tz` database when
+ * the feature is enabled.
+ */
+#[derive(Clone)]
+pub struct Resolver {
+    timezones: BTreeMap<String, Arc<dyn Zone>>,
+    fallback: Option<Arc<Fallback>>,
+    floating: Arc<dyn Zone>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Adds a `VTIMEZONE` definition, replacing the one with the same `TZID`.
+     */
+    pub fn append(mut self, timezone: Timezone) -> Self {
+        self.timezones
+            .insert(timezone.tzid.clone(), Arc::new(timezone));
+
+        self
+    }
+
+    /**
+     * Resolves the time zones without a `VTIMEZONE` definition, typically from an IANA database.
+     */
+    pub fn fallback<F, Z>(mut self, fallback: F) -> Self
+    where
+        F: Fn(&str) -> Option<Z> + Send + Sync + 'static,
+        Z: Zone + 'static,
+    {
+        self.fallback = Some(Arc::new(move |name| {
+            fallback(name).map(|x| Arc::new(x) as Arc<dyn Zone>)
+        }));
+
+        self
+    }
+
+    /**
+     * Time zone of the dates and floating date-times, the local one by default.
+     */
+    pub fn floating<Z: Zone + 'static>(mut self, zone: Z) -> Self {
+        self.floating = Arc::new(zone);
+
+        self
+    }
+
+    pub fn resolve(&self, tzid: &str) -> Option<Arc<dyn Zone>> {
+        if let Some(zone) = self.timezones.get(tzid) {
+            return Some(zone.clone());
+        }
+
+        let name = iana_name(tzid).unwrap_or(tzid);
+
+        if matches!(name, "UTC" | "Etc/UTC" | "GMT" | "Etc/GMT" | "Z") {
+            return Some(Arc::new(Utc));
+        }
+
+        self.fallback.as_ref().and_then(|fallback| fallback(name))
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        let resolver = Self {
+            timezones: BTreeMap::new(),
+            fallback: None,
+            floating: Arc::new(chrono::Local),
+        };
+
+        #[cfg(feature = "chrono-tz")]
+        let resolver = resolver.fallback(|name| name.parse::<chrono_tz::Tz>().ok());
+
+        resolver
+    }
+}
+
+impl std::fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver")
+            .field("timezones", &self.timezones.keys())
+            .field("fallback", &self.fallback.is_some())
+            .field("floating", &self.floating)
+            .finish()
+    }
+}
+
+/**
+ * The IANA name of a `TZID`.
+ *
+ * Vendor prefixes, like `/mozilla.org/20050126_1/`, are removed and the Windows names are
+ * translated.
+ */
+pub fn iana_name(tzid: &str) -> Option<&str> {
+    if let Some((_, iana)) = WINDOWS.iter().find(|(windows, _)| *windows == tzid) {
+        return Some(iana);
+    }
+
+    let mut offset = 0;
+
+    for segment in tzid.split('/') {
+        if AREAS.contains(&segment) {
+            return Some(&tzid[offset..]);
+        }
+
+        offset += segment.len() + 1;
+    }
+
+    None
+}
+
+/**
+ * A date or date-time value, with its time zone.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.5>
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DateTime {
+    Date(NaiveDate),
+    Floating(NaiveDateTime),
+    Utc(NaiveDateTime),
+    Zoned {
+        datetime: NaiveDateTime,
+        tzid: String,
+    },
+}
+
+impl DateTime {
+    /**
+     * Parses a property value and its `TZID` parameter.
+     */
+    pub fn parse(value: &str, tzid: Option<&str>) -> crate::Result<Self> {
+        let value = value.trim();
+
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+            return Ok(Self::Date(date));
+        }
+
+        let datetime = parse_local(value)?;
+
+        let datetime = if value.ends_with(['Z', 'z']) {
+            Self::Utc(datetime)
+        } else if let Some(tzid) = tzid {
+            Self::Zoned {
+                datetime,
+                tzid: tzid.to_string(),
+            }
+        } else {
+            Self::Floating(datetime)
+        };
+
+        Ok(datetime)
+    }
+
+    fn from_property(property: &Property) -> Option<Self> {
+        Self::parse(&property.value, property.param("TZID")).ok()
+    }
+
+    /**
+     * The written date-time, without its time zone.
+     */
+    pub fn naive(&self) -> NaiveDateTime {
+        match self {
+            Self::Date(date) => date.and_time(chrono::NaiveTime::MIN),
+            Self::Floating(datetime) | Self::Utc(datetime) => *datetime,
+            Self::Zoned { datetime, .. } => *datetime,
+        }
+    }
+
+    /**
+     * The instant of this date-time.
+     *
+     * Dates start at midnight in the floating time zone of `resolver`. A time skipped by a
+     * transition uses the offset before it, a repeated time is the first occurrence.
+     */
+    pub fn to_utc(&self, resolver: &Resolver) -> crate::Result<chrono::DateTime<Utc>> {
+        let zone = match self {
+            Self::Utc(datetime) => return Ok(datetime.and_utc()),
+            Self::Date(_) | Self::Floating(_) => resolver.floating.clone(),
+            Self::Zoned { tzid, .. } => resolver
+                .resolve(tzid)
+                .ok_or_else(|| crate::Error::Timezone(format!("unknown TZID {tzid}")))?,
+        };
+
+        let local = self.naive();
+        let offset = match zone.offset_from_local(&local) {
+            LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset,
+            LocalResult::None => zone.offset_from_utc(&(local - TimeDelta::days(1))),
+        };
+
+        Ok((local - delta(offset)).and_utc())
+    }
+
+    /**
+     * The instant of this date-time in the `tz` time zone.
+     */
+    pub fn to_tz<Tz: chrono::TimeZone>(
+        &self,
+        resolver: &Resolver,
+        tz: &Tz,
+    ) -> crate::Result<chrono::DateTime<Tz>> {
+        self.to_utc(resolver).map(|x| x.with_timezone(tz))
+    }
+}
+
+/**
+ * A calendar object read with the `TZID` parameters, which `ikal` drops.
+ */
+#[derive(Clone, Debug)]
+pub struct Calendar {
+    resolver: Resolver,
+    components: Vec<Component>,
+}
+
+impl Calendar {
+    /**
+     * Parses a calendar object, adding its `VTIMEZONE` components to `resolver`.
+     */
+    pub fn new(data: &str, mut resolver: Resolver) -> crate::Result<Self> {
+        let calendar = crate::component::parse(data)
+            .filter(|x| x.name == "VCALENDAR")
+            .ok_or_else(|| crate::Error::Misc("Invalid calendar data".to_string()))?;
+
+        let (timezones, components) = calendar
+            .components
+            .into_iter()
+            .partition::<Vec<_>, _>(|x| x.name == "VTIMEZONE");
+
+        for timezone in &timezones {
+            resolver = resolver.append(Timezone::from_component(timezone)?);
+        }
+
+        Ok(Self {
+            resolver,
+            components,
+        })
+    }
+
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+
+    /**
+     * The `name` property of the component with this `UID` and `RECURRENCE-ID`, compared by
+     * their written value.
+     */
+    pub fn date_time(
+        &self,
+        uid: &str,
+        recurrence_id: Option<NaiveDateTime>,
+        name: &str,
+    ) -> Option<DateTime> {
+        self.components
+            .iter()
+            .filter(|x| x.property("UID").is_some_and(|x| x.value == uid))
+            .find(|x| {
+                x.property("RECURRENCE-ID")
+                    .and_then(DateTime::from_property)
+                    .map(|x| x.naive())
+                    == recurrence_id
+            })?
+            .property(name)
+            .and_then(DateTime::from_property)
+    }
+}
+
+impl std::str::FromStr for Calendar {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::new(s, Resolver::default())
+    }
+}
+
+/**
+ * The date-times of events and tasks, with their time zone.
+ */
+pub trait DateTimes {
+    fn start(&self, calendar: &Calendar) -> Option<DateTime>;
+
+    /**
+     * The `DTEND` of an event, the `DUE` of a task.
+     */
+    fn end(&self, calendar: &Calendar) -> Option<DateTime>;
+}
+
+impl DateTimes for ikal::VEvent {
+    fn start(&self, calendar: &Calendar) -> Option<DateTime> {
+        let recurrence_id = recurrence_id(
+            self.recurid.as_ref(),
+            self.iana_prop
+                .get("RECURRENCE-ID")
+                .map(|x| x.value.as_str()),
+        );
+
+        calendar.date_time(&self.uid, recurrence_id, "DTSTART")
+    }
+
+    fn end(&self, calendar: &Calendar) -> Option<DateTime> {
+        let recurrence_id = recurrence_id(
+            self.recurid.as_ref(),
+            self.iana_prop
+                .get("RECURRENCE-ID")
+                .map(|x| x.value.as_str()),
+        );
+
+        calendar.date_time(&self.uid, recurrence_id, "DTEND")
+    }
+}
+
+impl DateTimes for ikal::VTodo {
+    fn start(&self, calendar: &Calendar) -> Option<DateTime> {
+        let recurrence_id = recurrence_id(
+            self.recurid.as_ref(),
+            self.iana_prop
+                .get("RECURRENCE-ID")
+                .map(|x| x.value.as_str()),
+        );
+
+        calendar.date_time(&self.uid, recurrence_id, "DTSTART")
+    }
+
+    fn end(&self, calendar: &Calendar) -> Option<DateTime> {
+        let recurrence_id = recurrence_id(
+            self.recurid.as_ref(),
+            self.iana_prop
+                .get("RECURRENCE-ID")
+                .map(|x| x.value.as_str()),
+        );
+
+        calendar.date_time(&self.uid, recurrence_id, "DUE")
+    }
+}
+
+/**
+ * The written `RECURRENCE-ID` of an `ikal` component, which keeps it as an unknown property.
+ */
+fn recurrence_id(recurid: Option<&ikal::Date>, unknown: Option<&str>) -> Option<NaiveDateTime> {
+    if let Some(value) = unknown {
+        return DateTime::parse(value, None).ok().map(|x| x.naive());
+    }
+
+    recurid.map(|date| match date {
+        ikal::Date::Date(date) => date.and_time(chrono::NaiveTime::MIN),
+        ikal::Date::DateTime(ikal::DateTime::Naive(datetime)) => *datetime,
+        // `ikal` reads UTC date-times as local ones
+        ikal::Date::DateTime(ikal::DateTime::Local(datetime)) => datetime.naive_utc(),
+    })
+}
+
+fn delta(offset: FixedOffset) -> TimeDelta {
+    TimeDelta::seconds(offset.local_minus_utc().into())
+}
+
+fn parse_local(value: &str) -> crate::Result<NaiveDateTime> {
+    let value = value.trim();
+    let value = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y%m%d").map(|x| x.and_time(chrono::NaiveTime::MIN))
+        })
+        .map_err(|_| crate::Error::Timezone(format!("invalid date-time {value}")))
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.14>
+ */
+fn parse_offset(value: &str) -> crate::Result<FixedOffset> {
+    let invalid = || crate::Error::Timezone(format!("invalid UTC offset {value}"));
+
+    let (sign, digits) = match value.trim().split_at_checked(1) {
+        Some(("+", digits)) => (1, digits),
+        Some(("-", digits)) => (-1, digits),
+        _ => return Err(invalid()),
+    };
+
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|x| x.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let seconds = digits
+        .as_bytes()
+        .chunks(2)
+        .zip([3_600, 60, 1])
+        .map(|(x, unit)| i32::from((x[0] - b'0') * 10 + x[1] - b'0') * unit)
+        .sum::<i32>();
+
+    FixedOffset::east_opt(sign * seconds).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Zone as _;
+    use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime};
+
+    const PARIS: &str = "BEGIN:VTIMEZONE\r
+TZID:/mozilla.org/20050126_1/Europe/Paris\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+DTSTART:19810329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+TZNAME:CEST\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19961027T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+TZNAME:CET\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+    const NEW_YORK: &str = "BEGIN:VTIMEZONE\r
+TZID:America/New_York\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:-0500\r
+TZOFFSETTO:-0400\r
+DTSTART:19870405T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=4;BYDAY=1SU;UNTIL=20060402T070000Z\r
+END:DAYLIGHT\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:-0500\r
+TZOFFSETTO:-0400\r
+DTSTART:20070311T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:-0400\r
+TZOFFSETTO:-0500\r
+DTSTART:19671029T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU;UNTIL=20061029T060000Z\r
+END:STANDARD\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:-0400\r
+TZOFFSETTO:-0500\r
+DTSTART:20071104T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+    fn datetime(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").unwrap()
+    }
+
+    fn offset(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3_600).unwrap()
+    }
+
+    #[test]
+    fn timezone() -> crate::Result {
+        let paris = PARIS.parse::<super::Timezone>()?;
+
+        assert_eq!(paris.tzid(), "/mozilla.org/20050126_1/Europe/Paris");
+        assert_eq!(
+            paris.offset_from_utc(&datetime("20240115T120000")),
+            offset(1)
+        );
+        assert_eq!(
+            paris.offset_from_utc(&datetime("20240701T120000")),
+            offset(2)
+        );
+        assert_eq!(
+            paris.offset_from_utc(&datetime("20240331T005959")),
+            offset(1)
+        );
+        assert_eq!(
+            paris.offset_from_utc(&datetime("20240331T010000")),
+            offset(2)
+        );
+        assert_eq!(
+            paris.offset_from_utc(&datetime("20241027T010000")),
+            offset(1)
+        );
+        assert_eq!(
+            paris.offset_from_utc(&datetime("19700101T000000")),
+            offset(1)
+        );
+
+        assert_eq!(
+            paris.offset_from_local(&datetime("20240331T023000")),
+            LocalResult::None
+        );
+        assert_eq!(
+            paris.offset_from_local(&datetime("20241027T023000")),
+            LocalResult::Ambiguous(offset(2), offset(1))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> crate::Result {
+        let new_york = NEW_YORK.parse::<super::Timezone>()?;
+
+        // The first Sunday of April until 2006, the second Sunday of March since 2007
+        assert_eq!(
+            new_york.offset_from_utc(&datetime("20060320T120000")),
+            offset(-5)
+        );
+        assert_eq!(
+            new_york.offset_from_utc(&datetime("20060410T120000")),
+            offset(-4)
+        );
+        assert_eq!(
+            new_york.offset_from_utc(&datetime("20070320T120000")),
+            offset(-4)
+        );
+        assert_eq!(
+            new_york.offset_from_utc(&datetime("20061101T120000")),
+            offset(-5)
+        );
+        assert_eq!(
+            new_york.offset_from_utc(&datetime("20071101T120000")),
+            offset(-4)
+        );
+
+        let rdate = "BEGIN:VTIMEZONE\r
+TZID:Custom\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0000\r
+TZOFFSETTO:+0530\r
+DTSTART:20200101T000000\r
+RDATE:20220601T000000\r
+END:STANDARD\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0530\r
+TZOFFSETTO:+0600\r
+DTSTART:20210101T000000\r
+END:DAYLIGHT\r
+END:VTIMEZONE\r
+"
+        .parse::<super::Timezone>()?;
+
+        assert_eq!(
+            rdate.offset_from_utc(&datetime("20210601T000000")),
+            FixedOffset::east_opt(6 * 3_600).unwrap()
+        );
+        assert_eq!(
+            rdate.offset_from_utc(&datetime("20220701T000000")),
+            FixedOffset::east_opt(5 * 3_600 + 1_800).unwrap()
+        );
+
+        assert!(
+            "BEGIN:VTIMEZONE\r\nTZID:Weekly\r\nBEGIN:STANDARD\r\nTZOFFSETFROM:+0000\r\nTZOFFSETTO:+0100\r\nDTSTART:20200101T000000\r\nRRULE:FREQ=WEEKLY\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n"
+                .parse::<super::Timezone>()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolver() {
+        let resolver = super::Resolver::new().fallback(|name| {
+            (name == "Asia/Tokyo").then(|| FixedOffset::east_opt(9 * 3_600).unwrap())
+        });
+
+        assert!(resolver.resolve("Tokyo Standard Time").is_some());
+        assert!(
+            resolver
+                .resolve("/citadel.org/20190914_1/Asia/Tokyo")
+                .is_some()
+        );
+        assert!(resolver.resolve("Etc/UTC").is_some());
+        assert!(resolver.resolve("Europe/Paris").is_none());
+
+        assert_eq!(
+            super::iana_name("Romance Standard Time"),
+            Some("Europe/Paris")
+        );
+        assert_eq!(
+            super::iana_name("/softwarestudio.org/Olson_20011030_5/America/Argentina/Cordoba"),
+            Some("America/Argentina/Cordoba")
+        );
+        assert_eq!(super::iana_name("Custom"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono-tz")]
+    fn chrono_tz() {
+        for (windows, iana) in super::WINDOWS {
+            assert!(iana.parse::<chrono_tz::Tz>().is_ok(), "{windows}: {iana}");
+        }
+
+        let resolver = super::Resolver::default();
+        let zone = resolver.resolve("W. Europe Standard Time").unwrap();
+
+        assert_eq!(
+            zone.offset_from_utc(&datetime("20240701T120000")),
+            offset(2)
+        );
+        assert!(resolver.resolve("Custom").is_none());
+    }
+
+    #[test]
+    fn date_time() -> crate::Result {
+        use super::DateTimes as _;
+
+        let data = format!(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:kaldav\r
+{PARIS}BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20240101T120000Z\r
+DTSTART;TZID=/mozilla.org/20050126_1/Europe/Paris:20240331T023000\r
+DTEND;TZID=/mozilla.org/20050126_1/Europe/Paris:20240701T100000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20240101T120000Z\r
+RECURRENCE-ID:20240601T080000Z\r
+DTSTART:20240601T090000Z\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:2\r
+DTSTAMP:20240101T120000Z\r
+DTSTART;VALUE=DATE:20240105\r
+DUE;TZID=Asia/Tokyo:20240105T090000\r
+END:VTODO\r
+END:VCALENDAR\r
+"
+        );
+
+        let resolver = super::Resolver::new().floating(FixedOffset::east_opt(3_600).unwrap());
+        let calendar = super::Calendar::new(&data, resolver)?;
+        let resolver = calendar.resolver();
+        let utc = |value: &str| datetime(value).and_utc();
+
+        let vcalendar = ikal::VCalendar::try_from(data.as_str())?;
+        let event = &vcalendar.events[0];
+
+        // Skipped by the transition, read with the offset before it
+        assert_eq!(
+            event.start(&calendar).unwrap().to_utc(resolver)?,
+            utc("20240331T013000")
+        );
+        assert_eq!(
+            event.end(&calendar).unwrap().to_utc(resolver)?,
+            utc("20240701T080000")
+        );
+        assert_eq!(
+            event
+                .end(&calendar)
+                .unwrap()
+                .to_tz(resolver, &offset(-4))?
+                .naive_local(),
+            datetime("20240701T040000")
+        );
+
+        let instance = &vcalendar.events[1];
+        assert_eq!(
+            instance.start(&calendar),
+            Some(super::DateTime::Utc(datetime("20240601T090000")))
+        );
+
+        let todo = &vcalendar.todo[0];
+        assert_eq!(
+            todo.start(&calendar),
+            Some(super::DateTime::Date(
+                NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()
+            ))
+        );
+        assert_eq!(
+            todo.start(&calendar).unwrap().to_utc(resolver)?,
+            utc("20240104T230000")
+        );
+        assert_eq!(
+            todo.end(&calendar).unwrap().to_utc(resolver).is_ok(),
+            cfg!(feature = "chrono-tz")
+        );
+
+        Ok(())
+    }
+}
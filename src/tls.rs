@@ -0,0 +1,850 @@
+/*!
+ * HTTPS requests presenting a client certificate.
+ *
+ * attohttpc can’t authenticate with a client certificate: its TLS connector isn’t configurable
+ * beyond root certificates. The requests of a session configured with an identity are sent over
+ * a TLS connection of their own, as HTTP/1.1 with `Connection: close`, through an HTTP proxy
+ * tunnel if one is configured. Bodies are requested without content coding.
+ *
+ * Redirections are followed like attohttpc does, except that the credentials, and the custom
+ * headers that may carry some, aren’t sent to another origin.
+ */
+
+use std::io::{Read as _, Write as _};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "native-tls")]
+pub(crate) type Identity = native_tls::Identity;
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+#[derive(Debug)]
+pub(crate) struct Identity {
+    pub chain: Vec<rustls_pki_types::CertificateDer<'static>>,
+    pub key: rustls_pki_types::PrivateKeyDer<'static>,
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+impl Clone for Identity {
+    fn clone(&self) -> Self {
+        Self {
+            chain: self.chain.clone(),
+            key: self.key.clone_key(),
+        }
+    }
+}
+
+/**
+ * Sends `request` authenticated by `identity`, following redirections.
+ */
+pub(crate) fn send(
+    config: &crate::session::Config,
+    identity: &Identity,
+    request: &crate::transport::Request,
+) -> crate::Result<crate::transport::Response> {
+    let deadline = config.timeout.map(|x| Instant::now() + x);
+    let mut url = parse(&request.href)?;
+    let mut method = request.method.clone();
+    let mut headers = request.headers.clone();
+    let mut body = request.body.clone();
+    let mut redirections = 0;
+
+    loop {
+        let response = exchange(
+            config,
+            identity,
+            &method,
+            &url,
+            &headers,
+            body.as_deref().unwrap_or_default(),
+            deadline,
+        )?;
+
+        let location = response
+            .headers
+            .get(attohttpc::header::LOCATION)
+            .and_then(|x| x.to_str().ok());
+
+        let (crate::Redirect::Limited(max), Some(location)) = (config.redirect, location) else {
+            return Ok(response);
+        };
+
+        if !response.status.is_redirection() {
+            return Ok(response);
+        }
+
+        if redirections == max {
+            return Err(crate::Error::Misc(format!(
+                "Too many redirections from {}",
+                request.href
+            )));
+        }
+
+        redirections += 1;
+        let next = url
+            .join(location)
+            .map_err(|error| crate::Error::Misc(error.to_string()))?;
+
+        if next.scheme() != "https" {
+            return Err(crate::Error::Misc(format!(
+                "Redirection to a non HTTPS URL: {next}"
+            )));
+        }
+
+        if next.origin() != url.origin() {
+            headers.retain(|(name, _)| {
+                !crate::trace::is_sensitive(name, &request.sensitive_headers)
+                    && !config.headers.contains_key(name.as_str())
+            });
+        }
+
+        url = next;
+
+        if response.status == attohttpc::StatusCode::SEE_OTHER {
+            method = "GET".to_string();
+            body = None;
+        }
+    }
+}
+
+fn parse(href: &str) -> crate::Result<url::Url> {
+    url::Url::parse(href).map_err(|error| crate::Error::Misc(format!("{href}: {error}")))
+}
+
+fn exchange(
+    config: &crate::session::Config,
+    identity: &Identity,
+    method: &str,
+    url: &url::Url,
+    headers: &[(String, String)],
+    body: &str,
+    deadline: Option<Instant>,
+) -> crate::Result<crate::transport::Response> {
+    let (host, port) = authority(url)?;
+    let proxy = config.proxy.as_ref().and_then(|x| x.for_url(url));
+
+    let stream = match proxy {
+        Some(proxy) => {
+            let (proxy_host, proxy_port) = authority(proxy)?;
+            let mut stream = connect(config, proxy_host, proxy_port, deadline)?;
+            tunnel(&mut stream, proxy, host, port)?;
+
+            stream
+        }
+        None => connect(config, host, port, deadline)?,
+    };
+    let mut stream = handshake(config, identity, host.trim_matches(['[', ']']), stream)?;
+
+    let mut head = format!(
+        "{method} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        &url[url::Position::BeforePath..url::Position::AfterQuery],
+        &url[url::Position::BeforeHost..url::Position::AfterPort],
+        body.len(),
+    );
+
+    for (name, value) in headers {
+        let name = attohttpc::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| crate::Error::Misc(format!("Invalid header name: {name}")))?;
+        attohttpc::header::HeaderValue::from_str(value)
+            .map_err(|_| crate::Error::Misc(format!("Invalid value for header {name}")))?;
+
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()?;
+
+    let mut raw = Vec::new();
+
+    match stream.read_to_end(&mut raw) {
+        Ok(_) => (),
+        // the server closed the connection without a TLS close notification
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof && !raw.is_empty() => (),
+        Err(error) => return Err(error.into()),
+    }
+
+    response(&raw)
+}
+
+fn authority(url: &url::Url) -> crate::Result<(&str, u16)> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| crate::Error::Misc(format!("Missing host: {url}")))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| crate::Error::Misc(format!("Missing port: {url}")))?;
+
+    Ok((host, port))
+}
+
+/**
+ * Asks the HTTP `proxy` to open a tunnel to `host`.
+ */
+fn tunnel(
+    stream: &mut std::net::TcpStream,
+    proxy: &url::Url,
+    host: &str,
+    port: u16,
+) -> crate::Result {
+    if proxy.scheme() != "http" {
+        return Err(crate::Error::Misc(format!(
+            "Unsupported proxy with a client certificate: {proxy}"
+        )));
+    }
+
+    let mut head = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+
+    if !proxy.username().is_empty() {
+        use base64::Engine as _;
+
+        let credentials = format!(
+            "{}:{}",
+            proxy.username(),
+            proxy.password().unwrap_or_default()
+        );
+        head.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        ));
+    }
+
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+
+    // the TLS handshake follows the response head, so it can’t be read ahead
+    let mut raw = Vec::new();
+    let mut byte = [0];
+
+    while !raw.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(crate::Error::Misc(format!(
+                "Proxy {proxy} closed the connection"
+            )));
+        }
+
+        raw.push(byte[0]);
+    }
+
+    let status = String::from_utf8_lossy(&raw)
+        .split(' ')
+        .nth(1)
+        .and_then(|x| attohttpc::StatusCode::from_bytes(x.as_bytes()).ok())
+        .ok_or_else(|| crate::Error::Misc("Invalid HTTP response".to_string()))?;
+
+    if !status.is_success() {
+        return Err(crate::Error::Misc(format!(
+            "Proxy {proxy} refused the tunnel to {host}:{port}: {status}"
+        )));
+    }
+
+    Ok(())
+}
+
+fn connect(
+    config: &crate::session::Config,
+    host: &str,
+    port: u16,
+    deadline: Option<Instant>,
+) -> crate::Result<std::net::TcpStream> {
+    use std::net::ToSocketAddrs as _;
+
+    let connect_timeout = shortest(config.connect_timeout, deadline)?;
+    let read_timeout = shortest(config.read_timeout, deadline)?;
+    let mut last_error = None;
+
+    for addr in (host.trim_matches(['[', ']']), port).to_socket_addrs()? {
+        let stream = match connect_timeout {
+            Some(timeout) => std::net::TcpStream::connect_timeout(&addr, timeout),
+            None => std::net::TcpStream::connect(addr),
+        };
+
+        match stream {
+            Ok(stream) => {
+                stream.set_read_timeout(read_timeout)?;
+                stream.set_write_timeout(read_timeout)?;
+
+                return Ok(stream);
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| std::io::Error::other(format!("{host}: no address found")))
+        .into())
+}
+
+/**
+ * The shortest of `timeout` and the time left before `deadline`.
+ */
+fn shortest(
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+) -> crate::Result<Option<Duration>> {
+    let Some(deadline) = deadline else {
+        return Ok(timeout);
+    };
+
+    let remaining = deadline.saturating_duration_since(Instant::now());
+
+    if remaining.is_zero() {
+        return Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into());
+    }
+
+    Ok(Some(timeout.map_or(remaining, |x| x.min(remaining))))
+}
+
+#[cfg(feature = "native-tls")]
+fn handshake(
+    config: &crate::session::Config,
+    identity: &Identity,
+    host: &str,
+    stream: std::net::TcpStream,
+) -> crate::Result<impl std::io::Read + std::io::Write> {
+    let mut builder = native_tls::TlsConnector::builder();
+    builder
+        .identity(identity.clone())
+        .danger_accept_invalid_certs(config.accept_invalid_certs)
+        .danger_accept_invalid_hostnames(config.accept_invalid_hostnames);
+
+    for certificate in &config.root_certificates {
+        builder.add_root_certificate(certificate.clone());
+    }
+
+    let connector = builder
+        .build()
+        .map_err(|error| crate::Error::Misc(error.to_string()))?;
+
+    connector
+        .connect(host, stream)
+        .map_err(|error| crate::Error::Misc(format!("TLS handshake with {host}: {error}")))
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+fn handshake(
+    config: &crate::session::Config,
+    identity: &Identity,
+    host: &str,
+    stream: std::net::TcpStream,
+) -> crate::Result<impl std::io::Read + std::io::Write> {
+    use std::sync::Arc;
+
+    let error = |error: rustls::Error| crate::Error::Misc(error.to_string());
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    for certificate in &config.root_certificates {
+        roots.add(certificate.clone()).map_err(error)?;
+    }
+
+    let builder = rustls::ClientConfig::builder();
+    let builder = if config.accept_invalid_certs || config.accept_invalid_hostnames {
+        let upstream = rustls::client::WebPkiServerVerifier::builder(Arc::new(roots))
+            .build()
+            .map_err(|error| crate::Error::Misc(error.to_string()))?;
+
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(Verifier {
+                upstream,
+                accept_invalid_certs: config.accept_invalid_certs,
+                accept_invalid_hostnames: config.accept_invalid_hostnames,
+            }))
+    } else {
+        builder.with_root_certificates(roots)
+    };
+
+    let client = builder
+        .with_client_auth_cert(identity.chain.clone(), identity.key.clone_key())
+        .map_err(error)?;
+    let name = rustls_pki_types::ServerName::try_from(host.to_string())
+        .map_err(|_| crate::Error::Misc(format!("Invalid server name: {host}")))?;
+    let connection = rustls::ClientConnection::new(Arc::new(client), name).map_err(error)?;
+
+    Ok(rustls::StreamOwned::new(connection, stream))
+}
+
+/**
+ * Verifies the server certificate, ignoring the errors allowed by the `danger_*` settings.
+ */
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+#[derive(Debug)]
+struct Verifier {
+    upstream: std::sync::Arc<rustls::client::WebPkiServerVerifier>,
+    accept_invalid_certs: bool,
+    accept_invalid_hostnames: bool,
+}
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+impl rustls::client::danger::ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls_pki_types::CertificateDer<'_>,
+        intermediates: &[rustls_pki_types::CertificateDer<'_>],
+        server_name: &rustls_pki_types::ServerName<'_>,
+        ocsp_response: &[u8],
+        now: rustls_pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        use rustls::CertificateError::*;
+
+        match self.upstream.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            Err(_) if self.accept_invalid_certs => {
+                Ok(rustls::client::danger::ServerCertVerified::assertion())
+            }
+            Err(rustls::Error::InvalidCertificate(
+                NotValidForName | NotValidForNameContext { .. },
+            )) if self.accept_invalid_hostnames => {
+                Ok(rustls::client::danger::ServerCertVerified::assertion())
+            }
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.upstream.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.upstream.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.upstream.supported_verify_schemes()
+    }
+}
+
+/**
+ * Parses a raw HTTP/1.1 response.
+ */
+fn response(raw: &[u8]) -> crate::Result<crate::transport::Response> {
+    let invalid = || crate::Error::Misc("Invalid HTTP response".to_string());
+
+    let mut rest = raw;
+
+    // skips the informational responses
+    let (status, headers, body) = loop {
+        let end = rest
+            .windows(4)
+            .position(|x| x == b"\r\n\r\n")
+            .ok_or_else(invalid)?;
+        let head = String::from_utf8_lossy(&rest[..end]).to_string();
+        let body = &rest[end + 4..];
+
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|x| x.split(' ').nth(1))
+            .and_then(|x| attohttpc::StatusCode::from_bytes(x.as_bytes()).ok())
+            .ok_or_else(invalid)?;
+
+        if status.is_informational() {
+            rest = body;
+            continue;
+        }
+
+        let mut headers = attohttpc::header::HeaderMap::new();
+
+        for line in lines {
+            let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+            let name = attohttpc::header::HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| invalid())?;
+            let value =
+                attohttpc::header::HeaderValue::from_str(value.trim()).map_err(|_| invalid())?;
+
+            headers.append(name, value);
+        }
+
+        break (status, headers, body);
+    };
+
+    let chunked = headers
+        .get(attohttpc::header::TRANSFER_ENCODING)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.to_ascii_lowercase().contains("chunked"));
+    let length = headers
+        .get(attohttpc::header::CONTENT_LENGTH)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.trim().parse::<usize>().ok());
+
+    let body = if chunked {
+        dechunk(body).ok_or_else(invalid)?
+    } else if let Some(length) = length {
+        body.get(..length).ok_or_else(invalid)?.to_vec()
+    } else {
+        body.to_vec()
+    };
+
+    Ok(crate::transport::Response {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/**
+ * Decodes a `chunked` transfer coding, ignoring the trailers.
+ */
+fn dechunk(mut raw: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+
+    loop {
+        let end = raw.windows(2).position(|x| x == b"\r\n")?;
+        let size = std::str::from_utf8(&raw[..end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        raw = &raw[end + 2..];
+
+        if size == 0 {
+            return Some(body);
+        }
+
+        body.extend_from_slice(raw.get(..size)?);
+        raw = raw.get(size + 2..)?;
+    }
+}
+
+#[cfg(all(test, feature = "native-tls"))]
+mod test {
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::x509::extension::{
+        BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+    };
+    use openssl::x509::{X509, X509NameBuilder};
+    use std::io::{BufRead as _, Read as _, Write as _};
+    use std::sync::{Arc, Mutex};
+
+    type Result<T = ()> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+    fn key() -> Result<PKey<Private>> {
+        let group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1)?;
+
+        Ok(PKey::from_ec_key(openssl::ec::EcKey::generate(&group)?)?)
+    }
+
+    /**
+     * A certificate for `cn`, a certificate authority when `issuer` is `None`.
+     */
+    fn certificate(
+        cn: &str,
+        serial: u32,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+    ) -> Result<X509> {
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", cn)?;
+        let name = name.build();
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        let serial = BigNum::from_u32(serial)?.to_asn1_integer()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(issuer.map_or(&name, |(x, _)| x.subject_name()))?;
+        builder.set_pubkey(key)?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        builder.set_not_before(&not_before)?;
+        let not_after = Asn1Time::days_from_now(1)?;
+        builder.set_not_after(&not_after)?;
+
+        match issuer {
+            None => {
+                builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+                builder.append_extension(KeyUsage::new().key_cert_sign().crl_sign().build()?)?;
+            }
+            Some((issuer, _)) => {
+                let san = SubjectAlternativeName::new()
+                    .dns(cn)
+                    .build(&builder.x509v3_context(Some(issuer), None))?;
+                builder.append_extension(san)?;
+                builder.append_extension(
+                    ExtendedKeyUsage::new()
+                        .server_auth()
+                        .client_auth()
+                        .build()?,
+                )?;
+            }
+        }
+
+        builder.sign(issuer.map_or(key, |(_, x)| x), MessageDigest::sha256())?;
+
+        Ok(builder.build())
+    }
+
+    /**
+     * A server requiring a certificate issued by `ca`, `handler` answers the request head with
+     * the common name of the client certificate.
+     */
+    fn server<F>(ca: &X509, ca_key: &PKey<Private>, handler: F) -> Result<String>
+    where
+        F: Fn(&str, &str) -> String + Send + 'static,
+    {
+        use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+
+        let key = key()?;
+        let certificate = certificate("localhost", 2, &key, Some((ca, ca_key)))?;
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+        acceptor.set_private_key(&key)?;
+        acceptor.set_certificate(&certificate)?;
+        acceptor.cert_store_mut().add_cert(ca.clone())?;
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = acceptor.build();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("https://localhost:{}/", listener.local_addr()?.port());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let Ok(stream) = acceptor.accept(stream) else {
+                    continue;
+                };
+                let mut stream = std::io::BufReader::new(stream);
+
+                let mut head = String::new();
+                let mut length = 0;
+                let mut line = String::new();
+                while stream.read_line(&mut line).is_ok_and(|x| x > 2) {
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap_or_default();
+                    }
+                    head.push_str(&line);
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).ok();
+
+                let stream = stream.get_mut();
+                let cn = stream
+                    .ssl()
+                    .peer_certificate()
+                    .and_then(|x| {
+                        x.subject_name()
+                            .entries()
+                            .next()
+                            .and_then(|x| x.data().to_string().ok())
+                    })
+                    .unwrap_or_default();
+
+                stream.write_all(handler(&head, &cn).as_bytes()).ok();
+                stream.shutdown().ok();
+            }
+        });
+
+        Ok(url)
+    }
+
+    /**
+     * A multistatus response with a principal named after `cn`.
+     */
+    fn principal(_: &str, cn: &str) -> String {
+        let body = format!(
+            r#"<d:multistatus xmlns:d="DAV:">
+    <d:response>
+        <d:href>/</d:href>
+        <d:propstat>
+            <d:prop>
+                <d:current-user-principal>
+                    <d:href>/principals/{cn}/</d:href>
+                </d:current-user-principal>
+            </d:prop>
+            <d:status>HTTP/1.1 200 OK</d:status>
+        </d:propstat>
+    </d:response>
+</d:multistatus>"#
+        );
+
+        format!(
+            "HTTP/1.1 207 Multi-Status\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /**
+     * An HTTP proxy opening tunnels, recording the `CONNECT` request lines.
+     */
+    fn proxy() -> Result<(String, Arc<Mutex<Vec<String>>>)> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://127.0.0.1:{}/", listener.local_addr()?.port());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let Ok(client) = stream.try_clone() else {
+                    continue;
+                };
+                let mut client = std::io::BufReader::new(client);
+
+                let mut request = String::new();
+                client.read_line(&mut request).ok();
+                let mut line = String::new();
+                while client.read_line(&mut line).is_ok_and(|x| x > 2) {
+                    line.clear();
+                }
+                recorded.lock().unwrap().push(request.trim().to_string());
+
+                let Some(Ok(mut target)) =
+                    request.split(' ').nth(1).map(std::net::TcpStream::connect)
+                else {
+                    continue;
+                };
+                let Ok(mut upstream) = target.try_clone() else {
+                    continue;
+                };
+
+                stream
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .ok();
+                std::thread::spawn(move || std::io::copy(&mut client, &mut upstream));
+                std::io::copy(&mut target, &mut stream).ok();
+                stream.shutdown(std::net::Shutdown::Both).ok();
+            }
+        });
+
+        Ok((url, requests))
+    }
+
+    /**
+     * Authenticates `builder` with a `johndoe` certificate issued by `ca`.
+     */
+    fn with_identity(
+        builder: crate::ClientBuilder,
+        ca: &X509,
+        ca_key: &PKey<Private>,
+    ) -> Result<crate::ClientBuilder> {
+        let key = key()?;
+        let certificate = certificate("johndoe", 3, &key, Some((ca, ca_key)))?;
+
+        let builder = builder.identity_pkcs12(
+            &openssl::pkcs12::Pkcs12::builder()
+                .name("johndoe")
+                .pkey(&key)
+                .cert(&certificate)
+                .build2("s3cr3t")?
+                .to_der()?,
+            "s3cr3t",
+        )?;
+
+        Ok(builder)
+    }
+
+    #[test]
+    fn identity() -> Result {
+        use crate::Xmlable as _;
+
+        let ca_key = key()?;
+        let ca = certificate("kaldav test CA", 1, &ca_key, None)?;
+        let url = server(&ca, &ca_key, principal)?;
+
+        let builder = || crate::Client::builder(&url).add_root_certificate(&ca.to_pem().unwrap());
+
+        assert!(builder().build()?.principals().is_err());
+
+        let principals = with_identity(builder(), &ca, &ca_key)?
+            .build()?
+            .principals()?;
+        assert_eq!(principals[0].url(), format!("{url}principals/johndoe/"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn redirect() -> Result {
+        let ca_key = key()?;
+        let ca = certificate("kaldav test CA", 1, &ca_key, None)?;
+
+        let heads = Arc::new(Mutex::new(Vec::new()));
+        let recorded = heads.clone();
+        let target = server(&ca, &ca_key, move |head, cn| {
+            recorded.lock().unwrap().push(head.to_ascii_lowercase());
+
+            principal(head, cn)
+        })?;
+        let url = server(&ca, &ca_key, move |_, _| {
+            format!(
+                "HTTP/1.1 307 Temporary Redirect\r\nLocation: {target}\r\nContent-Length: 0\r\n\r\n"
+            )
+        })?;
+
+        let builder = crate::Client::builder(&url)
+            .add_root_certificate(&ca.to_pem()?)
+            .auth(crate::Auth::basic("johndoe", Some("s3cr3t")))
+            .header("X-Api-Key", "s3cr3t");
+        let client = with_identity(builder, &ca, &ca_key)?.build()?;
+
+        assert_eq!(client.principals()?.len(), 1);
+
+        let heads = heads.lock().unwrap();
+        assert_eq!(heads.len(), 1);
+        assert!(heads[0].starts_with("propfind / "));
+        assert!(heads[0].contains("user-agent:"));
+        assert!(!heads[0].contains("authorization:"));
+        assert!(!heads[0].contains("x-api-key:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn proxy_tunnel() -> Result {
+        let ca_key = key()?;
+        let ca = certificate("kaldav test CA", 1, &ca_key, None)?;
+        let url = server(&ca, &ca_key, principal)?;
+        let (proxy, requests) = proxy()?;
+
+        let builder = crate::Client::builder(&url)
+            .add_root_certificate(&ca.to_pem()?)
+            .https_proxy(&proxy)?;
+        let client = with_identity(builder, &ca, &ca_key)?.build()?;
+
+        assert_eq!(client.principals()?.len(), 1);
+
+        let authority = url.trim_start_matches("https://").trim_end_matches('/');
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [format!("CONNECT {authority} HTTP/1.1")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn response() -> crate::Result {
+        let response = super::response(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nETag: \"1\"\r\n\r\n5\r\nHello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n",
+        )?;
+
+        assert_eq!(response.status, attohttpc::StatusCode::OK);
+        assert_eq!(response.headers["ETag"], "\"1\"");
+        assert_eq!(response.body, "Hello, world");
+
+        let response =
+            super::response(b"HTTP/1.1 404 Not Found\r\nContent-Length: 3\r\n\r\nNo!garbage")?;
+        assert_eq!(response.status, attohttpc::StatusCode::NOT_FOUND);
+        assert_eq!(response.body, "No!");
+
+        assert!(super::response(b"garbage").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,489 @@
+use std::borrow::Cow;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/**
+ * Headers whose value is replaced by `***` in an [`Exchange`], in addition to the header of an
+ * [`crate::Auth::Header`] credential and the ones added with
+ * [`crate::ClientBuilder::sensitive_header`].
+ */
+const SENSITIVE_HEADERS: [&str; 4] = [
+    "Authorization",
+    "Proxy-Authorization",
+    "Cookie",
+    "Set-Cookie",
+];
+
+/**
+ * Rewrites a traced body, to hide private data.
+ */
+pub type Redact = dyn Fn(&str) -> Cow<'_, str> + Send + Sync;
+
+/**
+ * A request sent to the server and its outcome.
+ *
+ * Credentials are redacted from headers. Bodies are only captured when
+ * [`crate::ClientBuilder::trace_bodies`] is enabled, through the
+ * [`crate::ClientBuilder::redact_bodies`] hook.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Exchange {
+    pub method: String,
+    pub href: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    /** Response status, `None` if the request failed before a response was received. */
+    pub status: Option<u16>,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Option<String>,
+    /** Why the request failed. */
+    pub error: Option<String>,
+    pub duration: Duration,
+}
+
+/**
+ * Writes the exchange in a format close to HTTP, one line per header and per body line:
+ *
+ * ```text
+ * ### PROPFIND https://caldav.example.org/ 12ms
+ * > Depth: 0
+ * < 207
+ * < Content-Type: text/xml
+ * ```
+ */
+impl std::fmt::Display for Exchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "### {} {} {}ms",
+            self.method,
+            self.href,
+            self.duration.as_millis()
+        )?;
+        write_message(f, '>', &self.request_headers, self.request_body.as_deref())?;
+
+        if let Some(status) = self.status {
+            writeln!(f, "< {status}")?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "< error: {error}")?;
+        }
+        write_message(
+            f,
+            '<',
+            &self.response_headers,
+            self.response_body.as_deref(),
+        )?;
+
+        writeln!(f)
+    }
+}
+
+fn write_message(
+    f: &mut std::fmt::Formatter<'_>,
+    prefix: char,
+    headers: &[(String, String)],
+    body: Option<&str>,
+) -> std::fmt::Result {
+    for (name, value) in headers {
+        writeln!(f, "{prefix} {name}: {value}")?;
+    }
+
+    if let Some(body) = body {
+        writeln!(f, "{prefix}")?;
+
+        for line in body.split('\n') {
+            writeln!(f, "{prefix} {line}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Receives every exchange with the server, including retries.
+ */
+pub trait Observer: Send + Sync {
+    fn observe(&self, exchange: &Exchange);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Exchange) + Send + Sync,
+{
+    fn observe(&self, exchange: &Exchange) {
+        self(exchange)
+    }
+}
+
+/**
+ * Appends every exchange to a file, to attach to a bug report.
+ *
+ * Write errors are emitted as `tracing` events, with the `tracing` feature.
+ *
+ * ```no_run
+ * let client = kaldav::Client::builder("https://caldav.example.org/")
+ *     .observer(kaldav::trace::Recorder::create("caldav.log")?)
+ *     .trace_bodies(true)
+ *     .build()?;
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+#[derive(Debug)]
+pub struct Recorder {
+    file: Mutex<std::fs::File>,
+}
+
+impl Recorder {
+    pub fn create<P>(path: P) -> crate::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::create(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Observer for Recorder {
+    fn observe(&self, exchange: &Exchange) {
+        use std::io::Write as _;
+
+        let mut file = self.file.lock().unwrap();
+
+        if let Err(error) = write!(file, "{exchange}") {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(%error, "unable to record exchange");
+            #[cfg(not(feature = "tracing"))]
+            let _ = error;
+        }
+    }
+}
+
+pub(crate) fn headers(
+    headers: &attohttpc::header::HeaderMap,
+    sensitive: &[String],
+) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            redact(
+                name.as_str(),
+                &String::from_utf8_lossy(value.as_bytes()),
+                sensitive,
+            )
+        })
+        .collect()
+}
+
+/**
+ * Replaces the value of a standard credential header or of a `sensitive` one.
+ */
+pub(crate) fn redact(name: &str, value: &str, sensitive: &[String]) -> (String, String) {
+    let value = if is_sensitive(name, sensitive) {
+        "***"
+    } else {
+        value
+    };
+
+    (name.to_string(), value.to_string())
+}
+
+/**
+ * Whether the `name` header carries credentials, `sensitive` listing the non-standard ones.
+ */
+pub(crate) fn is_sensitive(name: &str, sensitive: &[String]) -> bool {
+    SENSITIVE_HEADERS
+        .iter()
+        .copied()
+        .chain(sensitive.iter().map(String::as_str))
+        .any(|x| name.eq_ignore_ascii_case(x))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Headers,
+    Body,
+}
+
+/**
+ * Parses exchanges written with the [`Exchange`] `Display` implementation.
+ */
+pub(crate) fn parse(s: &str) -> crate::Result<Vec<Exchange>> {
+    let mut exchanges = Vec::new();
+    let mut exchange: Option<Exchange> = None;
+    let mut request = Section::Headers;
+    let mut response = Section::Headers;
+
+    for (n, line) in s.split('\n').enumerate() {
+        let error = || crate::Error::Misc(format!("Invalid exchange at line {}: {line}", n + 1));
+
+        if let Some(start) = line.strip_prefix("### ") {
+            exchanges.extend(exchange.take());
+            request = Section::Headers;
+            response = Section::Headers;
+
+            let mut parts = start.trim_end().split(' ');
+            let method = parts.next().ok_or_else(error)?;
+            let href = parts.next().ok_or_else(error)?;
+            let duration = parts
+                .next()
+                .and_then(|x| x.strip_suffix("ms"))
+                .and_then(|x| x.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or_default();
+
+            exchange = Some(Exchange {
+                method: method.to_string(),
+                href: href.to_string(),
+                duration,
+                ..Default::default()
+            });
+
+            continue;
+        }
+
+        if line.trim_end().is_empty() {
+            continue;
+        }
+
+        let current = exchange.as_mut().ok_or_else(error)?;
+
+        if let Some(content) = line.strip_prefix('>') {
+            parse_line(
+                content,
+                &mut request,
+                &mut current.request_headers,
+                &mut current.request_body,
+            )
+            .ok_or_else(error)?;
+        } else if let Some(content) = line.strip_prefix('<') {
+            let value = content.strip_prefix(' ').unwrap_or(content);
+
+            if response == Section::Headers && current.status.is_none() && current.error.is_none() {
+                if let Ok(status) = value.trim_end().parse() {
+                    current.status = Some(status);
+                    continue;
+                } else if let Some(error) = value.strip_prefix("error: ") {
+                    current.error = Some(error.to_string());
+                    continue;
+                }
+            }
+
+            parse_line(
+                content,
+                &mut response,
+                &mut current.response_headers,
+                &mut current.response_body,
+            )
+            .ok_or_else(error)?;
+        } else {
+            return Err(error());
+        }
+    }
+
+    exchanges.extend(exchange);
+
+    Ok(exchanges)
+}
+
+fn parse_line(
+    content: &str,
+    section: &mut Section,
+    headers: &mut Vec<(String, String)>,
+    body: &mut Option<String>,
+) -> Option<()> {
+    match section {
+        Section::Headers if content.trim_end().is_empty() => {
+            *section = Section::Body;
+        }
+        Section::Headers => {
+            let (name, value) = content.strip_prefix(' ')?.split_once(": ")?;
+            headers.push((name.to_string(), value.to_string()));
+        }
+        Section::Body => {
+            let line = content.strip_prefix(' ').unwrap_or(content);
+
+            match body {
+                Some(body) => {
+                    body.push('\n');
+                    body.push_str(line);
+                }
+                None => *body = Some(line.to_string()),
+            }
+        }
+    }
+
+    Some(())
+}
+
+/**
+ * Emits the exchange as `tracing` events in the current span.
+ */
+#[cfg(feature = "tracing")]
+pub(crate) fn emit(exchange: &Exchange) {
+    let duration_ms = exchange.duration.as_millis() as u64;
+
+    if let Some(body) = &exchange.request_body {
+        tracing::trace!(body, "request body");
+    }
+
+    match (&exchange.status, &exchange.error) {
+        (_, Some(error)) => tracing::warn!(duration_ms, error, "request failed"),
+        (Some(status), None) => tracing::debug!(status, duration_ms, "response"),
+        (None, None) => (),
+    }
+
+    if let Some(body) = &exchange.response_body {
+        tracing::trace!(body, "response body");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    fn observe(builder: crate::ClientBuilder) -> crate::Result<Vec<super::Exchange>> {
+        let exchanges = Arc::new(Mutex::new(Vec::new()));
+        let observed = exchanges.clone();
+
+        let client = builder
+            .observer(move |exchange: &super::Exchange| {
+                observed.lock().unwrap().push(exchange.clone())
+            })
+            .build()?;
+        client.principals()?;
+
+        Ok(exchanges.lock().unwrap().clone())
+    }
+
+    fn builder(server: &httpmock::MockServer) -> crate::ClientBuilder {
+        crate::Client::builder(server.url("")).auth(crate::Auth::basic("johndoe", Some("s3cr3t")))
+    }
+
+    #[test]
+    fn observer() -> crate::Result {
+        let server = crate::test::server();
+        let exchanges = observe(builder(&server))?;
+        assert_eq!(exchanges.len(), 1);
+
+        let exchange = &exchanges[0];
+        assert_eq!(exchange.method, "PROPFIND");
+        assert_eq!(exchange.status, Some(207));
+        assert!(
+            exchange
+                .request_headers
+                .contains(&("Authorization".to_string(), "***".to_string()))
+        );
+        assert_eq!(exchange.request_body, None);
+        assert_eq!(exchange.response_body, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bodies() -> crate::Result {
+        let server = crate::test::server();
+        let exchanges = observe(builder(&server).trace_bodies(true))?;
+        let exchange = &exchanges[0];
+
+        assert!(
+            exchange
+                .request_body
+                .as_ref()
+                .unwrap()
+                .contains("current-user-principal")
+        );
+        assert!(
+            exchange
+                .response_body
+                .as_ref()
+                .unwrap()
+                .contains("/principals/users/johndoe/")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn redact_bodies() -> crate::Result {
+        let server = crate::test::server();
+        let builder = builder(&server)
+            .trace_bodies(true)
+            .redact_bodies(|body| body.replace("johndoe", "***").into());
+        let exchanges = observe(builder)?;
+
+        let body = exchanges[0].response_body.as_ref().unwrap();
+        assert!(body.contains("/principals/users/***/"));
+        assert!(!body.contains("johndoe"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sensitive_headers() -> crate::Result {
+        let server = crate::test::server();
+        let builder = crate::Client::builder(server.url(""))
+            .auth(crate::Auth::header("X-Api-Key", "s3cr3t"))
+            .header("X-Session", "abc123")
+            .sensitive_header("X-Session");
+        let exchanges = observe(builder)?;
+
+        let headers = &exchanges[0].request_headers;
+        assert!(headers.contains(&("X-Api-Key".to_string(), "***".to_string())));
+        assert!(headers.contains(&("x-session".to_string(), "***".to_string())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse() -> crate::Result {
+        let exchange = super::Exchange {
+            method: "PUT".to_string(),
+            href: "/calendars/johndoe/home/event.ics".to_string(),
+            request_headers: vec![("If-None-Match".to_string(), "*".to_string())],
+            request_body: Some("BEGIN:VCALENDAR\r\n\r\nEND:VCALENDAR\r\n".to_string()),
+            status: Some(201),
+            response_headers: vec![("ETag".to_string(), "\"1\"".to_string())],
+            response_body: Some(String::new()),
+            error: None,
+            duration: std::time::Duration::from_millis(12),
+        };
+        let failed = super::Exchange {
+            method: "GET".to_string(),
+            href: "/".to_string(),
+            error: Some("connection refused".to_string()),
+            ..Default::default()
+        };
+
+        let log = format!("{exchange}{failed}");
+        assert_eq!(super::parse(&log)?, vec![exchange, failed]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn recorder() -> crate::Result {
+        let server = crate::test::server();
+        let path = crate::test::TempPath::new("kaldav", ".log");
+
+        let client = crate::Client::builder(server.url(""))
+            .auth(crate::Auth::basic("johndoe", Some("s3cr3t")))
+            .observer(super::Recorder::create(&path)?)
+            .trace_bodies(true)
+            .build()?;
+        client.principals()?;
+
+        let log = std::fs::read_to_string(&path)?;
+
+        assert!(log.starts_with(&format!("### PROPFIND {} ", server.url(""))));
+        assert!(log.contains("> Authorization: ***\n"));
+        assert!(log.contains("\n< 207\n"));
+        assert!(log.contains("<d:href>/principals/users/johndoe/</d:href>"));
+        assert!(!log.contains("s3cr3t"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use std::sync::Mutex;
+
+/**
+ * A request about to be sent, with the session headers and credentials applied.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub href: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /**
+     * Headers carrying credentials, like the one of the session authentication, redacted
+     * from traces and fixtures in addition to the standard ones.
+     */
+    pub sensitive_headers: Vec<String>,
+}
+
+/**
+ * A response whose body has been read.
+ */
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: attohttpc::StatusCode,
+    pub headers: attohttpc::header::HeaderMap,
+    pub body: String,
+}
+
+impl Response {
+    pub(crate) fn read(response: attohttpc::Response) -> crate::Result<Self> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text()?;
+
+        Ok(Self {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+}
+
+/**
+ * Sends the requests of a session.
+ *
+ * `http` sends the request over the network with the session settings, a transport can call it,
+ * or answer without touching the network.
+ */
+pub trait Transport: Send + Sync + std::fmt::Debug {
+    fn send(&self, request: &Request, http: &Http) -> crate::Result<Response>;
+}
+
+/**
+ * Sends a request over the network.
+ */
+pub type Http<'a> = dyn Fn(&Request) -> crate::Result<Response> + 'a;
+
+/**
+ * Saves every request and its response to a fixture file, to replay them later with [`Replay`].
+ *
+ * Hrefs are stored without their scheme and host, request bodies are normalized and credentials
+ * are redacted.
+ *
+ * ```no_run
+ * let client = kaldav::Client::builder("https://caldav.example.org/")
+ *     .transport(kaldav::transport::Record::create("tests/fixtures/calendars.http")?)
+ *     .build()?;
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+#[derive(Debug)]
+pub struct Record {
+    file: Mutex<std::fs::File>,
+}
+
+impl Record {
+    pub fn create<P>(path: P) -> crate::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let file = std::fs::File::create(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Transport for Record {
+    fn send(&self, request: &Request, http: &Http) -> crate::Result<Response> {
+        use std::io::Write as _;
+
+        let start = std::time::Instant::now();
+        let response = http(request)?;
+
+        let exchange = crate::trace::Exchange {
+            method: request.method.clone(),
+            href: path(&request.href),
+            request_headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| crate::trace::redact(name, value, &request.sensitive_headers))
+                .collect(),
+            request_body: request.body.as_deref().map(normalize),
+            status: Some(response.status.as_u16()),
+            response_headers: crate::trace::headers(&response.headers, &request.sensitive_headers),
+            response_body: Some(response.body.clone()),
+            error: None,
+            duration: start.elapsed(),
+        };
+
+        write!(self.file.lock().unwrap(), "{exchange}")?;
+
+        Ok(response)
+    }
+}
+
+/**
+ * Answers requests with the responses saved by [`Record`], without network access.
+ *
+ * Requests are matched on their method, path and normalized body. When the same request was
+ * recorded several times, the responses are served in the recorded order, the last one being
+ * repeated.
+ *
+ * ```no_run
+ * let client = kaldav::Client::builder("https://caldav.example.org/")
+ *     .transport(kaldav::transport::Replay::open("tests/fixtures/calendars.http")?)
+ *     .build()?;
+ * let calendars = client.calendars()?;
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ */
+#[derive(Debug)]
+pub struct Replay {
+    exchanges: Mutex<Vec<(crate::trace::Exchange, bool)>>,
+}
+
+impl Replay {
+    pub fn open<P>(path: P) -> crate::Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let fixture = std::fs::read_to_string(path)?;
+
+        fixture.parse()
+    }
+
+    fn response(exchange: &crate::trace::Exchange) -> crate::Result<Response> {
+        let status = exchange
+            .status
+            .and_then(|x| attohttpc::StatusCode::from_u16(x).ok())
+            .ok_or_else(|| {
+                crate::Error::Misc(format!(
+                    "Recorded exchange without status: {} {}",
+                    exchange.method, exchange.href
+                ))
+            })?;
+
+        let mut headers = attohttpc::header::HeaderMap::new();
+
+        for (name, value) in &exchange.response_headers {
+            let name = attohttpc::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| crate::Error::Misc(format!("Invalid header name: {name}")))?;
+            let value = attohttpc::header::HeaderValue::from_str(value)
+                .map_err(|_| crate::Error::Misc(format!("Invalid value for header {name}")))?;
+
+            headers.append(name, value);
+        }
+
+        Ok(Response {
+            status,
+            headers,
+            body: exchange.response_body.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl std::str::FromStr for Replay {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let exchanges = crate::trace::parse(s)?
+            .into_iter()
+            .map(|x| (x, false))
+            .collect();
+
+        Ok(Self {
+            exchanges: Mutex::new(exchanges),
+        })
+    }
+}
+
+impl Transport for Replay {
+    fn send(&self, request: &Request, _: &Http) -> crate::Result<Response> {
+        let href = path(&request.href);
+        let body = request.body.as_deref().map(normalize);
+
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let mut matching = exchanges.iter_mut().filter(|(x, _)| {
+            x.method == request.method
+                && x.href == href
+                && x.request_body.as_deref().unwrap_or_default()
+                    == body.as_deref().unwrap_or_default()
+        });
+
+        let mut last = None;
+
+        for (exchange, used) in matching.by_ref() {
+            if !*used {
+                *used = true;
+                return Self::response(exchange);
+            }
+
+            last = Some(exchange);
+        }
+
+        match last {
+            Some(exchange) => Self::response(exchange),
+            None => Err(crate::Error::Misc(format!(
+                "No recorded response for {} {href}",
+                request.method
+            ))),
+        }
+    }
+}
+
+/**
+ * Removes the scheme and host of `href`.
+ */
+fn path(href: &str) -> String {
+    match url::Url::parse(href) {
+        Ok(url) => match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        },
+        Err(_) => href.to_string(),
+    }
+}
+
+/**
+ * Trims lines and removes the empty ones, so indentation changes don't break matching.
+ */
+fn normalize(body: &str) -> String {
+    body.lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            super::normalize("\n<d:propfind>\r\n    <d:prop />\n</d:propfind>\n"),
+            "<d:propfind>\n<d:prop />\n</d:propfind>"
+        );
+        assert_eq!(
+            super::path("http://127.0.0.1:8080/calendars/?a=b"),
+            "/calendars/?a=b"
+        );
+    }
+
+    #[test]
+    fn replay() -> crate::Result {
+        let server = crate::test::server();
+        let path = crate::test::TempPath::new("kaldav", ".http");
+
+        let client = crate::Client::builder(server.url(""))
+            .transport(super::Record::create(&path)?)
+            .build()?;
+        let recorded = client.calendars()?["Home calendar"].events()?.count();
+
+        drop(server);
+
+        let client = crate::Client::builder("http://127.0.0.1:1/")
+            .transport(super::Replay::open(&path)?)
+            .build()?;
+        let calendars = client.calendars()?;
+        let replayed = calendars["Home calendar"].events()?.count();
+
+        assert_eq!(calendars.len(), 2);
+        assert_eq!(recorded, 1);
+        assert_eq!(replayed, recorded);
+
+        let Err(error) = calendars["My TODO list"].tasks() else {
+            panic!("unrecorded request");
+        };
+        assert!(
+            error
+                .to_string()
+                .starts_with("No recorded response for REPORT")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn order() -> crate::Result {
+        use crate::Requestable as _;
+
+        let replay = "### GET /event.ics 1ms
+< 200
+<
+< first
+
+### GET /event.ics 1ms
+< 200
+<
+< second
+
+"
+        .parse::<super::Replay>()?;
+
+        let client = crate::Client::builder("http://127.0.0.1:1/")
+            .transport(replay)
+            .build()?;
+
+        assert_eq!(client.get("http://127.0.0.1:1/event.ics")?, "first");
+        assert_eq!(client.get("http://127.0.0.1:1/event.ics")?, "second");
+        assert_eq!(client.get("http://127.0.0.1:1/event.ics")?, "second");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+use crate::timezone::Timezone;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+/**
+ * A time zone distribution service client.
+ *
+ * `url` is the context path of the service, the requests share the session of the client it’s
+ * created from, see [`crate::Client::tzdist`]. Time zones are cached with their `ETag` and
+ * revalidated by [`Self::timezone`].
+ *
+ * ```no_run
+ * let client = kaldav::Client::new("https://caldav.example.org/");
+ * let tzdist = client.tzdist("https://tz.example.org/api/");
+ *
+ * let timezone = tzdist.timezone("Europe/Paris")?;
+ * let resolver = tzdist.resolver();
+ * # Ok::<(), kaldav::Error>(())
+ * ```
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc7808>
+ */
+#[derive(Clone, Debug, Default, crate::Object)]
+pub struct Tzdist {
+    url: String,
+    session: crate::Session,
+    cache: Arc<Mutex<BTreeMap<String, Cached>>>,
+}
+
+#[derive(Clone, Debug)]
+struct Cached {
+    etag: Option<String>,
+    timezone: Timezone,
+}
+
+/**
+ * A time zone listed by the service.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc7808#section-5.1>
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Info {
+    pub tzid: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+impl Tzdist {
+    pub fn new<S>(url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_session(mut self, session: crate::Session) -> Self {
+        self.session = session;
+
+        self
+    }
+
+    /**
+     * Lists the time zones of the service.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7808#section-4.2.3>
+     */
+    pub fn list(&self) -> crate::Result<Vec<Info>> {
+        let url = self.endpoint(&["zones"])?;
+        let mut headers = crate::Headers::new();
+        headers.insert("Accept", "application/json");
+
+        let response =
+            crate::Requestable::request(self, crate::Method::GET, url, None, Some(headers))?;
+        let json = serde_json::from_str::<serde_json::Value>(&response)
+            .map_err(|error| crate::Error::Misc(format!("Invalid time zones list: {error}")))?;
+
+        let text = |value: &serde_json::Value, name: &str| {
+            value
+                .get(name)
+                .and_then(|x| x.as_str())
+                .map(ToString::to_string)
+        };
+
+        let timezones = json
+            .get("timezones")
+            .and_then(|x| x.as_array())
+            .ok_or_else(|| crate::Error::Misc("Invalid time zones list".to_string()))?
+            .iter()
+            .filter_map(|x| {
+                Some(Info {
+                    tzid: text(x, "tzid")?,
+                    etag: text(x, "etag"),
+                    last_modified: text(x, "last-modified"),
+                    aliases: x
+                        .get("aliases")
+                        .and_then(|x| x.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|x| x.as_str())
+                        .map(ToString::to_string)
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Ok(timezones)
+    }
+
+    /**
+     * Retrieves the `VTIMEZONE` of `tzid`, revalidating the cached one.
+     *
+     * <https://datatracker.ietf.org/doc/html/rfc7808#section-4.2.4>
+     */
+    pub fn timezone(&self, tzid: &str) -> crate::Result<Timezone> {
+        let url = self.endpoint(&["zones", tzid])?;
+        let cached = self.cache.lock().unwrap().get(tzid).cloned();
+
+        let mut headers = crate::Headers::new();
+        headers.insert("Accept", "text/calendar");
+
+        if let Some(etag) = cached.as_ref().and_then(|x| x.etag.as_deref()) {
+            headers.insert("If-None-Match", etag);
+        }
+
+        let response = self.session.send("GET", &url, None, &headers)?;
+
+        if response.status == attohttpc::StatusCode::NOT_MODIFIED
+            && let Some(cached) = cached
+        {
+            return Ok(cached.timezone);
+        }
+
+        if !response.is_success() {
+            return Err(crate::Error::from_response(
+                crate::Method::GET,
+                &url,
+                response,
+            ));
+        }
+
+        let timezone = crate::component::parse(&response.body)
+            .and_then(|x| x.components.into_iter().find(|x| x.name == "VTIMEZONE"))
+            .ok_or_else(|| crate::Error::Timezone(format!("{tzid}: missing VTIMEZONE")))
+            .and_then(|x| Timezone::from_component(&x))?;
+
+        let etag = response
+            .headers
+            .get("ETag")
+            .and_then(|x| x.to_str().ok())
+            .map(ToString::to_string);
+
+        self.cache.lock().unwrap().insert(
+            tzid.to_string(),
+            Cached {
+                etag,
+                timezone: timezone.clone(),
+            },
+        );
+
+        Ok(timezone)
+    }
+
+    /**
+     * A resolver falling back to this service. Cached time zones are used without
+     * revalidation.
+     *
+     * A failed lookup isn’t retried by this resolver, the time zone stays unknown. The error is
+     * logged with the `tracing` feature.
+     */
+    pub fn resolver(&self) -> crate::timezone::Resolver {
+        let tzdist = self.clone();
+        let missing = Mutex::new(BTreeSet::new());
+
+        crate::timezone::Resolver::new().fallback(move |tzid| {
+            let cached = tzdist
+                .cache
+                .lock()
+                .unwrap()
+                .get(tzid)
+                .map(|x| x.timezone.clone());
+
+            if cached.is_some() || missing.lock().unwrap().contains(tzid) {
+                return cached;
+            }
+
+            match tzdist.timezone(tzid) {
+                Ok(timezone) => Some(timezone),
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%error, tzid, "unable to retrieve time zone");
+                    #[cfg(not(feature = "tracing"))]
+                    let _ = error;
+
+                    missing.lock().unwrap().insert(tzid.to_string());
+
+                    None
+                }
+            }
+        })
+    }
+
+    /**
+     * URL of an endpoint, with each segment percent-encoded.
+     */
+    fn endpoint(&self, segments: &[&str]) -> crate::Result<String> {
+        let mut url =
+            url::Url::parse(&self.url).map_err(|error| crate::Error::Misc(error.to_string()))?;
+
+        url.path_segments_mut()
+            .map_err(|_| crate::Error::Misc(format!("Invalid service URL: {}", self.url)))?
+            .pop_if_empty()
+            .extend(segments);
+
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    const PARIS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//kaldav//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Paris\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+DTSTART:19810329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19961027T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn list() -> crate::Result {
+        let server = httpmock::MockServer::start();
+
+        server.mock(|when, then| {
+            when.path("/api/zones").header("Accept", "application/json");
+            then.status(200).body(
+                r#"{
+    "synctoken": "2024-01-01T00:00:00Z",
+    "timezones": [
+        {
+            "tzid": "America/New_York",
+            "etag": "\"1\"",
+            "last-modified": "2024-01-01T00:00:00Z",
+            "aliases": ["US/Eastern"]
+        },
+        {
+            "tzid": "Europe/Paris",
+            "last-modified": "2024-01-01T00:00:00Z"
+        }
+    ]
+}"#,
+            );
+        });
+
+        let client = crate::Client::new(server.url("/"));
+        let timezones = client.tzdist(server.url("/api/")).list()?;
+
+        assert_eq!(timezones.len(), 2);
+        assert_eq!(timezones[0].tzid, "America/New_York");
+        assert_eq!(timezones[0].etag.as_deref(), Some("\"1\""));
+        assert_eq!(timezones[0].aliases, ["US/Eastern"]);
+        assert_eq!(timezones[1].etag, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn timezone() -> crate::Result {
+        let server = httpmock::MockServer::start();
+
+        let fetch = server.mock(|when, then| {
+            when.path("/api/zones/Europe%2FParis")
+                .header("Accept", "text/calendar")
+                .header_missing("If-None-Match");
+            then.status(200).header("ETag", "\"1\"").body(PARIS);
+        });
+        let revalidate = server.mock(|when, then| {
+            when.path("/api/zones/Europe%2FParis")
+                .header("If-None-Match", "\"1\"");
+            then.status(304);
+        });
+
+        let tzdist = crate::Tzdist::new(server.url("/api"));
+
+        let timezone = tzdist.timezone("Europe/Paris")?;
+        assert_eq!(timezone.tzid(), "Europe/Paris");
+        assert_eq!(tzdist.timezone("Europe/Paris")?, timezone);
+        assert_eq!(fetch.calls(), 1);
+        assert_eq!(revalidate.calls(), 1);
+
+        let resolver = tzdist.resolver();
+        let zone = resolver
+            .resolve("/mozilla.org/20050126_1/Europe/Paris")
+            .unwrap();
+        let july = chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        assert_eq!(zone.offset_from_utc(&july).local_minus_utc(), 7_200);
+        assert_eq!(revalidate.calls(), 1);
+
+        let missing = server.mock(|when, then| {
+            when.path("/api/zones/Mars%2FOlympus_Mons");
+            then.status(404);
+        });
+
+        assert!(resolver.resolve("Mars/Olympus_Mons").is_none());
+        assert!(resolver.resolve("Mars/Olympus_Mons").is_none());
+        assert_eq!(missing.calls(), 1);
+
+        Ok(())
+    }
+}
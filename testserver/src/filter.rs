@@ -0,0 +1,642 @@
+use crate::ical::{Component, Property};
+use crate::xml::{CALDAV, Node};
+use chrono::{DateTime, Datelike as _, Months, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+
+/**
+ * Checks a `CALDAV:filter` element, returns the failed precondition of an invalid one.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7>
+ */
+pub(crate) fn validate(filter: &Node) -> Result<(), &'static str> {
+    let comp_filters = elements(filter).collect::<Vec<_>>();
+
+    match comp_filters.as_slice() {
+        [calendar]
+            if calendar.is(CALDAV, "comp-filter")
+                && calendar.attribute("name") == Some("VCALENDAR") =>
+        {
+            validate_filter(calendar)
+        }
+        _ => Err("valid-filter"),
+    }
+}
+
+fn validate_filter(filter: &Node) -> Result<(), &'static str> {
+    if filter.attribute("name").is_none_or(str::is_empty)
+        || !matches!(filter.attribute("test"), None | Some("allof" | "anyof"))
+    {
+        return Err("valid-filter");
+    }
+
+    let children = elements(filter).collect::<Vec<_>>();
+
+    if children.iter().any(|x| x.is(CALDAV, "is-not-defined")) && children.len() > 1 {
+        return Err("valid-filter");
+    }
+
+    let (allowed, single): (&[&str], &[&str]) = if filter.is(CALDAV, "comp-filter") {
+        (
+            &["is-not-defined", "time-range", "prop-filter", "comp-filter"],
+            &["time-range"],
+        )
+    } else if filter.is(CALDAV, "prop-filter") {
+        (
+            &["is-not-defined", "time-range", "text-match", "param-filter"],
+            &["time-range", "text-match"],
+        )
+    } else {
+        (&["is-not-defined", "text-match"], &["text-match"])
+    };
+
+    for name in single {
+        if children.iter().filter(|x| x.is(CALDAV, name)).count() > 1 {
+            return Err("valid-filter");
+        }
+    }
+
+    for child in children {
+        let Some(name) = allowed.iter().find(|x| child.is(CALDAV, x)) else {
+            return Err("valid-filter");
+        };
+
+        match *name {
+            "time-range" => validate_time_range(child)?,
+            "text-match" => validate_text_match(child)?,
+            "comp-filter" | "prop-filter" | "param-filter" => validate_filter(child)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Accepts the `-infinity` and `+infinity` bounds sent by kaldav for an unbounded range.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
+ */
+fn validate_time_range(filter: &Node) -> Result<(), &'static str> {
+    let parse = |name| match filter.attribute(name) {
+        None | Some("-infinity" | "+infinity") => Ok(None),
+        Some(value) if value.ends_with('Z') => parse_date(value).map(Some).ok_or("valid-filter"),
+        Some(_) => Err("valid-filter"),
+    };
+
+    match (parse("start")?, parse("end")?) {
+        (None, None) => Err("valid-filter"),
+        (Some(start), Some(end)) if start >= end => Err("valid-filter"),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.5>
+ */
+fn validate_text_match(filter: &Node) -> Result<(), &'static str> {
+    if !matches!(
+        filter.attribute("collation"),
+        None | Some("i;ascii-casemap" | "i;octet")
+    ) {
+        return Err("supported-collation");
+    }
+
+    if !matches!(
+        filter.attribute("match-type"),
+        None | Some("equals" | "contains" | "starts-with" | "ends-with")
+    ) || !matches!(
+        filter.attribute("negate-condition"),
+        None | Some("yes" | "no")
+    ) {
+        return Err("valid-filter");
+    }
+
+    Ok(())
+}
+
+fn elements(node: &Node) -> impl Iterator<Item = &Node> {
+    node.children.iter().filter(|x| x.name.is_some())
+}
+
+/**
+ * Evaluates a valid `CALDAV:filter` element against a `VCALENDAR` object.
+ *
+ * This evaluator is independent of the kaldav one, it’s simpler and only understands what the
+ * tests need:
+ *
+ * - times with a `TZID` and floating times are read as UTC;
+ * - recurrences are expanded for the `FREQ`, `INTERVAL`, `COUNT` and `UNTIL` parts, `RDATE`,
+ *   `EXDATE` and overridden instances are ignored;
+ * - a recurring component with other rule parts, like `BYDAY`, matches any time range;
+ * - components other than `VEVENT`, `VTODO` and `VJOURNAL` match any time range.
+ *
+ * When `ignore_test` is `true`, the children are always combined with `allof`.
+ */
+pub(crate) fn matches(filter: &Node, calendar: &Component, ignore_test: bool) -> bool {
+    elements(filter).all(|x| comp_filter(x, std::slice::from_ref(calendar), ignore_test))
+}
+
+fn comp_filter(filter: &Node, components: &[Component], ignore_test: bool) -> bool {
+    let name = filter.attribute("name").unwrap_or_default();
+    let mut candidates = components
+        .iter()
+        .filter(|x| x.name.eq_ignore_ascii_case(name));
+
+    if filter.child(CALDAV, "is-not-defined").is_some() {
+        return candidates.next().is_none();
+    }
+
+    candidates.any(|component| {
+        test(filter, ignore_test, |child| {
+            if child.is(CALDAV, "time-range") {
+                component_time_range(child, component)
+            } else if child.is(CALDAV, "comp-filter") {
+                comp_filter(child, &component.components, ignore_test)
+            } else {
+                prop_filter(child, component, ignore_test)
+            }
+        })
+    })
+}
+
+fn prop_filter(filter: &Node, component: &Component, ignore_test: bool) -> bool {
+    let name = filter.attribute("name").unwrap_or_default();
+    let mut candidates = component
+        .properties
+        .iter()
+        .filter(|x| x.name.eq_ignore_ascii_case(name));
+
+    if filter.child(CALDAV, "is-not-defined").is_some() {
+        return candidates.next().is_none();
+    }
+
+    candidates.any(|property| {
+        test(filter, ignore_test, |child| {
+            if child.is(CALDAV, "time-range") {
+                property_time_range(child, property)
+            } else if child.is(CALDAV, "text-match") {
+                text_match(child, &property.value)
+            } else {
+                param_filter(child, property, ignore_test)
+            }
+        })
+    })
+}
+
+fn param_filter(filter: &Node, property: &Property, ignore_test: bool) -> bool {
+    let value = property.param(filter.attribute("name").unwrap_or_default());
+
+    if filter.child(CALDAV, "is-not-defined").is_some() {
+        return value.is_none();
+    }
+
+    value.is_some_and(|value| test(filter, ignore_test, |child| text_match(child, value)))
+}
+
+/**
+ * Combines the result of the children tests, according to the `test` attribute.
+ */
+fn test<F>(filter: &Node, ignore_test: bool, f: F) -> bool
+where
+    F: Fn(&Node) -> bool,
+{
+    let mut children = elements(filter);
+
+    if filter.attribute("test") == Some("anyof") && !ignore_test {
+        let children = children.collect::<Vec<_>>();
+
+        children.is_empty() || children.into_iter().any(f)
+    } else {
+        children.all(f)
+    }
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.7.5>
+ */
+fn text_match(filter: &Node, value: &str) -> bool {
+    let (text, value) = if filter.attribute("collation") == Some("i;octet") {
+        (filter.text.clone(), value.to_string())
+    } else {
+        (filter.text.to_ascii_lowercase(), value.to_ascii_lowercase())
+    };
+
+    let matches = match filter.attribute("match-type") {
+        Some("equals") => value == text,
+        Some("starts-with") => value.starts_with(&text),
+        Some("ends-with") => value.ends_with(&text),
+        _ => value.contains(&text),
+    };
+
+    matches != (filter.attribute("negate-condition") == Some("yes"))
+}
+
+/**
+ * The range of a `time-range` element, unbounded for a missing attribute.
+ */
+fn range(filter: &Node) -> (DateTime<Utc>, DateTime<Utc>) {
+    let date = |name| filter.attribute(name).and_then(parse_date);
+
+    (
+        date("start").unwrap_or(DateTime::<Utc>::MIN_UTC),
+        date("end").unwrap_or(DateTime::<Utc>::MAX_UTC),
+    )
+}
+
+/**
+ * Whether a component, starting at `start` and ending at `end`, overlaps the range.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-9.9>
+ */
+fn overlaps(
+    (start, end): (DateTime<Utc>, DateTime<Utc>),
+    range: (DateTime<Utc>, DateTime<Utc>),
+) -> bool {
+    if start == end {
+        range.0 <= start && start < range.1
+    } else {
+        range.0 < end && start < range.1
+    }
+}
+
+fn component_time_range(filter: &Node, component: &Component) -> bool {
+    let range = range(filter);
+
+    let Some((start, end)) = span(component) else {
+        return !matches!(component.name.as_str(), "VEVENT" | "VTODO" | "VJOURNAL");
+    };
+
+    let Some(rrule) = component.property("RRULE") else {
+        return overlaps((start, end), range);
+    };
+
+    let Some(rule) = Rule::parse(&rrule.value) else {
+        return true;
+    };
+
+    let duration = end - start;
+
+    let after = range
+        .0
+        .checked_sub_signed(duration)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+    rule.occurrences(start, after)
+        .take_while(|x| *x < range.1)
+        .any(|x| overlaps((x, x + duration), range))
+}
+
+fn property_time_range(filter: &Node, property: &Property) -> bool {
+    let (start, end) = range(filter);
+
+    parse_date(&property.value).is_some_and(|value| start <= value && value < end)
+}
+
+/**
+ * Start and end of the first occurrence of a component, `None` for components without dates.
+ */
+fn span(component: &Component) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let date = |name| component.property(name).and_then(|x| parse_date(&x.value));
+    let duration = component
+        .property("DURATION")
+        .and_then(|x| parse_duration(&x.value));
+    let is_date = component
+        .property("DTSTART")
+        .is_some_and(|x| x.value.len() == 8);
+
+    match component.name.as_str() {
+        "VEVENT" | "VJOURNAL" => {
+            let start = date("DTSTART")?;
+            let end = date("DTEND")
+                .or(duration.map(|x| start + x))
+                .unwrap_or(if is_date {
+                    start + TimeDelta::days(1)
+                } else {
+                    start
+                });
+
+            Some((start, end))
+        }
+        "VTODO" => {
+            let start = date("DTSTART").or(date("DUE"))?;
+            let end = date("DUE").or(duration.map(|x| start + x)).unwrap_or(start);
+
+            Some((start, end))
+        }
+        _ => None,
+    }
+}
+
+/**
+ * A recurrence rule limited to its `FREQ`, `INTERVAL`, `COUNT` and `UNTIL` parts.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.10>
+ */
+struct Rule {
+    step: Step,
+    count: Option<u64>,
+    until: Option<DateTime<Utc>>,
+}
+
+enum Step {
+    Fixed(TimeDelta),
+    Months(u32),
+}
+
+impl Rule {
+    /**
+     * `None` for an invalid rule or one with other parts.
+     */
+    fn parse(value: &str) -> Option<Self> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+
+        for part in value.split(';') {
+            let (name, value) = part.split_once('=')?;
+
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.to_ascii_uppercase()),
+                "INTERVAL" => interval = value.parse().ok().filter(|x| *x > 0)?,
+                "COUNT" => count = Some(value.parse().ok()?),
+                "UNTIL" => until = Some(parse_date(value)?),
+                "WKST" => (),
+                _ => return None,
+            }
+        }
+
+        let step = match frequency?.as_str() {
+            "SECONDLY" => Step::Fixed(TimeDelta::seconds(interval.into())),
+            "MINUTELY" => Step::Fixed(TimeDelta::minutes(interval.into())),
+            "HOURLY" => Step::Fixed(TimeDelta::hours(interval.into())),
+            "DAILY" => Step::Fixed(TimeDelta::days(interval.into())),
+            "WEEKLY" => Step::Fixed(TimeDelta::weeks(interval.into())),
+            "MONTHLY" => Step::Months(interval),
+            "YEARLY" => Step::Months(interval.checked_mul(12)?),
+            _ => return None,
+        };
+
+        Some(Self { step, count, until })
+    }
+
+    /**
+     * Start of the occurrences, from the first one not before `after`. Months without the day
+     * of `dtstart`, like a 31st, are skipped.
+     */
+    fn occurrences(
+        &self,
+        dtstart: DateTime<Utc>,
+        after: DateTime<Utc>,
+    ) -> impl Iterator<Item = DateTime<Utc>> {
+        // Occurrences with a fixed step before `after` are skipped without being computed.
+        let first = match self.step {
+            Step::Fixed(step) if after > dtstart => {
+                ((after - dtstart).num_seconds() / step.num_seconds()).max(0) as u64
+            }
+            _ => 0,
+        };
+
+        let count = self
+            .count
+            .map_or(usize::MAX, |x| x.saturating_sub(first) as usize);
+
+        (first..)
+            // `None` once out of range, `Some(None)` for a skipped month.
+            .map_while(move |n| match self.step {
+                Step::Fixed(step) => i32::try_from(n)
+                    .ok()
+                    .and_then(|n| step.checked_mul(n))
+                    .and_then(|x| dtstart.checked_add_signed(x))
+                    .map(Some),
+                Step::Months(months) => u32::try_from(n)
+                    .ok()
+                    .and_then(|n| n.checked_mul(months))
+                    .and_then(|x| dtstart.checked_add_months(Months::new(x)))
+                    .map(|x| Some(x).filter(|x| x.day() == dtstart.day())),
+            })
+            .flatten()
+            .take(count)
+            .take_while(move |x| self.until.is_none_or(|until| *x <= until))
+    }
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.strip_suffix('Z').unwrap_or(value);
+
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some(datetime.and_utc());
+    }
+
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|x| x.and_hms_opt(0, 0, 0))
+        .map(|x| x.and_utc())
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.6>
+ */
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+
+    for c in value.chars() {
+        let unit = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' => continue,
+            'W' => TimeDelta::weeks(1),
+            'D' => TimeDelta::days(1),
+            'H' => TimeDelta::hours(1),
+            'M' => TimeDelta::minutes(1),
+            'S' => TimeDelta::seconds(1),
+            _ => return None,
+        };
+
+        duration += unit * number.parse().ok()?;
+        number.clear();
+    }
+
+    Some(duration * sign)
+}
+
+#[cfg(test)]
+mod test {
+    fn filter(comp_filter: &str) -> crate::xml::Node {
+        crate::xml::parse(&format!(
+            r#"<c:filter xmlns:c="urn:ietf:params:xml:ns:caldav"><c:comp-filter name="VCALENDAR">{comp_filter}</c:comp-filter></c:filter>"#
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    fn matches(comp_filter: &str, object: &str) -> bool {
+        let filter = filter(comp_filter);
+        assert_eq!(super::validate(&filter), Ok(()));
+
+        super::matches(&filter, &crate::ical::parse(object).unwrap(), false)
+    }
+
+    fn time_range(start: &str, end: &str, object: &str) -> bool {
+        matches(
+            &format!(
+                r#"<c:comp-filter name="VEVENT"><c:time-range start="{start}" end="{end}" /></c:comp-filter>"#
+            ),
+            object,
+        )
+    }
+
+    const EVENT: &str = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+SUMMARY:Weekly meeting\r
+ATTENDEE;PARTSTAT=ACCEPTED:mailto:john@example.org\r
+DTSTART:20120101T120000Z\r
+DURATION:PT1H\r
+RRULE:FREQ=WEEKLY;UNTIL=20120301T120000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn comp_filter() {
+        assert!(matches(r#"<c:comp-filter name="VEVENT" />"#, EVENT));
+        assert!(!matches(r#"<c:comp-filter name="VTODO" />"#, EVENT));
+        assert!(matches(
+            r#"<c:comp-filter name="VTODO"><c:is-not-defined /></c:comp-filter>"#,
+            EVENT
+        ));
+    }
+
+    #[test]
+    fn prop_filter() {
+        let prop_filter = |filter: &str| {
+            matches(
+                &format!(r#"<c:comp-filter name="VEVENT">{filter}</c:comp-filter>"#),
+                EVENT,
+            )
+        };
+
+        assert!(prop_filter(
+            r#"<c:prop-filter name="SUMMARY"><c:text-match>MEETING</c:text-match></c:prop-filter>"#
+        ));
+        assert!(!prop_filter(
+            r#"<c:prop-filter name="SUMMARY"><c:text-match collation="i;octet">MEETING</c:text-match></c:prop-filter>"#
+        ));
+        assert!(!prop_filter(
+            r#"<c:prop-filter name="SUMMARY"><c:text-match negate-condition="yes">meeting</c:text-match></c:prop-filter>"#
+        ));
+        assert!(prop_filter(
+            r#"<c:prop-filter name="ATTENDEE"><c:param-filter name="PARTSTAT"><c:text-match>accepted</c:text-match></c:param-filter></c:prop-filter>"#
+        ));
+        assert!(!prop_filter(
+            r#"<c:prop-filter name="LOCATION" /><c:prop-filter name="SUMMARY" />"#
+        ));
+        assert!(matches(
+            r#"<c:comp-filter name="VEVENT" test="anyof"><c:prop-filter name="LOCATION" /><c:prop-filter name="SUMMARY" /></c:comp-filter>"#,
+            EVENT
+        ));
+
+        let anyof = filter(
+            r#"<c:comp-filter name="VEVENT" test="anyof"><c:prop-filter name="LOCATION" /><c:prop-filter name="SUMMARY" /></c:comp-filter>"#,
+        );
+        assert!(!super::matches(
+            &anyof,
+            &crate::ical::parse(EVENT).unwrap(),
+            true
+        ));
+    }
+
+    #[test]
+    fn recurrence() {
+        // Weekly on sundays from 12:00 to 13:00, the last one on 2012-02-26.
+        assert!(time_range("20120101T123000Z", "20120101T124500Z", EVENT));
+        assert!(!time_range("20111201T000000Z", "20120101T120000Z", EVENT));
+        assert!(!time_range("20120102T000000Z", "20120108T120000Z", EVENT));
+        assert!(time_range("20120108T125959Z", "20120108T130000Z", EVENT));
+        assert!(!time_range("20120108T130000Z", "20120115T120000Z", EVENT));
+        assert!(time_range("20120226T000000Z", "20120227T000000Z", EVENT));
+        assert!(!time_range("20120227T000000Z", "20120501T000000Z", EVENT));
+
+        let hourly = EVENT.replace(
+            "FREQ=WEEKLY;UNTIL=20120301T120000Z",
+            "FREQ=HOURLY;INTERVAL=5",
+        );
+        // 2027-01-01T00:00 is 131 484 hours after the start, the occurrences of that day start
+        // at 01:00, 06:00, …
+        assert!(time_range("20270101T004500Z", "20270101T011500Z", &hourly));
+        assert!(!time_range("20270101T020000Z", "20270101T060000Z", &hourly));
+
+        let monthly = EVENT
+            .replace("DTSTART:20120101T120000Z", "DTSTART:20120131T120000Z")
+            .replace("FREQ=WEEKLY;UNTIL=20120301T120000Z", "FREQ=MONTHLY;COUNT=3");
+        // On january, march and may 31st, the months without 31st are skipped.
+        assert!(!time_range(
+            "20120201T000000Z",
+            "20120301T000000Z",
+            &monthly
+        ));
+        assert!(time_range("20120331T000000Z", "20120401T000000Z", &monthly));
+        assert!(time_range("20120531T000000Z", "20120601T000000Z", &monthly));
+        assert!(!time_range(
+            "20120701T000000Z",
+            "20130101T000000Z",
+            &monthly
+        ));
+
+        let byday = EVENT.replace("UNTIL=20120301T120000Z", "BYDAY=MO");
+        assert!(time_range("20200101T000000Z", "20200101T000001Z", &byday));
+    }
+
+    #[test]
+    fn validate() {
+        let validate = |comp_filter: &str| super::validate(&filter(comp_filter));
+
+        assert_eq!(
+            validate(
+                r#"<c:comp-filter name="VEVENT"><c:is-not-defined /><c:prop-filter name="SUMMARY" /></c:comp-filter>"#
+            ),
+            Err("valid-filter")
+        );
+        assert_eq!(
+            validate(
+                r#"<c:comp-filter name="VEVENT"><c:time-range start="20120101T000000" /></c:comp-filter>"#
+            ),
+            Err("valid-filter")
+        );
+        assert_eq!(
+            validate(
+                r#"<c:comp-filter name="VEVENT"><c:prop-filter name="SUMMARY"><c:text-match collation="i;unicode-casemap">a</c:text-match></c:prop-filter></c:comp-filter>"#
+            ),
+            Err("supported-collation")
+        );
+
+        let filter = crate::xml::parse(
+            r#"<c:filter xmlns:c="urn:ietf:params:xml:ns:caldav"><c:comp-filter name="VEVENT" /></c:filter>"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(super::validate(&filter), Err("valid-filter"));
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(
+            super::parse_duration("P1DT2H"),
+            Some(chrono::TimeDelta::hours(26))
+        );
+        assert_eq!(
+            super::parse_duration("-PT15M"),
+            Some(chrono::TimeDelta::minutes(-15))
+        );
+        assert_eq!(super::parse_duration("1H"), None);
+    }
+}
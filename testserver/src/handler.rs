@@ -0,0 +1,719 @@
+use crate::store::Store;
+use crate::xml::{APPLE, CALDAV, CALENDARSERVER, DAV, Multistatus, Name, Node};
+
+/**
+ * A received HTTP request.
+ */
+#[derive(Debug, Default)]
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+
+        self
+    }
+
+    fn xml(status: u16, body: String) -> Self {
+        Self {
+            status,
+            body,
+            ..Default::default()
+        }
+        .header("Content-Type", "application/xml; charset=utf-8")
+    }
+
+    fn multistatus(multistatus: &Multistatus) -> Self {
+        Self::xml(207, multistatus.to_string())
+    }
+
+    fn precondition(status: u16, precondition: Name) -> Self {
+        Self::xml(status, crate::xml::error(&precondition))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Resource {
+    Root,
+    Principal,
+    Home,
+    Calendar(String),
+    Object(String, String),
+}
+
+impl Resource {
+    fn resolve(store: &Store, path: &str) -> Option<Self> {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments = path
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+
+        let resource = match segments.as_slice() {
+            [] => Self::Root,
+            ["principals", user] if *user == store.user => Self::Principal,
+            ["calendars", user] if *user == store.user => Self::Home,
+            ["calendars", user, calendar] if *user == store.user => {
+                Self::Calendar(calendar.to_string())
+            }
+            ["calendars", user, calendar, object] if *user == store.user => {
+                Self::Object(calendar.to_string(), object.to_string())
+            }
+            _ => return None,
+        };
+
+        Some(resource)
+    }
+
+    fn exists(&self, store: &Store) -> bool {
+        match self {
+            Self::Calendar(calendar) => store.calendars.contains_key(calendar),
+            Self::Object(calendar, object) => store
+                .calendars
+                .get(calendar)
+                .is_some_and(|x| x.objects.contains_key(object)),
+            _ => true,
+        }
+    }
+
+    fn href(&self, store: &Store) -> String {
+        let user = &store.user;
+
+        match self {
+            Self::Root => "/".to_string(),
+            Self::Principal => format!("/principals/{user}/"),
+            Self::Home => format!("/calendars/{user}/"),
+            Self::Calendar(calendar) => format!("/calendars/{user}/{calendar}/"),
+            Self::Object(calendar, object) => format!("/calendars/{user}/{calendar}/{object}"),
+        }
+    }
+
+    fn children(&self, store: &Store) -> Vec<Self> {
+        match self {
+            Self::Home => store
+                .calendars
+                .keys()
+                .map(|x| Self::Calendar(x.clone()))
+                .collect(),
+            Self::Calendar(calendar) => store
+                .calendars
+                .get(calendar)
+                .map(|x| {
+                    x.objects
+                        .keys()
+                        .map(|object| Self::Object(calendar.clone(), object.clone()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /**
+     * Live and dead properties of this resource. `calendar-data` is omitted for `DAV:allprop`.
+     */
+    fn properties(&self, store: &Store, data: Data) -> Vec<(Name, String)> {
+        let href = |x: String| format!("<d:href>{}</d:href>", crate::xml::escape(&x));
+        let text = |x: &str| crate::xml::escape(x);
+
+        let mut properties = vec![(
+            Name::new(DAV, "current-user-principal"),
+            href(Self::Principal.href(store)),
+        )];
+
+        match self {
+            Self::Root => properties.push((
+                Name::new(DAV, "resourcetype"),
+                "<d:collection />".to_string(),
+            )),
+            Self::Principal => {
+                properties.push((
+                    Name::new(DAV, "resourcetype"),
+                    "<d:principal />".to_string(),
+                ));
+                properties.push((Name::new(DAV, "displayname"), text(&store.user)));
+                properties.push((Name::new(DAV, "principal-URL"), href(self.href(store))));
+                properties.push((
+                    Name::new(CALDAV, "calendar-home-set"),
+                    href(Self::Home.href(store)),
+                ));
+            }
+            Self::Home => {
+                properties.push((
+                    Name::new(DAV, "resourcetype"),
+                    "<d:collection />".to_string(),
+                ));
+
+                if !store.timezone_service_set.is_empty() {
+                    properties.push((
+                        Name::new(CALDAV, "timezone-service-set"),
+                        store
+                            .timezone_service_set
+                            .iter()
+                            .map(|x| href(x.clone()))
+                            .collect(),
+                    ));
+                }
+            }
+            Self::Calendar(calendar) => {
+                let Some(calendar) = store.calendars.get(calendar) else {
+                    return Vec::new();
+                };
+
+                properties.push((
+                    Name::new(DAV, "resourcetype"),
+                    "<d:collection /><c:calendar />".to_string(),
+                ));
+                properties.push((Name::new(DAV, "sync-token"), text(&calendar.sync_token())));
+                properties.push((
+                    Name::new(CALENDARSERVER, "getctag"),
+                    text(&calendar.sync_token()),
+                ));
+                properties.push((
+                    Name::new(CALDAV, "supported-calendar-component-set"),
+                    calendar
+                        .components
+                        .iter()
+                        .map(|x| format!("<c:comp name=\"{}\" />", text(x)))
+                        .collect(),
+                ));
+
+                let optional = [
+                    (Name::new(DAV, "displayname"), &calendar.displayname),
+                    (
+                        Name::new(CALDAV, "calendar-description"),
+                        &calendar.description,
+                    ),
+                    (Name::new(CALDAV, "calendar-timezone"), &calendar.timezone),
+                    (
+                        Name::new(CALDAV, "calendar-timezone-id"),
+                        &calendar.timezone_id,
+                    ),
+                    (Name::new(APPLE, "calendar-color"), &calendar.color),
+                ];
+
+                for (name, value) in optional {
+                    if let Some(value) = value {
+                        properties.push((name, text(value)));
+                    }
+                }
+            }
+            Self::Object(calendar, object) => {
+                let Some(object) = store
+                    .calendars
+                    .get(calendar)
+                    .and_then(|x| x.objects.get(object))
+                else {
+                    return Vec::new();
+                };
+
+                properties.push((Name::new(DAV, "resourcetype"), String::new()));
+                properties.push((Name::new(DAV, "getetag"), text(&object.etag)));
+                properties.push((
+                    Name::new(DAV, "getcontenttype"),
+                    "text/calendar; charset=utf-8".to_string(),
+                ));
+                properties.push((
+                    Name::new(DAV, "getcontentlength"),
+                    object.data.len().to_string(),
+                ));
+
+                match data {
+                    Data::Omitted => (),
+                    Data::Full => {
+                        properties.push((Name::new(CALDAV, "calendar-data"), text(&object.data)))
+                    }
+                    Data::WithoutTimezones => properties.push((
+                        Name::new(CALDAV, "calendar-data"),
+                        text(&without_timezones(&object.data)),
+                    )),
+                }
+            }
+        }
+
+        properties
+    }
+}
+
+/**
+ * How the calendar data is returned.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Data {
+    Omitted,
+    Full,
+    /** Without the `VTIMEZONE` components, see RFC 7809. */
+    WithoutTimezones,
+}
+
+impl Data {
+    /**
+     * <https://datatracker.ietf.org/doc/html/rfc7809#section-3.2>
+     */
+    fn from(request: &Request) -> Self {
+        let no_timezone = request
+            .header("Prefer")
+            .unwrap_or_default()
+            .split(',')
+            .any(|x| x.trim().eq_ignore_ascii_case("calendar-no-timezone"));
+
+        if no_timezone {
+            Self::WithoutTimezones
+        } else {
+            Self::Full
+        }
+    }
+
+    fn applied(self, response: Response) -> Response {
+        if self == Self::WithoutTimezones {
+            response.header("Preference-Applied", "calendar-no-timezone")
+        } else {
+            response
+        }
+    }
+}
+
+/**
+ * Removes the `VTIMEZONE` components of an iCalendar object.
+ */
+fn without_timezones(data: &str) -> String {
+    let mut nested = false;
+
+    data.split_inclusive('\n')
+        .filter(|line| {
+            let line = line.trim_end();
+
+            if line.eq_ignore_ascii_case("BEGIN:VTIMEZONE") {
+                nested = true;
+            }
+
+            let keep = !nested;
+
+            if line.eq_ignore_ascii_case("END:VTIMEZONE") {
+                nested = false;
+            }
+
+            keep
+        })
+        .collect()
+}
+
+/**
+ * Properties asked by a `PROPFIND` or `REPORT` body.
+ */
+enum Props {
+    All,
+    Names,
+    Some(Vec<Name>),
+}
+
+impl Props {
+    fn from(node: Option<&Node>) -> Self {
+        let Some(node) = node else {
+            return Self::All;
+        };
+
+        if node.child(DAV, "propname").is_some() {
+            Self::Names
+        } else if let Some(prop) = node.child(DAV, "prop") {
+            Self::Some(
+                prop.children
+                    .iter()
+                    .filter_map(|x| x.name.clone())
+                    .collect(),
+            )
+        } else {
+            Self::All
+        }
+    }
+
+    fn render(
+        &self,
+        multistatus: &mut Multistatus,
+        store: &Store,
+        resource: &Resource,
+        data: Data,
+    ) {
+        let href = resource.href(store);
+
+        match self {
+            Self::All => {
+                multistatus.propstat(&href, &resource.properties(store, Data::Omitted), &[])
+            }
+            Self::Names => {
+                let names = resource
+                    .properties(store, Data::Omitted)
+                    .into_iter()
+                    .map(|(name, _)| (name, String::new()))
+                    .collect::<Vec<_>>();
+
+                multistatus.propstat(&href, &names, &[]);
+            }
+            Self::Some(names) => {
+                let properties = resource.properties(store, data);
+                let mut found = Vec::new();
+                let mut missing = Vec::new();
+
+                for name in names {
+                    match properties.iter().find(|(x, _)| x == name) {
+                        Some(property) => found.push(property.clone()),
+                        None => missing.push(name.clone()),
+                    }
+                }
+
+                multistatus.propstat(&href, &found, &missing);
+            }
+        }
+    }
+}
+
+pub(crate) fn handle(store: &mut Store, request: &Request) -> Response {
+    let Some(resource) = Resource::resolve(store, &request.path) else {
+        return Response::new(404);
+    };
+
+    let body = match crate::xml::parse(&request.body) {
+        Ok(body) => body,
+        Err(_) if request.method == "PUT" => None,
+        Err(error) => {
+            return Response {
+                status: 400,
+                body: error,
+                ..Default::default()
+            };
+        }
+    };
+
+    match request.method.as_str() {
+        "OPTIONS" => Response::new(200)
+            .header("DAV", "1, 3, calendar-access, calendar-no-timezone")
+            .header(
+                "Allow",
+                "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT, MKCALENDAR",
+            ),
+        "PROPFIND" => propfind(store, request, &resource, body.as_ref()),
+        "REPORT" => report(store, request, &resource, body.as_ref()),
+        "MKCALENDAR" => mkcalendar(store, &resource, body.as_ref()),
+        "GET" | "HEAD" => get(store, request, &resource),
+        "PUT" => put(store, request, &resource),
+        "DELETE" => delete(store, request, &resource),
+        _ => Response::new(405),
+    }
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4918#section-9.1>
+ */
+fn propfind(
+    store: &Store,
+    request: &Request,
+    resource: &Resource,
+    body: Option<&Node>,
+) -> Response {
+    if !resource.exists(store) {
+        return Response::new(404);
+    }
+
+    let props = Props::from(body);
+    let mut multistatus = Multistatus::default();
+
+    let data = Data::from(request);
+
+    props.render(&mut multistatus, store, resource, data);
+
+    if request.header("Depth") != Some("0") {
+        for child in resource.children(store) {
+            props.render(&mut multistatus, store, &child, data);
+        }
+    }
+
+    data.applied(Response::multistatus(&multistatus))
+}
+
+fn report(store: &Store, request: &Request, resource: &Resource, body: Option<&Node>) -> Response {
+    if !resource.exists(store) {
+        return Response::new(404);
+    }
+
+    let Some(body) = body else {
+        return Response::new(400);
+    };
+
+    let props = Props::from(Some(body));
+    let data = Data::from(request);
+    let mut multistatus = Multistatus::default();
+
+    if body.is(CALDAV, "calendar-query") {
+        let filter = body.child(CALDAV, "filter");
+
+        if let Some(Err(precondition)) = filter.map(crate::filter::validate) {
+            return Response::precondition(403, Name::new(CALDAV, precondition));
+        }
+
+        let objects = match resource {
+            Resource::Object(..) => vec![resource.clone()],
+            _ => resource.children(store),
+        };
+
+        for object in objects {
+            let Resource::Object(calendar, name) = &object else {
+                continue;
+            };
+            let ical = &store.calendars[calendar].objects[name].data;
+
+            let matches = match (filter, crate::ical::parse(ical)) {
+                (None, _) => true,
+                (Some(filter), Some(component)) => {
+                    crate::filter::matches(filter, &component, store.ignore_test)
+                }
+                (Some(_), None) => false,
+            };
+
+            if matches {
+                props.render(&mut multistatus, store, &object, data);
+            }
+        }
+    } else if body.is(CALDAV, "calendar-multiget") {
+        for href in body.children(DAV, "href") {
+            match Resource::resolve(store, href.text.trim()) {
+                Some(object @ Resource::Object(..)) if object.exists(store) => {
+                    props.render(&mut multistatus, store, &object, data)
+                }
+                _ => multistatus.status(href.text.trim(), 404),
+            }
+        }
+    } else if body.is(DAV, "sync-collection") {
+        let Resource::Calendar(name) = resource else {
+            return Response::precondition(403, Name::new(DAV, "supported-report"));
+        };
+        let calendar = &store.calendars[name];
+
+        let token = body
+            .child(DAV, "sync-token")
+            .map(|x| x.text.trim())
+            .unwrap_or_default();
+
+        if token.is_empty() {
+            for object in resource.children(store) {
+                props.render(&mut multistatus, store, &object, data);
+            }
+        } else {
+            let Some(sync) = calendar.parse_sync_token(token) else {
+                return Response::precondition(403, Name::new(DAV, "valid-sync-token"));
+            };
+
+            for object in calendar.changes_since(sync) {
+                let object = Resource::Object(name.clone(), object.to_string());
+
+                if object.exists(store) {
+                    props.render(&mut multistatus, store, &object, data);
+                } else {
+                    multistatus.status(&object.href(store), 404);
+                }
+            }
+        }
+
+        multistatus.sync_token(calendar.sync_token());
+    } else {
+        return Response::precondition(403, Name::new(DAV, "supported-report"));
+    }
+
+    data.applied(Response::multistatus(&multistatus))
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-5.3.1>
+ */
+fn mkcalendar(store: &mut Store, resource: &Resource, body: Option<&Node>) -> Response {
+    let Resource::Calendar(name) = resource else {
+        return Response::new(405);
+    };
+
+    if resource.exists(store) {
+        return Response::precondition(403, Name::new(DAV, "resource-must-be-null"));
+    }
+
+    let mut calendar = crate::Calendar::new(name, &["VEVENT", "VTODO"]);
+
+    if let Some(prop) = body
+        .and_then(|x| x.child(DAV, "set"))
+        .and_then(|x| x.child(DAV, "prop"))
+    {
+        let text = |namespace, local| {
+            prop.child(namespace, local)
+                .map(|x| x.text.trim().to_string())
+        };
+
+        if let Some(displayname) = text(DAV, "displayname") {
+            calendar.displayname = Some(displayname);
+        }
+        calendar.description = text(CALDAV, "calendar-description");
+        calendar.timezone = text(CALDAV, "calendar-timezone");
+        calendar.timezone_id = text(CALDAV, "calendar-timezone-id");
+        calendar.color = text(APPLE, "calendar-color");
+
+        if let Some(set) = prop.child(CALDAV, "supported-calendar-component-set") {
+            calendar.components = set
+                .children(CALDAV, "comp")
+                .filter_map(|x| x.attribute("name"))
+                .map(ToString::to_string)
+                .collect();
+        }
+    }
+
+    store.calendars.insert(name.clone(), calendar);
+
+    Response::new(201)
+}
+
+fn get(store: &Store, request: &Request, resource: &Resource) -> Response {
+    match resource {
+        Resource::Object(calendar, name) => {
+            match store
+                .calendars
+                .get(calendar)
+                .and_then(|x| x.objects.get(name))
+            {
+                Some(object) => {
+                    let data = Data::from(request);
+                    let body = match data {
+                        Data::WithoutTimezones => without_timezones(&object.data),
+                        _ => object.data.clone(),
+                    };
+
+                    data.applied(Response {
+                        status: 200,
+                        body,
+                        ..Default::default()
+                    })
+                    .header("Content-Type", "text/calendar; charset=utf-8")
+                    .header("ETag", &object.etag)
+                }
+                None => Response::new(404),
+            }
+        }
+        _ if resource.exists(store) => Response::new(405),
+        _ => Response::new(404),
+    }
+}
+
+/**
+ * Checks the `If-Match` and `If-None-Match` headers against the current `etag`.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc9110#section-13.1>
+ */
+fn precondition(request: &Request, etag: Option<&str>) -> bool {
+    let matches = |header: &str| {
+        header == "*" && etag.is_some() || header.split(',').any(|x| Some(x.trim()) == etag)
+    };
+
+    if let Some(header) = request.header("If-Match")
+        && !matches(header)
+    {
+        return false;
+    }
+
+    if let Some(header) = request.header("If-None-Match")
+        && matches(header)
+    {
+        return false;
+    }
+
+    true
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4791#section-5.3.2>
+ */
+fn put(store: &mut Store, request: &Request, resource: &Resource) -> Response {
+    let Resource::Object(calendar, name) = resource else {
+        return Response::new(405);
+    };
+
+    let Some(collection) = store.calendars.get(calendar) else {
+        return Response::new(409);
+    };
+
+    let existing = collection.objects.get(name).map(|x| x.etag.as_str());
+
+    if !precondition(request, existing) {
+        return Response::new(412);
+    }
+
+    let Some(component) = crate::ical::parse(&request.body).filter(|x| x.name == "VCALENDAR")
+    else {
+        return Response::precondition(403, Name::new(CALDAV, "valid-calendar-data"));
+    };
+
+    let supported = component
+        .components
+        .iter()
+        .filter(|x| x.name != "VTIMEZONE")
+        .all(|x| collection.components.contains(&x.name));
+
+    if !supported {
+        return Response::precondition(403, Name::new(CALDAV, "supported-calendar-component"));
+    }
+
+    let status = if existing.is_some() { 204 } else { 201 };
+    let etag = store.put(calendar, name, &request.body).unwrap_or_default();
+
+    Response::new(status).header("ETag", &etag)
+}
+
+fn delete(store: &mut Store, request: &Request, resource: &Resource) -> Response {
+    match resource {
+        Resource::Object(calendar, name) => {
+            let existing = store
+                .calendars
+                .get(calendar)
+                .and_then(|x| x.objects.get(name))
+                .map(|x| x.etag.clone());
+
+            if existing.is_none() {
+                return Response::new(404);
+            }
+
+            if !precondition(request, existing.as_deref()) {
+                return Response::new(412);
+            }
+
+            store.delete(calendar, name);
+
+            Response::new(204)
+        }
+        Resource::Calendar(calendar) => match store.calendars.remove(calendar) {
+            Some(_) => Response::new(204),
+            None => Response::new(404),
+        },
+        _ => Response::new(405),
+    }
+}
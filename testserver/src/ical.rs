@@ -0,0 +1,160 @@
+/**
+ * A calendar component, like `VCALENDAR` or `VEVENT`.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/**
+ * Parses an iCalendar object, without interpreting the values.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc5545#section-3.1>
+ */
+pub(crate) fn parse(data: &str) -> Option<Component> {
+    let mut stack: Vec<Component> = Vec::new();
+    let mut root = None;
+
+    for line in unfold(data) {
+        let property = property(&line)?;
+
+        if property.name.eq_ignore_ascii_case("BEGIN") {
+            stack.push(Component {
+                name: property.value.to_ascii_uppercase(),
+                ..Default::default()
+            });
+        } else if property.name.eq_ignore_ascii_case("END") {
+            let component = stack.pop()?;
+
+            if !component.name.eq_ignore_ascii_case(&property.value) {
+                return None;
+            }
+
+            match stack.last_mut() {
+                Some(parent) => parent.components.push(component),
+                None => root = Some(component),
+            }
+        } else {
+            stack.last_mut()?.properties.push(property);
+        }
+    }
+
+    if stack.is_empty() { root } else { None }
+}
+
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in data.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if let Some(continuation) = line.strip_prefix([' ', '\t'])
+            && let Some(last) = lines.last_mut()
+        {
+            last.push_str(continuation);
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+
+    lines
+}
+
+fn property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut value = None;
+
+    for (n, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..n]);
+                start = n + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[start..n]);
+                value = Some(&line[n + 1..]);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|x| x.split_once('='))
+        .map(|(name, value)| {
+            (
+                name.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn parse() {
+        let component = super::parse(
+            "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Weekly\r
+  meeting\r
+ATTENDEE;CN=\"Doe: John\";ROLE=CHAIR:mailto:john@example.org\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+        )
+        .unwrap();
+
+        assert_eq!(component.name, "VCALENDAR");
+
+        let event = &component.components[0];
+        assert_eq!(event.property("summary").unwrap().value, "Weekly meeting");
+
+        let attendee = event.property("ATTENDEE").unwrap();
+        assert_eq!(attendee.param("cn"), Some("Doe: John"));
+        assert_eq!(attendee.value, "mailto:john@example.org");
+
+        assert!(super::parse("BEGIN:VCALENDAR\r\nEND:VEVENT\r\n").is_none());
+    }
+}
@@ -0,0 +1,432 @@
+//! An in-memory CalDAV server for end-to-end tests.
+//!
+//! It serves one user, `johndoe` by default, with the usual layout:
+//!
+//! - `/principals/{user}/`: the principal;
+//! - `/calendars/{user}/`: the calendar home;
+//! - `/calendars/{user}/{calendar}/`: the calendars;
+//! - `/calendars/{user}/{calendar}/{object}`: the calendar objects.
+//!
+//! ```
+//! let server = kaldav_testserver::Server::start();
+//! server.add_calendar("home", kaldav_testserver::Calendar::new("Home calendar", &["VEVENT"]));
+//!
+//! let url = server.url("/calendars/johndoe/home/");
+//! ```
+
+mod filter;
+mod handler;
+mod ical;
+mod store;
+mod xml;
+
+pub use store::{Calendar, Object};
+
+use std::sync::{Arc, Mutex};
+
+pub struct Server {
+    address: std::net::SocketAddr,
+    store: Arc<Mutex<store::Store>>,
+    http: Arc<tiny_http::Server>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Server {
+    /**
+     * Starts a server for the `johndoe` user on a random port.
+     */
+    pub fn start() -> Self {
+        Self::with_user("johndoe")
+    }
+
+    pub fn with_user(user: &str) -> Self {
+        let http = Arc::new(
+            tiny_http::Server::http("127.0.0.1:0").expect("Unable to start the test server"),
+        );
+        let address = http
+            .server_addr()
+            .to_ip()
+            .expect("The test server isn’t listening on an IP address");
+        let store = Arc::new(Mutex::new(store::Store::new(user)));
+
+        let thread = {
+            let http = http.clone();
+            let store = store.clone();
+
+            std::thread::spawn(move || {
+                for request in http.incoming_requests() {
+                    serve(&store, request);
+                }
+            })
+        };
+
+        Self {
+            address,
+            store,
+            http,
+            thread: Some(thread),
+        }
+    }
+
+    /**
+     * Absolute URL of `path` on this server.
+     */
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    pub fn user(&self) -> String {
+        self.store.lock().unwrap().user.clone()
+    }
+
+    /**
+     * Creates or replaces the `name` calendar.
+     */
+    pub fn add_calendar(&self, name: &str, calendar: Calendar) {
+        self.store
+            .lock()
+            .unwrap()
+            .calendars
+            .insert(name.to_string(), calendar);
+    }
+
+    /**
+     * Advertises time zone distribution services on the calendar home, see RFC 7809.
+     */
+    pub fn set_timezone_service_set(&self, hrefs: &[&str]) {
+        self.store.lock().unwrap().timezone_service_set =
+            hrefs.iter().map(ToString::to_string).collect();
+    }
+
+    /**
+     * Ignores the `test` attribute of the filters, like servers that only implement RFC 4791:
+     * the children of a `comp-filter` or `prop-filter` must then all match, even with
+     * `test="anyof"`.
+     */
+    pub fn set_ignore_test(&self, ignore_test: bool) {
+        self.store.lock().unwrap().ignore_test = ignore_test;
+    }
+
+    pub fn calendar(&self, name: &str) -> Option<Calendar> {
+        self.store.lock().unwrap().calendars.get(name).cloned()
+    }
+
+    /**
+     * Stores an object in the `calendar` calendar, returns its etag or `None` if the calendar
+     * doesn’t exist.
+     */
+    pub fn put(&self, calendar: &str, name: &str, data: &str) -> Option<String> {
+        self.store.lock().unwrap().put(calendar, name, data)
+    }
+
+    /**
+     * Removes an object, returns it or `None` if it doesn’t exist.
+     */
+    pub fn delete(&self, calendar: &str, name: &str) -> Option<Object> {
+        self.store.lock().unwrap().delete(calendar, name)
+    }
+
+    pub fn object(&self, calendar: &str, name: &str) -> Option<Object> {
+        self.store
+            .lock()
+            .unwrap()
+            .calendars
+            .get(calendar)?
+            .objects
+            .get(name)
+            .cloned()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.http.unblock();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn serve(store: &Mutex<store::Store>, mut request: tiny_http::Request) {
+    let mut body = String::new();
+
+    if request.as_reader().read_to_string(&mut body).is_err() {
+        request.respond(tiny_http::Response::empty(400)).ok();
+        return;
+    }
+
+    let received = handler::Request {
+        method: request.method().as_str().to_string(),
+        path: request.url().to_string(),
+        headers: request
+            .headers()
+            .iter()
+            .map(|x| (x.field.to_string(), x.value.to_string()))
+            .collect(),
+        body,
+    };
+
+    let response = handler::handle(&mut store.lock().unwrap(), &received);
+
+    let mut reply =
+        tiny_http::Response::from_string(response.body).with_status_code(response.status);
+
+    for (name, value) in response.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            reply.add_header(header);
+        }
+    }
+
+    request.respond(reply).ok();
+}
+
+#[cfg(test)]
+mod test {
+    const EVENT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:kaldav\r
+BEGIN:VEVENT\r
+UID:1\r
+DTSTAMP:20120101T120000Z\r
+DTSTART:20120101T120000Z\r
+SUMMARY:Weekly meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn request(
+        server: &super::Server,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> (u16, String, Option<String>) {
+        let method = attohttpc::Method::from_bytes(method.as_bytes()).unwrap();
+        let mut request = attohttpc::RequestBuilder::new(method, server.url(path));
+
+        for (name, value) in headers {
+            request = request.header(
+                attohttpc::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                *value,
+            );
+        }
+
+        let response = request.text(body).send().unwrap();
+        let status = response.status().as_u16();
+        let etag = response
+            .headers()
+            .get("ETag")
+            .map(|x| x.to_str().unwrap().to_string());
+
+        (status, response.text().unwrap(), etag)
+    }
+
+    fn server() -> super::Server {
+        let server = super::Server::start();
+        server.add_calendar("home", super::Calendar::new("Home calendar", &["VEVENT"]));
+
+        server
+    }
+
+    #[test]
+    fn propfind() {
+        let server = server();
+
+        let (status, body, _) = request(
+            &server,
+            "PROPFIND",
+            "/calendars/johndoe/",
+            &[("Depth", "1")],
+            r#"<d:propfind xmlns:d="DAV:" xmlns:x="urn:example"><d:prop><d:displayname /><x:unknown /></d:prop></d:propfind>"#,
+        );
+
+        assert_eq!(status, 207);
+        assert!(body.contains(
+            "<d:response><d:href>/calendars/johndoe/home/</d:href><d:propstat><d:prop><d:displayname>Home calendar</d:displayname></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>"
+        ));
+        assert!(body.contains(r#"<x:unknown xmlns:x="urn:example" />"#));
+
+        let (status, ..) = request(&server, "PROPFIND", "/calendars/jane/", &[], "");
+        assert_eq!(status, 404);
+    }
+
+    #[test]
+    fn mkcalendar() {
+        let server = server();
+
+        let body = r#"<c:mkcalendar xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:set><d:prop>
+        <d:displayname>Tasks</d:displayname>
+        <c:supported-calendar-component-set><c:comp name="VTODO" /></c:supported-calendar-component-set>
+    </d:prop></d:set>
+</c:mkcalendar>"#;
+
+        let (status, ..) = request(
+            &server,
+            "MKCALENDAR",
+            "/calendars/johndoe/tasks/",
+            &[],
+            body,
+        );
+        assert_eq!(status, 201);
+
+        let calendar = server.calendar("tasks").unwrap();
+        assert_eq!(calendar.displayname.as_deref(), Some("Tasks"));
+        assert_eq!(calendar.components, ["VTODO"]);
+
+        let (status, ..) = request(
+            &server,
+            "MKCALENDAR",
+            "/calendars/johndoe/tasks/",
+            &[],
+            body,
+        );
+        assert_eq!(status, 403);
+
+        let (status, ..) = request(&server, "PUT", "/calendars/johndoe/tasks/1.ics", &[], EVENT);
+        assert_eq!(status, 403);
+    }
+
+    #[test]
+    fn etag() {
+        let server = server();
+        let path = "/calendars/johndoe/home/1.ics";
+
+        let (status, _, etag) = request(&server, "PUT", path, &[("If-None-Match", "*")], EVENT);
+        assert_eq!(status, 201);
+        let etag = etag.unwrap();
+
+        let (status, ..) = request(&server, "PUT", path, &[("If-None-Match", "*")], EVENT);
+        assert_eq!(status, 412);
+
+        let (status, _, new_etag) = request(&server, "PUT", path, &[("If-Match", &etag)], EVENT);
+        assert_eq!(status, 204);
+        assert_ne!(new_etag.as_ref(), Some(&etag));
+
+        let (status, ..) = request(&server, "DELETE", path, &[("If-Match", &etag)], "");
+        assert_eq!(status, 412);
+
+        let (status, body, get_etag) = request(&server, "GET", path, &[], "");
+        assert_eq!(status, 200);
+        assert_eq!(body, EVENT);
+        assert_eq!(get_etag, new_etag);
+
+        let (status, ..) = request(&server, "DELETE", path, &[], "");
+        assert_eq!(status, 204);
+        assert!(server.object("home", "1.ics").is_none());
+    }
+
+    #[test]
+    fn calendar_query() {
+        let server = server();
+        server.put("home", "1.ics", EVENT);
+
+        let query = |start: &str| {
+            format!(
+                r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /><c:calendar-data /></d:prop>
+    <c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"><c:time-range start="{start}" /></c:comp-filter></c:comp-filter></c:filter>
+</c:calendar-query>"#
+            )
+        };
+
+        let (status, body, _) = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query("20110101T000000Z"),
+        );
+        assert_eq!(status, 207);
+        assert!(body.contains("<d:href>/calendars/johndoe/home/1.ics</d:href>"));
+        assert!(body.contains("SUMMARY:Weekly meeting"));
+
+        let (_, body, _) = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[("Depth", "1")],
+            &query("20130101T000000Z"),
+        );
+        assert!(!body.contains("1.ics"));
+    }
+
+    #[test]
+    fn multiget() {
+        let server = server();
+        server.put("home", "1.ics", EVENT);
+
+        let (_, body, _) = request(
+            &server,
+            "REPORT",
+            "/calendars/johndoe/home/",
+            &[],
+            r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+    <d:prop><d:getetag /></d:prop>
+    <d:href>/calendars/johndoe/home/1.ics</d:href>
+    <d:href>/calendars/johndoe/home/2.ics</d:href>
+</c:calendar-multiget>"#,
+        );
+
+        assert!(body.contains("<d:getetag>&quot;1&quot;</d:getetag>"));
+        assert!(body.contains("<d:response><d:href>/calendars/johndoe/home/2.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>"));
+    }
+
+    #[test]
+    fn no_timezone() {
+        let server = server();
+        let data = EVENT.replace(
+            "BEGIN:VEVENT",
+            "BEGIN:VTIMEZONE\r\nTZID:UTC\r\nBEGIN:STANDARD\r\nDTSTART:19700101T000000\r\nTZOFFSETFROM:+0000\r\nTZOFFSETTO:+0000\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT",
+        );
+        server.put("home", "1.ics", &data);
+
+        let path = "/calendars/johndoe/home/1.ics";
+
+        let (_, body, _) = request(&server, "GET", path, &[], "");
+        assert_eq!(body, data);
+
+        let (_, body, _) = request(
+            &server,
+            "GET",
+            path,
+            &[("Prefer", "return=minimal, calendar-no-timezone")],
+            "",
+        );
+        assert_eq!(body, EVENT);
+    }
+
+    #[test]
+    fn sync_collection() {
+        let server = server();
+        server.put("home", "1.ics", EVENT);
+
+        let sync = |token: &str| {
+            request(
+                &server,
+                "REPORT",
+                "/calendars/johndoe/home/",
+                &[],
+                &format!(
+                    r#"<d:sync-collection xmlns:d="DAV:"><d:sync-token>{token}</d:sync-token><d:sync-level>1</d:sync-level><d:prop><d:getetag /></d:prop></d:sync-collection>"#
+                ),
+            )
+        };
+
+        let (_, body, _) = sync("");
+        assert!(body.contains("1.ics"));
+        let token = server.calendar("home").unwrap().sync_token();
+        assert!(body.contains(&format!("<d:sync-token>{token}</d:sync-token>")));
+
+        server.put("home", "2.ics", EVENT);
+        request(&server, "DELETE", "/calendars/johndoe/home/1.ics", &[], "");
+
+        let (_, body, _) = sync(&token);
+        assert!(body.contains("<d:response><d:href>/calendars/johndoe/home/1.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>"));
+        assert!(body.contains("<d:href>/calendars/johndoe/home/2.ics</d:href><d:propstat>"));
+
+        let (status, ..) = sync("urn:kaldav-testserver:sync:42");
+        assert_eq!(status, 403);
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+/**
+ * A calendar collection.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Calendar {
+    pub displayname: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    /** A `VCALENDAR` object containing the calendar `VTIMEZONE`. */
+    pub timezone: Option<String>,
+    /** The `TZID` of the calendar time zone, see RFC 7809. */
+    pub timezone_id: Option<String>,
+    /** Supported component types, like `VEVENT` or `VTODO`. */
+    pub components: Vec<String>,
+    /** Calendar objects by resource name. */
+    pub objects: BTreeMap<String, Object>,
+    sync: u64,
+    changes: BTreeMap<String, u64>,
+}
+
+impl Calendar {
+    pub fn new(displayname: &str, components: &[&str]) -> Self {
+        Self {
+            displayname: Some(displayname.to_string()),
+            components: components.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Current sync token, also used as `getctag`.
+     */
+    pub fn sync_token(&self) -> String {
+        format!("urn:kaldav-testserver:sync:{}", self.sync)
+    }
+
+    /**
+     * Parses a token returned by [`Self::sync_token`], fails if it was never emitted by this
+     * calendar.
+     */
+    pub(crate) fn parse_sync_token(&self, token: &str) -> Option<u64> {
+        let sync = token
+            .strip_prefix("urn:kaldav-testserver:sync:")?
+            .parse()
+            .ok()?;
+
+        (sync <= self.sync).then_some(sync)
+    }
+
+    /**
+     * Names of the objects created, modified or deleted after `sync`.
+     */
+    pub(crate) fn changes_since(&self, sync: u64) -> impl Iterator<Item = &str> {
+        self.changes
+            .iter()
+            .filter(move |(_, x)| **x > sync)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn changed(&mut self, name: &str) {
+        self.sync += 1;
+        self.changes.insert(name.to_string(), self.sync);
+    }
+}
+
+/**
+ * A calendar object resource.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    pub data: String,
+    pub etag: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct Store {
+    pub user: String,
+    pub calendars: BTreeMap<String, Calendar>,
+    /** Time zone distribution services advertised on the calendar home. */
+    pub timezone_service_set: Vec<String>,
+    /** Evaluates the filters as if the `test` attribute was always `allof`. */
+    pub ignore_test: bool,
+    etag: u64,
+}
+
+impl Store {
+    pub fn new(user: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            calendars: BTreeMap::new(),
+            timezone_service_set: Vec::new(),
+            ignore_test: false,
+            etag: 0,
+        }
+    }
+
+    /**
+     * Creates or replaces an object, returns its new etag. The calendar must exist.
+     */
+    pub fn put(&mut self, calendar: &str, name: &str, data: &str) -> Option<String> {
+        self.etag += 1;
+        let etag = format!("\"{}\"", self.etag);
+
+        let calendar = self.calendars.get_mut(calendar)?;
+        calendar.objects.insert(
+            name.to_string(),
+            Object {
+                data: data.to_string(),
+                etag: etag.clone(),
+            },
+        );
+        calendar.changed(name);
+
+        Some(etag)
+    }
+
+    pub fn delete(&mut self, calendar: &str, name: &str) -> Option<Object> {
+        let calendar = self.calendars.get_mut(calendar)?;
+        let object = calendar.objects.remove(name)?;
+        calendar.changed(name);
+
+        Some(object)
+    }
+}
@@ -0,0 +1,250 @@
+pub(crate) const DAV: &str = "DAV:";
+pub(crate) const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+pub(crate) const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+pub(crate) const APPLE: &str = "http://apple.com/ns/ical/";
+
+const PREFIXES: [(&str, &str); 4] = [
+    (DAV, "d"),
+    (CALDAV, "c"),
+    (CALENDARSERVER, "cs"),
+    (APPLE, "x1"),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Name {
+    pub namespace: String,
+    pub local: String,
+}
+
+impl Name {
+    pub fn new(namespace: &str, local: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            local: local.to_string(),
+        }
+    }
+
+    fn tag(&self) -> (String, String) {
+        match PREFIXES.iter().find(|(ns, _)| *ns == self.namespace) {
+            Some((_, prefix)) => (format!("{prefix}:{}", self.local), String::new()),
+            None => (
+                format!("x:{}", self.local),
+                format!(" xmlns:x=\"{}\"", escape(&self.namespace)),
+            ),
+        }
+    }
+
+    /**
+     * Renders this property with `value` as raw XML content.
+     */
+    pub fn element(&self, value: &str) -> String {
+        let (tag, xmlns) = self.tag();
+
+        if value.is_empty() {
+            format!("<{tag}{xmlns} />")
+        } else {
+            format!("<{tag}{xmlns}>{value}</{tag}>")
+        }
+    }
+}
+
+/**
+ * An owned copy of an XML element.
+ */
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Node {
+    pub name: Option<Name>,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn is(&self, namespace: &str, local: &str) -> bool {
+        self.name
+            .as_ref()
+            .is_some_and(|x| x.namespace == namespace && x.local == local)
+    }
+
+    pub fn child(&self, namespace: &str, local: &str) -> Option<&Node> {
+        self.children.iter().find(|x| x.is(namespace, local))
+    }
+
+    pub fn children<'a>(
+        &'a self,
+        namespace: &'a str,
+        local: &'a str,
+    ) -> impl Iterator<Item = &'a Node> + 'a {
+        self.children.iter().filter(move |x| x.is(namespace, local))
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn from_dom(element: sxd_document::dom::Element) -> Self {
+        let name = element.name();
+        let mut node = Self {
+            name: Some(Name::new(
+                name.namespace_uri().unwrap_or_default(),
+                name.local_part(),
+            )),
+            attributes: element
+                .attributes()
+                .iter()
+                .map(|x| (x.name().local_part().to_string(), x.value().to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        for child in element.children() {
+            match child {
+                sxd_document::dom::ChildOfElement::Element(element) => {
+                    node.children.push(Self::from_dom(element))
+                }
+                sxd_document::dom::ChildOfElement::Text(text) => node.text.push_str(text.text()),
+                _ => (),
+            }
+        }
+
+        node
+    }
+}
+
+/**
+ * Parses `body`, returns `None` for an empty body.
+ */
+pub(crate) fn parse(body: &str) -> Result<Option<Node>, String> {
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let package = sxd_document::parser::parse(body).map_err(|e| format!("{e:?}"))?;
+    let document = package.as_document();
+
+    let root = document
+        .root()
+        .children()
+        .into_iter()
+        .find_map(|x| x.element())
+        .ok_or_else(|| "Empty document".to_string())?;
+
+    Ok(Some(Node::from_dom(root)))
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/**
+ * <https://datatracker.ietf.org/doc/html/rfc4918#section-14.16>
+ */
+#[derive(Default)]
+pub(crate) struct Multistatus {
+    responses: Vec<String>,
+    sync_token: Option<String>,
+}
+
+impl Multistatus {
+    /**
+     * Adds the properties of `href`: `found` ones with their XML value and `missing` ones with
+     * a 404 status.
+     */
+    pub fn propstat(&mut self, href: &str, found: &[(Name, String)], missing: &[Name]) {
+        let mut propstats = String::new();
+
+        if !found.is_empty() {
+            let props = found
+                .iter()
+                .map(|(name, value)| name.element(value))
+                .collect::<String>();
+
+            propstats.push_str(&propstat(&props, 200));
+        }
+
+        if !missing.is_empty() {
+            let props = missing
+                .iter()
+                .map(|name| name.element(""))
+                .collect::<String>();
+
+            propstats.push_str(&propstat(&props, 404));
+        }
+
+        self.responses.push(format!(
+            "<d:response><d:href>{}</d:href>{propstats}</d:response>",
+            escape(href)
+        ));
+    }
+
+    pub fn status(&mut self, href: &str, status: u16) {
+        self.responses.push(format!(
+            "<d:response><d:href>{}</d:href><d:status>{}</d:status></d:response>",
+            escape(href),
+            status_line(status)
+        ));
+    }
+
+    pub fn sync_token(&mut self, token: String) {
+        self.sync_token = Some(token);
+    }
+}
+
+impl std::fmt::Display for Multistatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let xmlns = PREFIXES
+            .iter()
+            .map(|(ns, prefix)| format!(" xmlns:{prefix}=\"{ns}\""))
+            .collect::<String>();
+
+        write!(
+            f,
+            "<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n<d:multistatus{xmlns}>"
+        )?;
+
+        for response in &self.responses {
+            write!(f, "{response}")?;
+        }
+
+        if let Some(token) = &self.sync_token {
+            write!(f, "<d:sync-token>{}</d:sync-token>", escape(token))?;
+        }
+
+        write!(f, "</d:multistatus>")
+    }
+}
+
+fn propstat(props: &str, status: u16) -> String {
+    format!(
+        "<d:propstat><d:prop>{props}</d:prop><d:status>{}</d:status></d:propstat>",
+        status_line(status)
+    )
+}
+
+fn status_line(status: u16) -> String {
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "",
+    };
+
+    format!("HTTP/1.1 {status} {reason}")
+}
+
+/**
+ * A `DAV:error` body with the failed precondition.
+ *
+ * <https://datatracker.ietf.org/doc/html/rfc4918#section-16>
+ */
+pub(crate) fn error(precondition: &Name) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n<d:error xmlns:d=\"DAV:\" xmlns:c=\"{CALDAV}\">{}</d:error>",
+        precondition.element("")
+    )
+}